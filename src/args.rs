@@ -1,3 +1,9 @@
+use crate::command::bump_version::BumpPart;
+use crate::command::test::TestLog;
+use crate::config::io::FileExt;
+use crate::config::types::LanguageType;
+
+use anyhow::{anyhow, ensure, Result};
 use std::path::PathBuf;
 use structopt::{clap, StructOpt};
 use url::Url;
@@ -9,6 +15,7 @@ use url::Url;
     version = env!("CARGO_PKG_VERSION"),
 )]
 #[structopt(setting(clap::AppSettings::ColoredHelp))]
+#[allow(clippy::large_enum_variant)]
 pub enum Args {
     #[structopt(setting(clap::AppSettings::ColoredHelp))]
     /// Make a template for the gh-trs configuration file.
@@ -20,14 +27,65 @@ pub enum Args {
         #[structopt(long = "gh-token")]
         github_token: Option<String>,
 
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
         /// Path to the output file.
+        /// Use `-` to write to stdout.
         #[structopt(short, long, parse(from_os_str), default_value = "gh-trs-config.yml")]
         output: PathBuf,
 
+        /// Overwrite the output file if it already exists.
+        #[structopt(long)]
+        force: bool,
+
+        /// Format of the output (yaml or json). If not specified, it is inferred from the output file extension.
+        #[structopt(long)]
+        format: Option<FileExt>,
+
         /// Use commit_hash instead of branch in the generated GitHub raw URL.
         #[structopt(long)]
         use_commit_url: bool,
 
+        /// Language type of the workflow (CWL, WDL, NFL, or SMK).
+        /// If not specified, it is detected from the primary workflow, and it is an error if it cannot be detected.
+        #[structopt(long)]
+        language_type: Option<LanguageType>,
+
+        /// Print a JSON summary of the generated template (output, id, name, language, file_count) to stdout.
+        #[structopt(long)]
+        json: bool,
+
+        /// Instead of writing the template, check that regenerating it would not change the auto-generated fields
+        /// (`workflow.readme`, `workflow.language`, `workflow.files`) of this existing config (local file path or remote URL).
+        /// Exits with a non-zero status and prints the diff if they differ.
+        #[structopt(long)]
+        check: Option<String>,
+
+        /// Maximum depth of subdirectories to descend into when listing `workflow.files` (0 lists only the directory of the primary workflow).
+        /// Unlimited if not specified.
+        #[structopt(long)]
+        max_depth: Option<usize>,
+
+        /// Glob pattern of the files to include in `workflow.files`, relative to the directory of the primary workflow
+        /// (e.g., `**/*.cwl`, can be specified multiple times).
+        #[structopt(long)]
+        include: Vec<String>,
+
+        /// Glob pattern of the files to exclude from `workflow.files` (e.g., `**/test/**`, can be specified multiple times).
+        /// Evaluated after `--include`, and the last matching pattern wins.
+        #[structopt(long)]
+        exclude: Vec<String>,
+
+        /// For a packed CWL document, make one template per top-level `Workflow` entry of its `$graph`,
+        /// written to `<output stem>_<entry id>.<ext>`.
+        #[structopt(long, conflicts_with = "check")]
+        split_graph: bool,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
         /// Verbose mode.
         #[structopt(short, long)]
         verbose: bool,
@@ -44,6 +102,217 @@ pub enum Args {
         #[structopt(long = "gh-token")]
         github_token: Option<String>,
 
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
+        /// Strict mode. Reject unknown fields in the config, a newer schema version, and unclean workflow names.
+        #[structopt(long)]
+        strict: bool,
+
+        /// Fetch the `wf_params` and `wf_engine_params` files and check that they are valid JSON or YAML.
+        #[structopt(long)]
+        validate_params: bool,
+
+        /// Number of configs validated in parallel.
+        #[structopt(long, default_value = "4")]
+        workers: usize,
+
+        /// Show the URLs rewritten during validation (e.g., a branch resolved to a commit) at info level.
+        #[structopt(long)]
+        show_url_changes: bool,
+
+        /// Check only the schema and the contents of the configs without network access and a GitHub token.
+        /// The URLs are not pinned to commits and their reachability is not checked.
+        #[structopt(long)]
+        offline: bool,
+
+        /// Cache the default branch (for a day) and branch head (for 10 minutes) lookups in `.gh-trs-cache.json` across runs.
+        #[structopt(long)]
+        cache: bool,
+
+        /// Print a JSON report to stdout: for each config, its location, id, version, name, language, file count, warnings,
+        /// and pass/fail (with the error). The configs are validated one by one.
+        #[structopt(long)]
+        json: bool,
+
+        /// Also validate the workflow with the validator of its language (cwltool, womtool, nextflow, or snakemake),
+        /// skipped with a warning if the validator is not installed.
+        #[structopt(long, conflicts_with = "offline")]
+        deep: bool,
+
+        /// Validator command used by `--deep` for a language, instead of the default one.
+        /// The path to the primary workflow is appended (format: <language>=<command>, e.g., `WDL=java -jar womtool.jar validate`).
+        #[structopt(long = "validator", parse(try_from_str = parse_validator_map), requires = "deep")]
+        validator_map: Vec<(LanguageType, String)>,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
+        /// Verbose mode.
+        #[structopt(short, long)]
+        verbose: bool,
+    },
+
+    #[structopt(setting(clap::AppSettings::ColoredHelp))]
+    /// Rewrite the gh-trs configuration file in the canonical form.
+    Normalize {
+        /// Location of the gh-trs configuration file (local file path or remote URL).
+        #[structopt(default_value = "gh-trs-config.yml")]
+        config_location: String,
+
+        /// GitHub Personal Access Token.
+        #[structopt(long = "gh-token")]
+        github_token: Option<String>,
+
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
+        /// Path to the output file. Use `-` to write to stdout.
+        /// If not specified, the config is overwritten (required for a remote config).
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+
+        /// Format of the output (yaml or json). If not specified, it is inferred from the output file extension.
+        #[structopt(long)]
+        format: Option<FileExt>,
+
+        /// Also pin the GitHub URLs to commits as `validate` does (requires access to the GitHub API).
+        #[structopt(long)]
+        pin_urls: bool,
+
+        /// Keep the comments and the key order of the YAML config by rewriting only the changed values in place.
+        /// Falls back to the full rewrite with a warning if it is not possible.
+        #[structopt(long)]
+        keep_comments: bool,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
+        /// Verbose mode.
+        #[structopt(short, long)]
+        verbose: bool,
+    },
+
+    #[structopt(setting(clap::AppSettings::ColoredHelp))]
+    /// Bump the version of the gh-trs configuration file from the published versions.
+    BumpVersion {
+        /// Location of the gh-trs configuration file (local file path or remote URL).
+        config_location: String,
+
+        /// Part of the version to bump (major, minor, or patch).
+        part: BumpPart,
+
+        /// GitHub repository the TRS response is published to. (format: <owner>/<repo>)
+        /// If not specified, it is inferred from the primary workflow URL.
+        #[structopt(long)]
+        repo: Option<String>,
+
+        /// Base URL of the published TRS API, e.g., a custom domain of GitHub Pages.
+        /// If not specified, `https://<cname>/` with `--cname`, otherwise `https://<owner>.github.io/<name>/`.
+        #[structopt(long)]
+        base_url: Option<Url>,
+
+        /// Custom domain of GitHub Pages.
+        #[structopt(long)]
+        cname: Option<String>,
+
+        /// Path to the output file.
+        /// If not specified, the config is overwritten (required for a remote config).
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
+        /// Verbose mode.
+        #[structopt(short, long)]
+        verbose: bool,
+    },
+
+    #[structopt(setting(clap::AppSettings::ColoredHelp))]
+    /// Compare the tools and versions published at two TRS endpoints, e.g., to verify a migration.
+    Diff {
+        /// Location of the source TRS endpoint (e.g., https://suecharo.github.io/gh-trs/).
+        source_trs: Url,
+
+        /// Location of the target TRS endpoint compared with the source.
+        target_trs: Url,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
+        /// Verbose mode.
+        #[structopt(short, long)]
+        verbose: bool,
+    },
+
+    #[structopt(setting(clap::AppSettings::ColoredHelp))]
+    /// Export the workflow as a Workflow RO-Crate (the workflow files and ro-crate-metadata.json).
+    ExportRoCrate {
+        /// Location of the gh-trs configuration file (local file path or remote URL).
+        #[structopt(default_value = "gh-trs-config.yml")]
+        config_location: String,
+
+        /// GitHub Personal Access Token.
+        #[structopt(long = "gh-token")]
+        github_token: Option<String>,
+
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
+        /// Directory to write the RO-Crate to.
+        #[structopt(short, long, parse(from_os_str), default_value = "ro-crate")]
+        output_dir: PathBuf,
+
+        /// Overwrite the RO-Crate if it already exists in the directory.
+        #[structopt(long)]
+        force: bool,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
+        /// Verbose mode.
+        #[structopt(short, long)]
+        verbose: bool,
+    },
+
+    #[structopt(setting(clap::AppSettings::ColoredHelp))]
+    /// Submit the workflow to WorkflowHub as a Workflow RO-Crate.
+    PublishWorkflowhub {
+        /// Location of the gh-trs configuration file (local file path or remote URL).
+        #[structopt(default_value = "gh-trs-config.yml")]
+        config_location: String,
+
+        /// ID of the WorkflowHub team (project) to register the workflow to.
+        #[structopt(long)]
+        team: u64,
+
+        /// URL of the WorkflowHub instance.
+        #[structopt(long, default_value = "https://workflowhub.eu")]
+        api_url: Url,
+
+        /// WorkflowHub API token. If not specified, `WORKFLOWHUB_API_TOKEN` is used.
+        #[structopt(long)]
+        workflowhub_token: Option<String>,
+
+        /// GitHub Personal Access Token.
+        #[structopt(long = "gh-token")]
+        github_token: Option<String>,
+
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
+        /// Print the registered workflow (id and url) as JSON to stdout.
+        #[structopt(long)]
+        json: bool,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
         /// Verbose mode.
         #[structopt(short, long)]
         verbose: bool,
@@ -60,6 +329,10 @@ pub enum Args {
         #[structopt(long = "gh-token")]
         github_token: Option<String>,
 
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
         /// Location of the WES where the test will be run.
         /// If not specified, `sapporo-service` will be started.
         #[structopt(short, long)]
@@ -69,6 +342,78 @@ pub enum Args {
         #[structopt(short, long, default_value = "unix:///var/run/docker.sock")]
         docker_host: Url,
 
+        /// Timeout in seconds for `docker run` to start `sapporo-service`.
+        #[structopt(long, default_value = "60")]
+        docker_run_timeout: u64,
+
+        /// Timeout in seconds for the other docker commands (`ps`, `kill`).
+        #[structopt(long, default_value = "30")]
+        docker_timeout: u64,
+
+        /// Remove the finished runs under the run dir of `sapporo-service` (`SAPPORO_RUN_DIR`) when it is started and stopped.
+        /// Runs in progress are kept.
+        #[structopt(long)]
+        clean_run_dir: bool,
+
+        /// Run dir of `sapporo-service` for this invocation. It takes precedence over the `SAPPORO_RUN_DIR` env var.
+        /// If neither is set, a temp dir unique to this invocation is used and removed afterwards.
+        #[structopt(long, parse(from_os_str))]
+        run_dir: Option<PathBuf>,
+
+        /// With `--clean-run-dir`, keep the finished runs updated within this many seconds.
+        #[structopt(long, requires = "clean-run-dir")]
+        clean_run_dir_min_age: Option<u64>,
+
+        /// Host port for `sapporo-service` started by gh-trs. [default: a free port]
+        #[structopt(long)]
+        wes_port: Option<u16>,
+
+        /// Extra host directories mounted into `sapporo-service`. (format: /host/path:/container/path, can be specified multiple times)
+        #[structopt(long = "bind", parse(try_from_str = parse_bind))]
+        binds: Vec<(PathBuf, PathBuf)>,
+
+        /// Extra tags attached to the WES runs. (format: key=value, can be specified multiple times)
+        #[structopt(long = "wes-tag", parse(try_from_str = parse_key_val))]
+        wes_tags: Vec<(String, String)>,
+
+        /// Workflow engine name used for a language instead of the default one, e.g., `CWL=toil`.
+        /// (format: language=engine, can be specified multiple times)
+        #[structopt(long = "engine-map", parse(try_from_str = parse_engine_map))]
+        engine_map: Vec<(LanguageType, String)>,
+
+        /// Run only the test cases that have this profile in their `profiles` (e.g., `ci`, `nightly`).
+        /// All test cases are run if not specified.
+        #[structopt(long)]
+        profile: Option<String>,
+
+        /// Disable the colored output (also disabled by the `NO_COLOR` env var).
+        #[structopt(long)]
+        no_color: bool,
+
+        /// Stop at the first config with a failed test case, instead of testing all configs and reporting the failures together.
+        #[structopt(long)]
+        fail_fast: bool,
+
+        /// How much of each run log is printed: `full`, `tail` (the last 50 lines, or `tail:<lines>`), or `none` (only the status).
+        /// In CI, the full run log is always written to `test-logs/`.
+        #[structopt(long, default_value = "full")]
+        test_log: TestLog,
+
+        /// Strict mode. Reject unknown fields in the config, a newer schema version, and unclean workflow names.
+        #[structopt(long)]
+        strict: bool,
+
+        /// Fetch the `wf_params` and `wf_engine_params` files and check that they are valid JSON or YAML.
+        #[structopt(long)]
+        validate_params: bool,
+
+        /// Number of configs validated in parallel.
+        #[structopt(long, default_value = "4")]
+        workers: usize,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
         /// Verbose mode.
         #[structopt(short, long)]
         verbose: bool,
@@ -85,14 +430,92 @@ pub enum Args {
         #[structopt(long = "gh-token")]
         github_token: Option<String>,
 
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
         /// GitHub repository to publish the TRS response to. (format: <owner>/<repo>)
-        #[structopt(short, long, required = true)]
-        repo: String,
+        /// If not specified, it is inferred from the primary workflow URLs in the configs.
+        #[structopt(short, long)]
+        repo: Option<String>,
 
         /// GitHub branch to publish the TRS response to.
         #[structopt(short, long, default_value = "gh-pages")]
         branch: String,
 
+        /// Branch name or commit sha to create `--branch` from if it does not exist.
+        /// If not specified, an empty branch is created.
+        #[structopt(long)]
+        base_branch: Option<String>,
+
+        /// Path to a file used as the README of the empty branch created if `--branch` does not exist.
+        #[structopt(long, parse(from_os_str))]
+        initial_readme: Option<PathBuf>,
+
+        /// Base URL of the published TRS API, e.g., a custom domain of GitHub Pages.
+        /// If not specified, `https://<cname>/` with `--cname`, otherwise `https://<owner>.github.io/<name>/`.
+        #[structopt(long)]
+        base_url: Option<Url>,
+
+        /// Custom domain of GitHub Pages. A `CNAME` file with this domain is also published.
+        #[structopt(long)]
+        cname: Option<String>,
+
+        /// Also create this git tag in the repo, pointing at the commit the primary workflows are taken from.
+        /// It is an error if the tag already exists.
+        #[structopt(long)]
+        tag: Option<String>,
+
+        /// Also create a GitHub Release of `--tag` with the configs and a metadata file (`gh-trs-metadata.json`) as assets.
+        #[structopt(long, requires = "tag")]
+        release: bool,
+
+        /// Fail if the version of a config is lower than the published max version of the workflow (compared as semver).
+        #[structopt(long)]
+        require_increasing_version: bool,
+
+        /// Allow publishing a workflow with an id already used by a workflow of another name in the TRS.
+        /// By default, it is an error not to overwrite an unrelated workflow.
+        #[structopt(long)]
+        allow_id_reuse: bool,
+
+        /// Enable GitHub Pages from the branch if it is not enabled yet, and wait until the published commit is built.
+        #[structopt(long)]
+        enable_pages: bool,
+
+        /// Print curl commands for the TRS endpoints (service-info, tool, versions, descriptor, files, and tests)
+        /// of the published workflows to stdout.
+        #[structopt(long)]
+        print_curl: bool,
+
+        /// Print the summary of the publish (files, bytes, API calls, and duration) as JSON to stdout.
+        #[structopt(long)]
+        json: bool,
+
+        /// Also upload the TRS response to this bucket of an S3-compatible object storage.
+        /// The credentials are read from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, and `AWS_SESSION_TOKEN`,
+        /// and the region from `AWS_REGION` (default: us-east-1).
+        #[structopt(long)]
+        s3_bucket: Option<String>,
+
+        /// Key prefix of the objects in `--s3-bucket`.
+        #[structopt(long, requires = "s3-bucket")]
+        s3_prefix: Option<String>,
+
+        /// Endpoint of the S3-compatible object storage (e.g., MinIO or Cloudflare R2).
+        /// If not specified, the AWS S3 endpoint of the region is used.
+        #[structopt(long, requires = "s3-bucket")]
+        s3_endpoint: Option<Url>,
+
+        /// Also publish `index.html` listing the tools and versions for humans.
+        #[structopt(long)]
+        with_html: bool,
+
+        /// Omit the content of the primary workflow larger than this size (in bytes) from the descriptor, keeping only its url and checksum.
+        /// If not specified, the content is always inlined.
+        #[structopt(long)]
+        max_inline_size: Option<usize>,
+
         /// Test before publishing.
         #[structopt(long)]
         with_test: bool,
@@ -106,23 +529,273 @@ pub enum Args {
         #[structopt(short, long, default_value = "unix:///var/run/docker.sock")]
         docker_host: Url,
 
+        /// Timeout in seconds for `docker run` to start `sapporo-service`.
+        #[structopt(long, default_value = "60")]
+        docker_run_timeout: u64,
+
+        /// Timeout in seconds for the other docker commands (`ps`, `kill`).
+        #[structopt(long, default_value = "30")]
+        docker_timeout: u64,
+
+        /// Remove the finished runs under the run dir of `sapporo-service` (`SAPPORO_RUN_DIR`) when it is started and stopped.
+        /// Runs in progress are kept.
+        #[structopt(long)]
+        clean_run_dir: bool,
+
+        /// Run dir of `sapporo-service` for this invocation. It takes precedence over the `SAPPORO_RUN_DIR` env var.
+        /// If neither is set, a temp dir unique to this invocation is used and removed afterwards.
+        #[structopt(long, parse(from_os_str))]
+        run_dir: Option<PathBuf>,
+
+        /// With `--clean-run-dir`, keep the finished runs updated within this many seconds.
+        #[structopt(long, requires = "clean-run-dir")]
+        clean_run_dir_min_age: Option<u64>,
+
+        /// Host port for `sapporo-service` started by gh-trs. [default: a free port]
+        #[structopt(long)]
+        wes_port: Option<u16>,
+
+        /// Extra host directories mounted into `sapporo-service`. (format: /host/path:/container/path, can be specified multiple times)
+        #[structopt(long = "bind", parse(try_from_str = parse_bind))]
+        binds: Vec<(PathBuf, PathBuf)>,
+
+        /// Extra tags attached to the WES runs. (format: key=value, can be specified multiple times)
+        #[structopt(long = "wes-tag", parse(try_from_str = parse_key_val))]
+        wes_tags: Vec<(String, String)>,
+
+        /// Workflow engine name used for a language instead of the default one, e.g., `CWL=toil`.
+        /// (format: language=engine, can be specified multiple times)
+        #[structopt(long = "engine-map", parse(try_from_str = parse_engine_map))]
+        engine_map: Vec<(LanguageType, String)>,
+
+        /// With `--with-test`, how much of each run log is printed: `full`, `tail` (the last 50 lines, or `tail:<lines>`), or `none` (only the status).
+        /// In CI, the full run log is always written to `test-logs/`.
+        #[structopt(long, default_value = "full")]
+        test_log: TestLog,
+
         /// Recursively get the gh-trs configuration files from the TRS endpoint and publish them.
         /// This option is used to test and publish all workflows in a CI environment.
         /// If you use this option, specify the TRS endpoint for `config_locations`.
+        /// A tool URL (`<trs>/tools/<id>`) or a tool version URL (`<trs>/tools/<id>/versions/<version>`) limits the target.
         #[structopt(long)]
         from_trs: bool,
 
+        /// Resume a failed publish, skipping the configs already validated (and tested) in the previous run.
+        /// The progress is recorded in `.gh-trs-publish-state.json`.
+        #[structopt(long)]
+        resume: bool,
+
+        /// Command run with `sh -c` for each config before publishing (e.g., a lint or packaging step).
+        /// The config location is passed as the last argument and as `GH_TRS_CONFIG`.
+        /// The publish is aborted if it exits with a non-zero status.
+        #[structopt(long)]
+        pre_publish_hook: Option<String>,
+
+        /// Also publish `gh-trs-manifest.json` listing every generated path with its byte size and checksum.
+        #[structopt(long)]
+        manifest: bool,
+
+        /// Create a commit even if nothing has changed.
+        #[structopt(long)]
+        allow_empty: bool,
+
+        /// Allow publishing to a repo different from the repo of the primary workflows without a warning.
+        #[structopt(long)]
+        allow_cross_repo: bool,
+
+        /// Strict mode. Reject unknown fields in the config, a newer schema version, and unclean workflow names.
+        /// For publish, also reject publishing to a repo different from the repo of the primary workflows.
+        #[structopt(long)]
+        strict: bool,
+
+        /// Fetch the `wf_params` and `wf_engine_params` files and check that they are valid JSON or YAML.
+        #[structopt(long)]
+        validate_params: bool,
+
+        /// Number of configs validated in parallel.
+        #[structopt(long, default_value = "4")]
+        workers: usize,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
+        /// Verbose mode.
+        #[structopt(short, long)]
+        verbose: bool,
+    },
+
+    #[structopt(setting(clap::AppSettings::ColoredHelp))]
+    /// Verify that the published TRS serves the versions of the configs, waiting for the GitHub Pages build.
+    Verify {
+        /// Location of the gh-trs configuration files (local file path or remote URL).
+        #[structopt(default_value = "gh-trs-config.yml")]
+        config_locations: Vec<String>,
+
+        /// GitHub Personal Access Token.
+        #[structopt(long = "gh-token")]
+        github_token: Option<String>,
+
+        /// Path to a file containing the GitHub Personal Access Token.
+        #[structopt(long = "github-token-file", parse(from_os_str))]
+        github_token_file: Option<PathBuf>,
+
+        /// GitHub repository the TRS response is published to. (format: <owner>/<repo>)
+        /// If not specified, it is inferred from the primary workflow URLs in the configs.
+        #[structopt(short, long)]
+        repo: Option<String>,
+
+        /// GitHub branch the TRS response is published to.
+        #[structopt(short, long, default_value = "gh-pages")]
+        branch: String,
+
+        /// Base URL of the published TRS API, e.g., a custom domain of GitHub Pages.
+        /// If not specified, `https://<cname>/` with `--cname`, otherwise `https://<owner>.github.io/<name>/`.
+        #[structopt(long)]
+        base_url: Option<Url>,
+
+        /// Custom domain of GitHub Pages.
+        #[structopt(long)]
+        cname: Option<String>,
+
+        /// Timeout in seconds to wait for the GitHub Pages build of the head of the branch.
+        #[structopt(long, default_value = "600")]
+        timeout: u64,
+
+        /// Verify without waiting for the GitHub Pages build, e.g., for a TRS not served by GitHub Pages.
+        #[structopt(long)]
+        no_wait: bool,
+
+        #[structopt(flatten)]
+        http: HttpArgs,
+
         /// Verbose mode.
         #[structopt(short, long)]
         verbose: bool,
     },
 }
 
+/// Options for the HTTP clients, shared by all subcommands.
+#[derive(StructOpt, Debug, PartialEq, Clone)]
+pub struct HttpArgs {
+    /// Maximum number of simultaneous HTTP requests to GitHub, TRS and raw content hosts.
+    #[structopt(long, default_value = "4")]
+    pub concurrency: usize,
+
+    /// Proxy URL used for all HTTP requests. It takes precedence over the `HTTP_PROXY`/`HTTPS_PROXY` env vars.
+    /// Hosts listed in the `NO_PROXY` env var are not proxied.
+    #[structopt(long)]
+    pub proxy: Option<Url>,
+
+    /// Path to a CA certificate (PEM or DER) to trust, e.g., for GitHub Enterprise or WES with an internal CA.
+    #[structopt(long, parse(from_os_str))]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Disable TLS certificate verification. Insecure, use only for trusted internal hosts.
+    #[structopt(long)]
+    pub insecure_skip_tls_verify: bool,
+
+    /// Header attached to the requests for remote configs hosted outside GitHub, as `Name: value`
+    /// (e.g., `Authorization: Bearer <token>`). The configs on GitHub are fetched with the GitHub token.
+    #[structopt(long, parse(try_from_str = parse_header))]
+    pub config_auth_header: Option<(String, String)>,
+
+    /// Maximum size in bytes of a workflow file to fetch (for the descriptor and the checksums). 0 means no limit.
+    #[structopt(long, default_value = "52428800")]
+    pub max_file_size: u64,
+
+    /// User-Agent sent with all HTTP requests (GitHub, TRS, WES and raw content hosts). [default: gh-trs/<version>]
+    #[structopt(long, parse(try_from_str = parse_user_agent))]
+    pub user_agent: Option<String>,
+}
+
+impl Default for HttpArgs {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            proxy: None,
+            ca_cert: None,
+            insecure_skip_tls_verify: false,
+            config_auth_header: None,
+            max_file_size: crate::remote::DEFAULT_MAX_FILE_SIZE,
+            user_agent: None,
+        }
+    }
+}
+
+/// Parse a `key=value` pair.
+pub fn parse_key_val(s: &str) -> Result<(String, String)> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| anyhow!("Invalid key=value: no `=` found in `{}`", s))?;
+    Ok((s[..pos].to_string(), s[pos + 1..].to_string()))
+}
+
+/// Parse a `Name: value` HTTP header.
+pub fn parse_header(s: &str) -> Result<(String, String)> {
+    let pos = s
+        .find(':')
+        .ok_or_else(|| anyhow!("Invalid header: no `:` found in `{}`", s))?;
+    let name = s[..pos].trim();
+    let value = s[pos + 1..].trim();
+    ensure!(
+        !name.is_empty() && !name.contains(char::is_whitespace),
+        "Invalid header: invalid name in `{}`",
+        s
+    );
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// The User-Agent must be a non-empty valid header value.
+pub fn parse_user_agent(s: &str) -> Result<String> {
+    ensure!(
+        !s.trim().is_empty() && reqwest::header::HeaderValue::from_str(s).is_ok(),
+        "Invalid User-Agent: `{}`",
+        s
+    );
+    Ok(s.to_string())
+}
+
+/// Parse a `language=engine` pair. The language must be a known `LanguageType` (CWL, WDL, NFL, or SMK).
+pub fn parse_engine_map(s: &str) -> Result<(LanguageType, String)> {
+    let (language, engine) = parse_key_val(s)?;
+    ensure!(
+        !engine.is_empty(),
+        "Invalid language=engine: empty engine name in `{}`",
+        s
+    );
+    Ok((language.parse()?, engine))
+}
+
+/// Parse a `language=command` pair of `--validator`. The command may contain `=` and spaces.
+pub fn parse_validator_map(s: &str) -> Result<(LanguageType, String)> {
+    let (language, command) = parse_key_val(s)?;
+    ensure!(
+        !command.trim().is_empty(),
+        "Invalid language=command: empty command in `{}`",
+        s
+    );
+    Ok((language.parse()?, command))
+}
+
+/// Parse a `host:container` bind mount. Both paths must be absolute.
+pub fn parse_bind(s: &str) -> Result<(PathBuf, PathBuf)> {
+    let pos = s
+        .find(':')
+        .ok_or_else(|| anyhow!("Invalid bind: no `:` found in `{}`", s))?;
+    let host = PathBuf::from(&s[..pos]);
+    let container = PathBuf::from(&s[pos + 1..]);
+    ensure!(
+        host.is_absolute() && container.is_absolute(),
+        "Invalid bind: both paths must be absolute in `{}`",
+        s
+    );
+    Ok((host, container))
+}
+
 #[cfg(test)]
 #[cfg(not(tarpaulin_include))]
 mod tests {
     use super::*;
-    use anyhow::Result;
 
     #[test]
     fn test_make_template() -> Result<()> {
@@ -138,8 +811,19 @@ mod tests {
                     "https://github.com/suecharo/gh-trs/blob/main/path/to/workflow.yml"
                 )?,
                 github_token: None,
+                github_token_file: None,
                 output: PathBuf::from("gh-trs-config.yml"),
+                force: false,
+                format: None,
                 use_commit_url: false,
+                language_type: None,
+                json: false,
+                check: None,
+                max_depth: None,
+                include: vec![],
+                exclude: vec![],
+                split_graph: false,
+                http: HttpArgs::default(),
                 verbose: false,
             }
         );
@@ -154,6 +838,17 @@ mod tests {
             Args::Validate {
                 config_locations: vec!["gh-trs-config.yml".to_string()],
                 github_token: None,
+                github_token_file: None,
+                strict: false,
+                validate_params: false,
+                workers: 4,
+                show_url_changes: false,
+                offline: false,
+                cache: false,
+                json: false,
+                deep: false,
+                validator_map: vec![],
+                http: HttpArgs::default(),
                 verbose: false,
             }
         );
@@ -168,8 +863,26 @@ mod tests {
             Args::Test {
                 config_locations: vec!["gh-trs-config.yml".to_string()],
                 github_token: None,
+                github_token_file: None,
                 wes_location: None,
                 docker_host: Url::parse("unix:///var/run/docker.sock")?,
+                docker_run_timeout: 60,
+                docker_timeout: 30,
+                run_dir: None,
+                clean_run_dir: false,
+                clean_run_dir_min_age: None,
+                wes_port: None,
+                binds: vec![],
+                wes_tags: vec![],
+                engine_map: vec![],
+                profile: None,
+                no_color: false,
+                fail_fast: false,
+                test_log: TestLog::Full,
+                strict: false,
+                validate_params: false,
+                workers: 4,
+                http: HttpArgs::default(),
                 verbose: false,
             }
         );
@@ -189,16 +902,221 @@ mod tests {
             args,
             Args::Publish {
                 config_locations: vec!["gh-trs-config.yml".to_string()],
-                repo: "suecharo/gh-trs".to_string(),
+                repo: Some("suecharo/gh-trs".to_string()),
                 github_token: None,
+                github_token_file: None,
                 branch: "gh-pages".to_string(),
                 with_test: false,
                 wes_location: None,
                 docker_host: Url::parse("unix:///var/run/docker.sock")?,
+                docker_run_timeout: 60,
+                docker_timeout: 30,
+                run_dir: None,
+                clean_run_dir: false,
+                clean_run_dir_min_age: None,
+                wes_port: None,
+                binds: vec![],
+                wes_tags: vec![],
+                engine_map: vec![],
+                test_log: TestLog::Full,
                 from_trs: false,
+                resume: false,
+                manifest: false,
+                allow_empty: false,
+                allow_cross_repo: false,
+                base_branch: None,
+                initial_readme: None,
+                base_url: None,
+                cname: None,
+                tag: None,
+                release: false,
+                require_increasing_version: false,
+                allow_id_reuse: false,
+                enable_pages: false,
+                print_curl: false,
+                json: false,
+                s3_bucket: None,
+                s3_prefix: None,
+                s3_endpoint: None,
+                pre_publish_hook: None,
+                with_html: false,
+                max_inline_size: None,
+                strict: false,
+                validate_params: false,
+                workers: 4,
+                http: HttpArgs::default(),
                 verbose: false,
             }
         );
         Ok(())
     }
+
+    #[test]
+    fn test_verify() {
+        let args = Args::from_iter(&["gh-trs", "verify", "--timeout", "60"]);
+        assert_eq!(
+            args,
+            Args::Verify {
+                config_locations: vec!["gh-trs-config.yml".to_string()],
+                github_token: None,
+                github_token_file: None,
+                repo: None,
+                branch: "gh-pages".to_string(),
+                base_url: None,
+                cname: None,
+                timeout: 60,
+                no_wait: false,
+                http: HttpArgs::default(),
+                verbose: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let args = Args::from_iter(&["gh-trs", "normalize", "--pin-urls"]);
+        assert_eq!(
+            args,
+            Args::Normalize {
+                config_location: "gh-trs-config.yml".to_string(),
+                github_token: None,
+                github_token_file: None,
+                output: None,
+                format: None,
+                pin_urls: true,
+                keep_comments: false,
+                http: HttpArgs::default(),
+                verbose: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_wes_tag() -> Result<()> {
+        let args = Args::from_iter(&[
+            "gh-trs",
+            "test",
+            "--wes-tag",
+            "ci=true",
+            "--wes-tag",
+            "note=a=b",
+        ]);
+        match args {
+            Args::Test { wes_tags, .. } => assert_eq!(
+                wes_tags,
+                vec![
+                    ("ci".to_string(), "true".to_string()),
+                    ("note".to_string(), "a=b".to_string())
+                ]
+            ),
+            _ => panic!("Unexpected subcommand"),
+        }
+        assert!(parse_key_val("invalid").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_engine_map() -> Result<()> {
+        let args = Args::from_iter(&[
+            "gh-trs",
+            "test",
+            "--engine-map",
+            "CWL=toil",
+            "--engine-map",
+            "wdl=miniwdl",
+        ]);
+        match args {
+            Args::Test { engine_map, .. } => assert_eq!(
+                engine_map,
+                vec![
+                    (LanguageType::Cwl, "toil".to_string()),
+                    (LanguageType::Wdl, "miniwdl".to_string())
+                ]
+            ),
+            _ => panic!("Unexpected subcommand"),
+        }
+        assert!(parse_engine_map("Python=python").is_err());
+        assert!(parse_engine_map("CWL=").is_err());
+        assert!(parse_engine_map("CWL").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_map() -> Result<()> {
+        let args = Args::from_iter(&[
+            "gh-trs",
+            "validate",
+            "--deep",
+            "--validator",
+            "WDL=java -jar womtool.jar validate",
+        ]);
+        match args {
+            Args::Validate { validator_map, .. } => assert_eq!(
+                validator_map,
+                vec![(
+                    LanguageType::Wdl,
+                    "java -jar womtool.jar validate".to_string()
+                )]
+            ),
+            _ => panic!("Unexpected subcommand"),
+        }
+        assert!(parse_validator_map("CWL= ").is_err());
+        assert!(Args::from_iter_safe(&["gh-trs", "validate", "--deep", "--offline"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind() -> Result<()> {
+        let args = Args::from_iter(&["gh-trs", "test", "--bind", "/data/ref:/ref"]);
+        match args {
+            Args::Test { binds, .. } => assert_eq!(
+                binds,
+                vec![(PathBuf::from("/data/ref"), PathBuf::from("/ref"))]
+            ),
+            _ => panic!("Unexpected subcommand"),
+        }
+        assert!(parse_bind("/data/ref").is_err());
+        assert!(parse_bind("data/ref:/ref").is_err());
+        assert!(parse_bind("/data/ref:ref").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_args() -> Result<()> {
+        let args = Args::from_iter(&[
+            "gh-trs",
+            "validate",
+            "--concurrency",
+            "2",
+            "--proxy",
+            "http://proxy.example.com:8080",
+            "--config-auth-header",
+            "Authorization: Bearer abc:def",
+            "--user-agent",
+            "gh-trs/1.0.0 (admin@example.com)",
+        ]);
+        match args {
+            Args::Validate { http, .. } => assert_eq!(
+                http,
+                HttpArgs {
+                    concurrency: 2,
+                    proxy: Some(Url::parse("http://proxy.example.com:8080")?),
+                    ca_cert: None,
+                    insecure_skip_tls_verify: false,
+                    config_auth_header: Some((
+                        "Authorization".to_string(),
+                        "Bearer abc:def".to_string()
+                    )),
+                    max_file_size: 52428800,
+                    user_agent: Some("gh-trs/1.0.0 (admin@example.com)".to_string()),
+                }
+            ),
+            _ => panic!("Unexpected subcommand"),
+        }
+        assert!(parse_header("Authorization Bearer abc").is_err());
+        assert!(parse_header(": abc").is_err());
+        assert!(parse_user_agent("").is_err());
+        assert!(parse_user_agent("gh-trs\n").is_err());
+        Ok(())
+    }
 }
@@ -1,4 +1,10 @@
+pub mod bump_version;
+pub mod diff;
+pub mod export_ro_crate;
 pub mod make_template;
+pub mod normalize;
 pub mod publish;
+pub mod publish_workflowhub;
 pub mod test;
 pub mod validate;
+pub mod verify;
@@ -0,0 +1,299 @@
+use crate::command::normalize;
+use crate::command::publish;
+use crate::config;
+use crate::github_api;
+use crate::remote;
+use crate::trs;
+
+use anyhow::{anyhow, bail, Result};
+use log::{debug, info};
+use regex::Regex;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use url::Url;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BumpPart {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl FromStr for BumpPart {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "major" => Ok(BumpPart::Major),
+            "minor" => Ok(BumpPart::Minor),
+            "patch" => Ok(BumpPart::Patch),
+            _ => bail!(
+                "Invalid part: {}. It should be one of major, minor, or patch.",
+                s
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct SemVer {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl SemVer {
+    pub fn bump(&self, part: BumpPart) -> Self {
+        match part {
+            BumpPart::Major => Self {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+            BumpPart::Minor => Self {
+                major: self.major,
+                minor: self.minor + 1,
+                patch: 0,
+            },
+            BumpPart::Patch => Self {
+                major: self.major,
+                minor: self.minor,
+                patch: self.patch + 1,
+            },
+        }
+    }
+}
+
+impl FromStr for SemVer {
+    type Err = anyhow::Error;
+
+    /// Only `<major>.<minor>.<patch>` is accepted (no pre-release or build metadata).
+    fn from_str(s: &str) -> Result<Self> {
+        let re = Regex::new(r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)$")?;
+        let caps = re.captures(s.trim()).ok_or_else(|| {
+            anyhow!(
+                "Version {} is not a semantic version. It should be in the format of `<major>.<minor>.<patch>`.",
+                s
+            )
+        })?;
+        Ok(Self {
+            major: caps[1].parse()?,
+            minor: caps[2].parse()?,
+            patch: caps[3].parse()?,
+        })
+    }
+}
+
+impl fmt::Display for SemVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Bump `part` of the highest of the config version and the published versions.
+/// The published versions that are not semantic versions are ignored.
+pub fn next_version(
+    current: impl AsRef<str>,
+    published: &[String],
+    part: BumpPart,
+) -> Result<SemVer> {
+    let mut base = SemVer::from_str(current.as_ref())?;
+    for version in published {
+        match SemVer::from_str(version) {
+            Ok(version) => base = base.max(version),
+            Err(_) => debug!("Skip the published version {} as it is not semver", version),
+        }
+    }
+    Ok(base.bump(part))
+}
+
+/// Bump the version of the config and write it back (or to `output`).
+/// The published versions are read from the TRS of `repo` (inferred from the primary workflow URL if not specified),
+/// so that the new version does not go back to one already published.
+/// A YAML config is rewritten in place to keep the comments if possible (see `normalize::update_in_place`).
+/// A config with `extends` is only rewritten in place, so a version inherited from the base config is an error.
+#[cfg(not(tarpaulin_include))]
+pub fn bump_version(
+    http: &remote::Http,
+    config_loc: impl AsRef<str>,
+    part: BumpPart,
+    repo: &Option<String>,
+    base_url: &Option<Url>,
+    cname: &Option<String>,
+    output: &Option<PathBuf>,
+) -> Result<String> {
+    let output = normalize::output_path(&config_loc, output)?;
+    let content = config::io::read_config_content(http, &config_loc)?;
+    let (mut config, _) = config::io::config_from_content(http, &config_loc, &content, false)?;
+    // Fail before accessing the TRS if the config version is not semver.
+    SemVer::from_str(&config.version)?;
+
+    let (owner, name) = match repo {
+        Some(repo) => github_api::parse_repo(repo)?,
+        None => publish::infer_repo(&[config.clone()])?,
+    };
+    let trs_endpoint = trs::api::TrsEndpoint::new_from_url(&publish::resolve_trs_url(
+        &owner, &name, base_url, cname,
+    )?)?;
+    let published = published_versions(http, &trs_endpoint, config.id.to_string())?;
+    let new_version = next_version(&config.version, &published, part)?.to_string();
+    info!(
+        "Bump the version of {} from {} to {}",
+        config.id, config.version, new_version
+    );
+
+    let original = config.clone();
+    config.version = new_version.clone();
+    let file_ext = config::io::parse_file_ext(&output)?;
+    let updated = match file_ext {
+        config::io::FileExt::Yaml => {
+            normalize::update_in_place(http, &config_loc, &content, &original, &config)?
+        }
+        config::io::FileExt::Json => None,
+    };
+    match updated {
+        Some(updated) => config::io::write_content(updated, &output)?,
+        None => {
+            normalize::check_rewrite(
+                &config_loc,
+                &content,
+                &output,
+                "The version can not be bumped in place (e.g., it is set in the base config)",
+            )?;
+            config::io::write_config(&config, &output, &file_ext)?
+        }
+    }
+    Ok(new_version)
+}
+
+/// The versions of the workflow published in the TRS.
+/// Empty if the TRS or the workflow is not published yet (404).
+/// A TRS that is not reachable is an error, not to go back to a version already published.
+#[cfg(not(tarpaulin_include))]
+pub fn published_versions(
+    http: &remote::Http,
+    trs_endpoint: &trs::api::TrsEndpoint,
+    wf_id: impl AsRef<str>,
+) -> Result<Vec<String>> {
+    if !trs_endpoint.is_published(http)? {
+        info!("No TRS is published at {} yet", trs_endpoint.url);
+        return Ok(vec![]);
+    }
+    match trs_endpoint
+        .find_versions(http, wf_id.as_ref())
+        .map_err(|e| {
+            anyhow!(
+                "Failed to get the published versions of {} from {}: {}",
+                wf_id.as_ref(),
+                trs_endpoint.url,
+                e
+            )
+        })? {
+        Some(versions) => Ok(versions),
+        None => {
+            info!(
+                "The workflow {} is not published in {} yet",
+                wf_id.as_ref(),
+                trs_endpoint.url
+            );
+            Ok(vec![])
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semver_from_str() -> Result<()> {
+        assert_eq!(
+            SemVer::from_str("1.10.0")?,
+            SemVer {
+                major: 1,
+                minor: 10,
+                patch: 0
+            }
+        );
+        assert!(SemVer::from_str("1.0").is_err());
+        assert!(SemVer::from_str("v1.0.0").is_err());
+        assert!(SemVer::from_str("1.0.0-rc1").is_err());
+        assert!(SemVer::from_str("01.0.0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_version() -> Result<()> {
+        assert_eq!(
+            next_version("1.0.0", &[], BumpPart::Patch)?.to_string(),
+            "1.0.1"
+        );
+        assert_eq!(
+            next_version("1.2.3", &[], BumpPart::Minor)?.to_string(),
+            "1.3.0"
+        );
+        assert_eq!(
+            next_version("1.2.3", &[], BumpPart::Major)?.to_string(),
+            "2.0.0"
+        );
+        // Bumped from the highest published version, ignoring the non-semver ones.
+        let published = vec![
+            "1.0.0".to_string(),
+            "1.9.2".to_string(),
+            "latest".to_string(),
+        ];
+        assert_eq!(
+            next_version("1.0.0", &published, BumpPart::Patch)?.to_string(),
+            "1.9.3"
+        );
+        assert!(next_version("draft", &published, BumpPart::Patch).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bump_in_place() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config_loc = "./tests/test_config_CWL_validated.yml";
+        let content = format!(
+            "# Maintained by hand\n{}",
+            config::io::read_config_content(&http, config_loc)?
+        );
+        let (original, _) = config::io::config_from_content(&http, config_loc, &content, false)?;
+        let mut bumped = original.clone();
+        bumped.version = "1.0.1".to_string();
+        let updated = normalize::update_in_place(&http, config_loc, &content, &original, &bumped)?
+            .expect("the version is replaced in place");
+        assert!(updated.starts_with("# Maintained by hand\n"));
+        assert!(updated.contains("\nversion: 1.0.1\n"));
+
+        // The old version also used as another value must not be bumped with it.
+        let content = content.replace("id: test_1", "id: 1.0.0");
+        let (original, _) = config::io::config_from_content(&http, config_loc, &content, false)?;
+        let mut bumped = original.clone();
+        bumped.version = "1.0.1".to_string();
+        assert!(
+            normalize::update_in_place(&http, config_loc, &content, &original, &bumped)?.is_none()
+        );
+
+        // A version inherited from the base config is not in the content, so it can not be bumped in place.
+        let child_loc = "./tests/test_config_CWL_extends.yml";
+        let content = "extends: ./test_config_CWL_validated.yml\nlicense: MIT\n";
+        let (original, _) = config::io::config_from_content(&http, child_loc, content, false)?;
+        let mut bumped = original.clone();
+        bumped.version = "1.0.1".to_string();
+        assert!(
+            normalize::update_in_place(&http, child_loc, content, &original, &bumped)?.is_none()
+        );
+        assert!(normalize::check_rewrite(child_loc, content, child_loc, "hint").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bump_part_from_str() -> Result<()> {
+        assert_eq!(BumpPart::from_str("Minor")?, BumpPart::Minor);
+        assert!(BumpPart::from_str("build").is_err());
+        Ok(())
+    }
+}
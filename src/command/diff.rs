@@ -0,0 +1,241 @@
+use crate::remote;
+use crate::trs;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// The part of a published version compared between the endpoints.
+/// The URLs of the TRS responses differ by the endpoint, so only the host-independent fields are kept.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct VersionSnapshot {
+    pub descriptor_types: Vec<String>,
+    /// path (the URL of the workflow file) -> sha256 checksum
+    pub files: BTreeMap<String, Option<String>>,
+}
+
+/// tool id -> version -> snapshot
+pub type TrsSnapshot = BTreeMap<String, BTreeMap<String, VersionSnapshot>>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DiffEntry {
+    AddedTool(String),
+    RemovedTool(String),
+    AddedVersion(String, String),
+    RemovedVersion(String, String),
+    ChangedVersion(String, String, Vec<String>),
+}
+
+impl std::fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffEntry::AddedTool(id) => write!(f, "+ {}", id),
+            DiffEntry::RemovedTool(id) => write!(f, "- {}", id),
+            DiffEntry::AddedVersion(id, version) => write!(f, "+ {}@{}", id, version),
+            DiffEntry::RemovedVersion(id, version) => write!(f, "- {}@{}", id, version),
+            DiffEntry::ChangedVersion(id, version, reasons) => {
+                write!(f, "~ {}@{}: {}", id, version, reasons.join(", "))
+            }
+        }
+    }
+}
+
+/// Compare the tools and versions published at the two TRS endpoints.
+/// The entries are relative to `source_trs`, i.e., `AddedTool` is a tool only in `target_trs`.
+#[cfg(not(tarpaulin_include))]
+pub fn diff(http: &remote::Http, source_trs: &Url, target_trs: &Url) -> Result<Vec<DiffEntry>> {
+    let source = snapshot(http, &trs::api::TrsEndpoint::new_from_url(source_trs)?)?;
+    let target = snapshot(http, &trs::api::TrsEndpoint::new_from_url(target_trs)?)?;
+    Ok(diff_snapshots(&source, &target))
+}
+
+#[cfg(not(tarpaulin_include))]
+fn snapshot(http: &remote::Http, trs_endpoint: &trs::api::TrsEndpoint) -> Result<TrsSnapshot> {
+    trs_endpoint.is_valid(http)?;
+    info!("Fetching the tools from {}", trs_endpoint.url);
+    let tools = trs::api::get_tools(http, trs_endpoint)?;
+    let versions = tools
+        .iter()
+        .flat_map(|tool| tool.versions.iter().map(move |v| (tool, v)))
+        .collect::<Vec<_>>();
+    let snapshots =
+        trs::response::map_concurrently(&versions, http.concurrency(), |(tool, version)| {
+            version_snapshot(http, trs_endpoint, tool, version)
+        });
+    let mut snapshot = TrsSnapshot::new();
+    for tool in &tools {
+        snapshot.entry(tool.id.to_string()).or_default();
+    }
+    for ((tool, version), version_snapshot) in versions.iter().zip(snapshots) {
+        snapshot
+            .entry(tool.id.to_string())
+            .or_default()
+            .insert(version.version(), version_snapshot?);
+    }
+    Ok(snapshot)
+}
+
+#[cfg(not(tarpaulin_include))]
+fn version_snapshot(
+    http: &remote::Http,
+    trs_endpoint: &trs::api::TrsEndpoint,
+    tool: &trs::types::Tool,
+    version: &trs::types::ToolVersion,
+) -> Result<VersionSnapshot> {
+    let mut snapshot = VersionSnapshot::default();
+    for descriptor_type in version.descriptor_type.iter().flatten() {
+        let descriptor_type = serde_json::to_value(descriptor_type)?
+            .as_str()
+            .ok_or_else(|| anyhow!("Invalid descriptor type: {:?}", descriptor_type))?
+            .to_string();
+        let files = trs::api::get_tool_files(
+            http,
+            trs_endpoint,
+            tool.id.as_str(),
+            version.version(),
+            &descriptor_type,
+        )?;
+        for file in files {
+            if let Some(path) = file.path {
+                snapshot
+                    .files
+                    .insert(path.to_string(), file.checksum.map(|c| c.checksum));
+            }
+        }
+        snapshot.descriptor_types.push(descriptor_type);
+    }
+    Ok(snapshot)
+}
+
+pub fn diff_snapshots(source: &TrsSnapshot, target: &TrsSnapshot) -> Vec<DiffEntry> {
+    let mut entries = vec![];
+    for (id, source_versions) in source {
+        let target_versions = match target.get(id) {
+            Some(target_versions) => target_versions,
+            None => {
+                entries.push(DiffEntry::RemovedTool(id.clone()));
+                continue;
+            }
+        };
+        for (version, source_version) in source_versions {
+            match target_versions.get(version) {
+                Some(target_version) => {
+                    let reasons = diff_version(source_version, target_version);
+                    if !reasons.is_empty() {
+                        entries.push(DiffEntry::ChangedVersion(
+                            id.clone(),
+                            version.clone(),
+                            reasons,
+                        ));
+                    }
+                }
+                None => entries.push(DiffEntry::RemovedVersion(id.clone(), version.clone())),
+            }
+        }
+        for version in target_versions.keys() {
+            if !source_versions.contains_key(version) {
+                entries.push(DiffEntry::AddedVersion(id.clone(), version.clone()));
+            }
+        }
+    }
+    for id in target.keys() {
+        if !source.contains_key(id) {
+            entries.push(DiffEntry::AddedTool(id.clone()));
+        }
+    }
+    entries
+}
+
+fn diff_version(source: &VersionSnapshot, target: &VersionSnapshot) -> Vec<String> {
+    let mut reasons = vec![];
+    if source.descriptor_types != target.descriptor_types {
+        reasons.push(format!(
+            "descriptor type {} -> {}",
+            source.descriptor_types.join("/"),
+            target.descriptor_types.join("/")
+        ));
+    }
+    for (path, checksum) in &source.files {
+        match target.files.get(path) {
+            Some(target_checksum) if target_checksum != checksum => {
+                reasons.push(format!("checksum of {} differs", path))
+            }
+            Some(_) => {}
+            None => reasons.push(format!("file {} is removed", path)),
+        }
+    }
+    for path in target.files.keys() {
+        if !source.files.contains_key(path) {
+            reasons.push(format!("file {} is added", path));
+        }
+    }
+    reasons
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_snapshots() {
+        let version = |files: &[(&str, &str)]| VersionSnapshot {
+            descriptor_types: vec!["CWL".to_string()],
+            files: files
+                .iter()
+                .map(|(p, c)| (p.to_string(), Some(c.to_string())))
+                .collect(),
+        };
+        let snapshot = |tools: Vec<(&str, Vec<(&str, VersionSnapshot)>)>| -> TrsSnapshot {
+            tools
+                .into_iter()
+                .map(|(id, versions)| {
+                    (
+                        id.to_string(),
+                        versions
+                            .into_iter()
+                            .map(|(v, s)| (v.to_string(), s))
+                            .collect(),
+                    )
+                })
+                .collect()
+        };
+        let source = snapshot(vec![
+            (
+                "wf-a",
+                vec![
+                    ("1.0.0", version(&[("wf.cwl", "aaa")])),
+                    ("1.1.0", version(&[("wf.cwl", "bbb"), ("tool.cwl", "ccc")])),
+                ],
+            ),
+            ("wf-b", vec![("1.0.0", version(&[("wf.cwl", "ddd")]))]),
+        ]);
+        assert!(diff_snapshots(&source, &source).is_empty());
+
+        let target = snapshot(vec![
+            (
+                "wf-a",
+                vec![
+                    ("1.1.0", version(&[("wf.cwl", "eee"), ("main.cwl", "fff")])),
+                    ("1.2.0", version(&[("wf.cwl", "ggg")])),
+                ],
+            ),
+            ("wf-c", vec![]),
+        ]);
+        let entries = diff_snapshots(&source, &target);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "- wf-a@1.0.0",
+                "~ wf-a@1.1.0: file tool.cwl is removed, checksum of wf.cwl differs, file main.cwl is added",
+                "+ wf-a@1.2.0",
+                "- wf-b",
+                "+ wf-c",
+            ]
+        );
+    }
+}
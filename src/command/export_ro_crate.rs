@@ -0,0 +1,575 @@
+use crate::command::validate;
+use crate::config;
+use crate::remote;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{debug, info};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+pub const RO_CRATE_METADATA_FILE: &str = "ro-crate-metadata.json";
+const RO_CRATE_SPEC: &str = "https://w3id.org/ro/crate/1.1";
+const WORKFLOW_RO_CRATE_PROFILE: &str = "https://w3id.org/workflowhub/workflow-ro-crate/1.0";
+
+/// Export the workflow as a Workflow RO-Crate: the workflow files (laid out by their `target`s), the README,
+/// and `ro-crate-metadata.json` describing them with the authors and the license.
+/// The test files are referenced by their URLs instead of being downloaded, as they may be large.
+/// The crate is built in a staging dir first, so that a failure (e.g., of a fetch) leaves the previous RO-Crate as is.
+/// With `force`, the files of the previous RO-Crate (listed in its metadata) are then replaced,
+/// so that no stale file is left in the crate and the other files in `output_dir` are kept.
+#[cfg(not(tarpaulin_include))]
+pub fn export_ro_crate(
+    http: &remote::Http,
+    config_loc: impl AsRef<str>,
+    output_dir: impl AsRef<Path>,
+    force: bool,
+) -> Result<PathBuf> {
+    let output_dir = output_dir.as_ref();
+    let metadata_path = output_dir.join(RO_CRATE_METADATA_FILE);
+    ensure!(
+        force || !metadata_path.exists(),
+        "The RO-Crate {} already exists. Use --force to overwrite it.",
+        metadata_path.display()
+    );
+    let mut config = config::io::read_config(http, &config_loc, false)?;
+    validate::validate_workflow_offline(&mut config)?;
+    // Built before writing any file, as it fails without the license.
+    let metadata = ro_crate_metadata(&config, &Utc::now())?;
+
+    // In `output_dir`, so that the files are moved into place without copying them across file systems.
+    let staging_dir = output_dir.join(format!(".gh-trs-ro-crate-{}", uuid::Uuid::new_v4()));
+    let result = write_crate(http, &config, &metadata, &staging_dir).and_then(|paths| {
+        if metadata_path.exists() {
+            info!(
+                "Replacing the previous RO-Crate in {}",
+                output_dir.display()
+            );
+            remove_crate_files(output_dir)?;
+        }
+        for path in &paths {
+            let dest = output_dir.join(path);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(staging_dir.join(path), &dest)
+                .with_context(|| format!("Failed to write {}", dest.display()))?;
+        }
+        Ok(())
+    });
+    if let Err(e) = fs::remove_dir_all(&staging_dir) {
+        debug!("Failed to remove {}: {}", staging_dir.display(), e);
+    }
+    result?;
+    Ok(metadata_path)
+}
+
+/// Write the files of the crate into `dir` and return their paths relative to `dir`.
+#[cfg(not(tarpaulin_include))]
+fn write_crate(
+    http: &remote::Http,
+    config: &config::types::Config,
+    metadata: &Value,
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for file in &config.workflow.files {
+        let target = crate_path(&file.target, &file.url)?;
+        info!("Fetching {} to {}", file.url, target.display());
+        write_file(dir, &target, &remote::fetch_raw_bytes(http, &file.url)?)?;
+        paths.push(target);
+    }
+    let readme_path = readme_path(config)?;
+    write_file(
+        dir,
+        &readme_path,
+        &remote::fetch_raw_bytes(http, &config.workflow.readme)?,
+    )?;
+    paths.push(readme_path);
+    write_file(
+        dir,
+        RO_CRATE_METADATA_FILE,
+        serde_json::to_string_pretty(metadata)?.as_bytes(),
+    )?;
+    paths.push(PathBuf::from(RO_CRATE_METADATA_FILE));
+    Ok(paths)
+}
+
+/// Remove the files of the RO-Crate in `dir` listed in its metadata, and then the directories left empty by them.
+fn remove_crate_files(dir: &Path) -> Result<()> {
+    let metadata_path = dir.join(RO_CRATE_METADATA_FILE);
+    let metadata: Value = serde_json::from_str(&fs::read_to_string(&metadata_path)?)
+        .with_context(|| format!("Failed to parse {}", metadata_path.display()))?;
+    for path in crate_files(&metadata) {
+        let path = dir.join(path);
+        if !path.is_file() {
+            continue;
+        }
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        let mut parent = path.parent();
+        // Only succeeds while the directory is empty.
+        while let Some(p) = parent.filter(|p| *p != dir && fs::remove_dir(p).is_ok()) {
+            parent = p.parent();
+        }
+    }
+    Ok(())
+}
+
+/// The files of a crate listed in its metadata, i.e., the entities identified by a relative path (see `path_id`).
+/// The URLs (e.g., the test files) and the root dataset (`./`) are not files in the crate.
+pub fn crate_files(metadata: &Value) -> Vec<PathBuf> {
+    metadata["@graph"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entity| entity["@id"].as_str())
+        .filter(|id| Url::parse(id).is_err() && !id.starts_with('#'))
+        // `path_id` encodes `+` and `&`, so the id is decoded as a whole.
+        .filter_map(|id| url::form_urlencoded::parse(id.as_bytes()).next())
+        .map(|(path, _)| PathBuf::from(path.as_ref()))
+        .filter(|path| {
+            path.components().next().is_some()
+                && path.components().all(|c| matches!(c, Component::Normal(_)))
+        })
+        .collect()
+}
+
+/// Zip the crate directory (e.g., to submit it to WorkflowHub) with the entries deflated.
+/// The entries are sorted by path and have a fixed modification time so that the archive is reproducible.
+pub fn zip_crate(dir: impl AsRef<Path>) -> Result<Vec<u8>> {
+    let mut paths = Vec::new();
+    list_files(dir.as_ref(), Path::new(""), &mut paths)?;
+    paths.sort();
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for path in &paths {
+        let content = fs::read(dir.as_ref().join(path))?;
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .last_modified_time(zip::DateTime::default())
+            .large_file(content.len() as u64 >= u32::MAX as u64);
+        writer.start_file(path_name(path), options)?;
+        writer.write_all(&content)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// List the files under `dir/rel` recursively as paths relative to `dir`.
+fn list_files(dir: &Path, rel: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir.join(rel))? {
+        let entry = entry?;
+        let path = rel.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            list_files(dir, &path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The entry name in the archive, with `/` as the separator.
+fn path_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn write_file(dir: &Path, path: impl AsRef<Path>, content: &[u8]) -> Result<()> {
+    let path = dir.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The path of a file in the crate, which must be relative and stay inside the crate.
+fn crate_path(target: &Option<PathBuf>, url: &Url) -> Result<PathBuf> {
+    let target = target
+        .clone()
+        .ok_or_else(|| anyhow!("File target must be set: {}", url))?;
+    ensure!(
+        target
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir)),
+        "The target {} of {} must be a relative path without `..`",
+        target.display(),
+        url
+    );
+    Ok(target)
+}
+
+/// The README is placed at the root of the crate with the file name of its URL (e.g., `README.md`).
+fn readme_path(config: &config::types::Config) -> Result<PathBuf> {
+    let name = config
+        .workflow
+        .readme
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("README.md");
+    ensure!(
+        !config
+            .workflow
+            .files
+            .iter()
+            .any(|f| f.target.as_deref() == Some(Path::new(name))),
+        "The README {} conflicts with a workflow file of the same target",
+        name
+    );
+    Ok(PathBuf::from(name))
+}
+
+/// `ro-crate-metadata.json` of the Workflow RO-Crate profile, with the primary workflow as the `mainEntity`.
+/// The config is expected to be validated (the targets are filled in).
+/// The root dataset has the `name`, `description`, `datePublished`, and `license` required by RO-Crate 1.1,
+/// so the config must have the `license`.
+pub fn ro_crate_metadata(
+    config: &config::types::Config,
+    date_published: &DateTime<Utc>,
+) -> Result<Value> {
+    let license = config.license.as_ref().ok_or_else(|| {
+        anyhow!(
+            "The `license` of the config is required to export an RO-Crate (e.g., `license: MIT`)"
+        )
+    })?;
+    let primary_wf = config.workflow.primary_wf()?;
+    let primary_wf_path = crate_path(&primary_wf.target, &primary_wf.url)?;
+    let primary_wf_id = path_id(&primary_wf_path);
+    let readme_id = path_id(readme_path(config)?);
+
+    let people = config.authors.iter().map(person_entity).collect::<Vec<_>>();
+    let authors = people
+        .iter()
+        .map(|p| json!({ "@id": p["@id"] }))
+        .collect::<Vec<_>>();
+    let language = language_entity(&config.workflow.language)?;
+
+    let mut has_part = vec![json!({ "@id": readme_id })];
+    let mut entities = vec![
+        json!({
+            "@id": RO_CRATE_METADATA_FILE,
+            "@type": "CreativeWork",
+            "conformsTo": [
+                { "@id": RO_CRATE_SPEC },
+                { "@id": WORKFLOW_RO_CRATE_PROFILE },
+            ],
+            "about": { "@id": "./" },
+        }),
+        json!({
+            "@id": readme_id,
+            "@type": "File",
+            "name": "README",
+            "url": config.workflow.readme.as_str(),
+        }),
+    ];
+    for file in &config.workflow.files {
+        let id = path_id(crate_path(&file.target, &file.url)?);
+        has_part.push(json!({ "@id": id }));
+        if file.is_primary() {
+            entities.push(json!({
+                "@id": id,
+                "@type": ["File", "SoftwareSourceCode", "ComputationalWorkflow"],
+                "name": config.workflow.name,
+                "programmingLanguage": { "@id": language["@id"] },
+                "creator": authors,
+                "url": file.url.as_str(),
+            }));
+        } else {
+            entities.push(json!({
+                "@id": id,
+                "@type": "File",
+                "url": file.url.as_str(),
+            }));
+        }
+    }
+    // The same test file may be shared by multiple tests.
+    let mut test_urls = HashSet::new();
+    for testing in &config.workflow.testing {
+        for file in testing.files.iter().filter(|f| test_urls.insert(&f.url)) {
+            has_part.push(json!({ "@id": file.url.as_str() }));
+            entities.push(json!({
+                "@id": file.url.as_str(),
+                "@type": "File",
+                "name": file.target.as_ref().map(|t| t.to_string_lossy().to_string()),
+                "description": format!("Test data ({:?}) of the test {}", file.r#type, testing.id),
+            }));
+        }
+    }
+
+    let mut root = json!({
+        "@id": "./",
+        "@type": "Dataset",
+        "name": config.workflow.name,
+        "description": format!(
+            "{} workflow {} version {}",
+            language["name"].as_str().unwrap_or_default(),
+            config.workflow.name,
+            config.version
+        ),
+        "datePublished": date_published.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "license": { "@id": license_id(license) },
+        "identifier": config.id.to_string(),
+        "version": config.version,
+        "mainEntity": { "@id": primary_wf_id },
+        "hasPart": has_part,
+        "author": authors,
+    });
+    if let Some(zenodo) = &config.zenodo {
+        root["sameAs"] = json!({ "@id": format!("https://doi.org/{}", zenodo.doi) });
+    }
+
+    let mut graph = vec![entities.remove(0), root];
+    graph.extend(entities);
+    graph.extend(people);
+    graph.push(language);
+    Ok(json!({
+        "@context": format!("{}/context", RO_CRATE_SPEC),
+        "@graph": graph,
+    }))
+}
+
+/// A relative path as an RO-Crate data entity id (URL-encoded, with `/` as the separator).
+fn path_id(path: impl AsRef<Path>) -> String {
+    path.as_ref()
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => Some(
+                url::form_urlencoded::byte_serialize(s.to_string_lossy().as_bytes())
+                    .collect::<String>()
+                    .replace('+', "%20"),
+            ),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Identified by the ORCID if any, otherwise by the GitHub account.
+fn person_entity(author: &config::types::Author) -> Value {
+    let id = match &author.orcid {
+        Some(orcid) if orcid.starts_with("https://") => orcid.clone(),
+        Some(orcid) => format!("https://orcid.org/{}", orcid),
+        None => format!("https://github.com/{}", author.github_account),
+    };
+    let mut person = json!({
+        "@id": id,
+        "@type": "Person",
+        "name": author.name.clone().unwrap_or_else(|| author.github_account.clone()),
+    });
+    if let Some(affiliation) = &author.affiliation {
+        person["affiliation"] = json!(affiliation);
+    }
+    person
+}
+
+/// An SPDX license id is expanded to its URL, and a URL is kept as is.
+fn license_id(license: impl AsRef<str>) -> String {
+    match Url::parse(license.as_ref()) {
+        Ok(url) => url.to_string(),
+        Err(_) => format!("https://spdx.org/licenses/{}", license.as_ref()),
+    }
+}
+
+/// https://about.workflowhub.eu/Workflow-RO-Crate/#complete-workflow-example
+fn language_entity(language: &config::types::Language) -> Result<Value> {
+    let (key, name, url) = match language
+        .r#type
+        .as_ref()
+        .ok_or_else(|| anyhow!("Workflow language type must be set"))?
+    {
+        config::types::LanguageType::Cwl => (
+            "cwl",
+            "Common Workflow Language",
+            "https://www.commonwl.org/",
+        ),
+        config::types::LanguageType::Wdl => (
+            "wdl",
+            "Workflow Description Language",
+            "https://openwdl.org/",
+        ),
+        config::types::LanguageType::Nfl => ("nextflow", "Nextflow", "https://www.nextflow.io/"),
+        config::types::LanguageType::Smk => {
+            ("snakemake", "Snakemake", "https://snakemake.readthedocs.io")
+        }
+    };
+    let mut entity = json!({
+        "@id": format!("https://w3id.org/workflowhub/workflow-ro-crate#{}", key),
+        "@type": "ComputerLanguage",
+        "name": name,
+        "url": { "@id": url },
+    });
+    if let Some(version) = &language.version {
+        entity["version"] = json!(version);
+    }
+    Ok(entity)
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ro_crate_metadata() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let mut config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let date_published =
+            DateTime::parse_from_rfc3339("2022-04-01T12:00:00Z")?.with_timezone(&Utc);
+        let err = ro_crate_metadata(&config, &date_published).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`license` of the config is required"));
+
+        config.license = Some("Apache-2.0".to_string());
+        let metadata = ro_crate_metadata(&config, &date_published)?;
+        assert_eq!(
+            metadata["@context"],
+            "https://w3id.org/ro/crate/1.1/context"
+        );
+        let graph = metadata["@graph"]
+            .as_array()
+            .ok_or_else(|| anyhow!("No @graph"))?;
+        assert_eq!(graph[0]["@id"], RO_CRATE_METADATA_FILE);
+        assert_eq!(graph[1]["@id"], "./");
+        assert_eq!(graph[1]["version"], config.version.as_str());
+        // Required by RO-Crate 1.1.
+        assert_eq!(graph[1]["name"], config.workflow.name.as_str());
+        assert_eq!(
+            graph[1]["description"],
+            "Common Workflow Language workflow trimming_and_qc version 1.0.0"
+        );
+        assert_eq!(graph[1]["datePublished"], "2022-04-01T12:00:00Z");
+        assert_eq!(
+            graph[1]["license"]["@id"],
+            "https://spdx.org/licenses/Apache-2.0"
+        );
+
+        let main_entity = graph[1]["mainEntity"]["@id"].clone();
+        let primary_wf = graph
+            .iter()
+            .find(|e| e["@id"] == main_entity)
+            .ok_or_else(|| anyhow!("No main entity"))?;
+        assert_eq!(primary_wf["@type"][2], "ComputationalWorkflow");
+        assert_eq!(
+            primary_wf["programmingLanguage"]["@id"],
+            "https://w3id.org/workflowhub/workflow-ro-crate#cwl"
+        );
+        // The workflow files, the test files, and the README are the parts of the crate.
+        let test_file_count = config
+            .workflow
+            .testing
+            .iter()
+            .map(|t| t.files.len())
+            .sum::<usize>();
+        assert_eq!(
+            graph[1]["hasPart"].as_array().map(|p| p.len()),
+            Some(config.workflow.files.len() + test_file_count + 1)
+        );
+        assert!(graph.iter().any(|e| e["@type"] == "Person"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_crate_files() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let mut config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        config.license = Some("MIT".to_string());
+        let metadata = ro_crate_metadata(&config, &Utc::now())?;
+        let mut expected = config
+            .workflow
+            .files
+            .iter()
+            .map(|f| crate_path(&f.target, &f.url))
+            .collect::<Result<Vec<_>>>()?;
+        expected.push(readme_path(&config)?);
+        expected.push(PathBuf::from(RO_CRATE_METADATA_FILE));
+        let mut files = crate_files(&metadata);
+        files.sort();
+        expected.sort();
+        assert_eq!(files, expected);
+
+        let metadata = json!({"@graph": [
+            {"@id": "wf/my%20workflow.cwl"},
+            {"@id": "../outside.txt"},
+            {"@id": "/etc/passwd"},
+            {"@id": "#local"},
+        ]});
+        assert_eq!(
+            crate_files(&metadata),
+            vec![PathBuf::from("wf/my workflow.cwl")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_crate_files() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!(
+            "gh-trs-test-remove-crate-files-{}",
+            std::process::id()
+        ));
+        let metadata = json!({"@graph": [
+            {"@id": RO_CRATE_METADATA_FILE},
+            {"@id": "./"},
+            {"@id": "wf/main.cwl"},
+        ]});
+        write_file(
+            &dir,
+            RO_CRATE_METADATA_FILE,
+            metadata.to_string().as_bytes(),
+        )?;
+        write_file(&dir, "wf/main.cwl", b"hello")?;
+        write_file(&dir, "notes.txt", b"not in the crate")?;
+        remove_crate_files(&dir)?;
+        assert!(!dir.join(RO_CRATE_METADATA_FILE).exists());
+        assert!(!dir.join("wf").exists());
+        assert!(dir.join("notes.txt").exists());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_crate() -> Result<()> {
+        let dir = std::env::temp_dir().join("gh-trs-test-zip-crate");
+        write_file(&dir, RO_CRATE_METADATA_FILE, b"{}")?;
+        write_file(&dir, "wf/main.cwl", b"hello")?;
+        let archive = zip_crate(&dir)?;
+        // Reproducible, as the modification times are fixed.
+        assert_eq!(zip_crate(&dir)?, archive);
+        fs::remove_dir_all(&dir)?;
+
+        // The entries are sorted, with `/` as the separator.
+        let mut zip = zip::ZipArchive::new(Cursor::new(&archive))?;
+        assert_eq!(zip.len(), 2);
+        assert_eq!(zip.by_index(0)?.name(), RO_CRATE_METADATA_FILE);
+        let mut entry = zip.by_index(1)?;
+        assert_eq!(entry.name(), "wf/main.cwl");
+        assert_eq!(entry.crc32(), 0x3610_a686);
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut content)?;
+        assert_eq!(content, "hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_id() {
+        assert_eq!(path_id("wf/main workflow.cwl"), "wf/main%20workflow.cwl");
+        assert_eq!(path_id("./tools/trimming.cwl"), "tools/trimming.cwl");
+    }
+
+    #[test]
+    fn test_license_id() {
+        assert_eq!(license_id("MIT"), "https://spdx.org/licenses/MIT");
+        assert_eq!(
+            license_id("https://example.com/LICENSE"),
+            "https://example.com/LICENSE"
+        );
+    }
+}
@@ -3,48 +3,399 @@ use crate::env;
 use crate::github_api;
 use crate::inspect;
 use crate::raw_url;
+use crate::remote;
+use crate::warning::Warning;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use log::{debug, info};
-use std::path::Path;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use url::Url;
-use uuid::Uuid;
+
+/// Summary of the generated template, printed as JSON with `--json`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct TemplateSummary {
+    pub output: PathBuf,
+    pub id: config::types::WorkflowId,
+    pub name: String,
+    pub language: config::types::Language,
+    pub file_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_commit: Option<String>,
+    /// The soft issues found while generating the template (e.g., a language version assumed).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+impl TemplateSummary {
+    pub fn new(
+        config: &config::types::Config,
+        output: impl AsRef<Path>,
+        warnings: Vec<Warning>,
+    ) -> Self {
+        Self {
+            output: output.as_ref().to_path_buf(),
+            id: config.id.clone(),
+            name: config.workflow.name.clone(),
+            language: config.workflow.language.clone(),
+            file_count: config.workflow.files.len(),
+            source_commit: config.source_commit.clone(),
+            warnings,
+        }
+    }
+}
+
+/// Glob filter for `workflow.files` given by `--include` and `--exclude`.
+/// The patterns are evaluated in order (the includes, then the excludes) and the last matching one wins.
+/// If no pattern matches, a file is kept unless any include is given.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    rules: Vec<(Regex, bool)>,
+    keep_unmatched: bool,
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            keep_unmatched: true,
+        }
+    }
+}
+
+impl FileFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let mut rules = Vec::new();
+        for pattern in include {
+            rules.push((glob_to_regex(pattern)?, true));
+        }
+        for pattern in exclude {
+            rules.push((glob_to_regex(pattern)?, false));
+        }
+        Ok(Self {
+            rules,
+            keep_unmatched: include.is_empty(),
+        })
+    }
+
+    /// `path` is relative to the directory of the primary workflow.
+    pub fn is_kept(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref().to_string_lossy();
+        self.rules
+            .iter()
+            .rev()
+            .find(|(re, _)| re.is_match(&path))
+            .map_or(self.keep_unmatched, |(_, keep)| *keep)
+    }
+}
+
+/// `**` matches any number of directories, `*` and `?` do not match `/`.
+fn glob_to_regex(pattern: impl AsRef<str>) -> Result<Regex> {
+    let mut re = String::from("^");
+    let mut chars = pattern.as_ref().chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).map_err(|e| anyhow!("Invalid glob pattern {}: {}", pattern.as_ref(), e))
+}
 
 #[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
 pub fn make_template(
+    http: &remote::Http,
+    wf_loc: &Url,
+    gh_token: &Option<impl AsRef<str>>,
+    output: impl AsRef<Path>,
+    url_type: raw_url::UrlType,
+    language_type: &Option<config::types::LanguageType>,
+    format: &Option<config::io::FileExt>,
+    max_depth: Option<usize>,
+    filter: &FileFilter,
+    force: bool,
+) -> Result<TemplateSummary> {
+    check_no_clobber(&output, force)?;
+    let (config, warnings) = generate_template(
+        http,
+        wf_loc,
+        gh_token,
+        &url_type,
+        language_type,
+        max_depth,
+        filter,
+    )?;
+    if let Some(source_commit) = &config.source_commit {
+        info!(
+            "The workflow files are captured at commit {}",
+            source_commit
+        );
+    }
+
+    // The format is inferred from the output file extension if not specified.
+    let file_ext = match format {
+        Some(format) => format.clone(),
+        None => config::io::parse_file_ext(&output)?,
+    };
+    config::io::write_config(&config, &output, &file_ext)?;
+    Ok(TemplateSummary::new(&config, &output, warnings))
+}
+
+/// Refuse to overwrite an existing output file (e.g., a hand-edited config) unless `force`.
+/// Writing to stdout (`-`) is always allowed.
+pub fn check_no_clobber(output: impl AsRef<Path>, force: bool) -> Result<()> {
+    let output = output.as_ref();
+    ensure!(
+        force || output == Path::new("-") || !output.exists(),
+        "The output file {} already exists. Use --force to overwrite it, or --output to write to another file.",
+        output.display()
+    );
+    Ok(())
+}
+
+/// Make one template per top-level `Workflow` entry of a packed CWL `$graph` document.
+/// Each template has its own id, the name from the `label` (or `id`) of the entry, and the primary workflow URL
+/// with the entry `id` as the fragment (e.g., `packed.cwl#main`). The outputs are named by `graph_output_path`.
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn make_template_graph(
+    http: &remote::Http,
     wf_loc: &Url,
     gh_token: &Option<impl AsRef<str>>,
     output: impl AsRef<Path>,
     url_type: raw_url::UrlType,
-) -> Result<()> {
+    language_type: &Option<config::types::LanguageType>,
+    format: &Option<config::io::FileExt>,
+    max_depth: Option<usize>,
+    filter: &FileFilter,
+    force: bool,
+) -> Result<Vec<TemplateSummary>> {
+    let (config, warnings) = generate_template(
+        http,
+        wf_loc,
+        gh_token,
+        &url_type,
+        language_type,
+        max_depth,
+        filter,
+    )?;
+    ensure!(
+        config.workflow.language.r#type == Some(config::types::LanguageType::Cwl),
+        "Only a packed CWL document can be split, but the language of {} is {:?}",
+        wf_loc,
+        config.workflow.language.r#type
+    );
+    let wf_content = remote::fetch_raw_content(http, &config.workflow.primary_wf()?.url)?;
+    let entries = graph_workflows(&wf_content)?;
+    ensure!(
+        !entries.is_empty(),
+        "No top-level Workflow found in the $graph of {}",
+        wf_loc
+    );
+
+    let file_ext = match format {
+        Some(format) => format.clone(),
+        None => config::io::parse_file_ext(&output)?,
+    };
+    // Checked for all the outputs before writing any of them.
+    for entry in &entries {
+        check_no_clobber(graph_output_path(&output, &entry.id), force)?;
+    }
+    let mut summaries = Vec::new();
+    for (entry, config) in entries.iter().zip(split_graph_template(&config, &entries)?) {
+        let output = graph_output_path(&output, &entry.id);
+        info!(
+            "Writing the template of {} to {}",
+            entry.id,
+            output.display()
+        );
+        config::io::write_config(&config, &output, &file_ext)?;
+        summaries.push(TemplateSummary::new(&config, &output, warnings.clone()));
+    }
+    Ok(summaries)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GraphWorkflow {
+    /// Without the leading `#`.
+    pub id: String,
+    pub label: Option<String>,
+}
+
+/// The `Workflow` entries in the `$graph` of a packed CWL document (empty if not packed).
+pub fn graph_workflows(wf_content: impl AsRef<str>) -> Result<Vec<GraphWorkflow>> {
+    let doc: serde_yaml::Value = serde_yaml::from_str(wf_content.as_ref())?;
+    let graph = match doc.get("$graph").and_then(|g| g.as_sequence()) {
+        Some(graph) => graph,
+        None => return Ok(vec![]),
+    };
+    graph
+        .iter()
+        .filter(|entry| entry.get("class").and_then(|c| c.as_str()) == Some("Workflow"))
+        .map(|entry| {
+            let id = entry
+                .get("id")
+                .and_then(|id| id.as_str())
+                .ok_or_else(|| anyhow!("A Workflow in the $graph has no id"))?;
+            Ok(GraphWorkflow {
+                id: id.trim_start_matches('#').to_string(),
+                label: entry
+                    .get("label")
+                    .and_then(|l| l.as_str())
+                    .map(|l| l.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Copy the template for each entry with a new id, the name, and the fragment of the primary workflow URL.
+pub fn split_graph_template(
+    config: &config::types::Config,
+    entries: &[GraphWorkflow],
+) -> Result<Vec<config::types::Config>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let mut config = config.clone();
+            config.id = config::types::WorkflowId::new_v4();
+            config.workflow.name = entry.label.clone().unwrap_or_else(|| entry.id.clone());
+            let primary_wf = config
+                .workflow
+                .files
+                .iter_mut()
+                .find(|f| f.is_primary())
+                .ok_or_else(|| anyhow!("No primary workflow found"))?;
+            primary_wf.url.set_fragment(Some(&entry.id));
+            Ok(config)
+        })
+        .collect()
+}
+
+/// `gh-trs-config.yml` and `main` to `gh-trs-config_main.yml`. `-` (stdout) is kept as is.
+pub fn graph_output_path(output: impl AsRef<Path>, entry_id: impl AsRef<str>) -> PathBuf {
+    let output = output.as_ref();
+    if output == Path::new("-") {
+        return output.to_path_buf();
+    }
+    let entry_id = entry_id
+        .as_ref()
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect::<String>();
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match output.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, entry_id, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, entry_id),
+    };
+    output.with_file_name(file_name)
+}
+
+/// Regenerate the template in memory and return the differences from the existing config (empty if none).
+/// Only the auto-generated fields (`workflow.readme`, `workflow.language`, `workflow.files`) are compared.
+/// The warnings found while regenerating the template are returned along with them.
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn check_template(
+    http: &remote::Http,
+    wf_loc: &Url,
+    gh_token: &Option<impl AsRef<str>>,
+    url_type: raw_url::UrlType,
+    language_type: &Option<config::types::LanguageType>,
+    existing_loc: impl AsRef<str>,
+    max_depth: Option<usize>,
+    filter: &FileFilter,
+) -> Result<(Vec<String>, Vec<Warning>)> {
+    let existing = config::io::read_config(http, existing_loc, false)?;
+    let (generated, warnings) = generate_template(
+        http,
+        wf_loc,
+        gh_token,
+        &url_type,
+        language_type,
+        max_depth,
+        filter,
+    )?;
+    Ok((diff_templates(&existing, &generated)?, warnings))
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn generate_template(
+    http: &remote::Http,
+    wf_loc: &Url,
+    gh_token: &Option<impl AsRef<str>>,
+    url_type: &raw_url::UrlType,
+    language_type: &Option<config::types::LanguageType>,
+    max_depth: Option<usize>,
+    filter: &FileFilter,
+) -> Result<(config::types::Config, Vec<Warning>)> {
     let gh_token = env::github_token(gh_token)?;
 
     info!("Making a template from {}", wf_loc.as_str());
-    let primary_wf = raw_url::RawUrl::new(&gh_token, wf_loc, None, None)?;
+    let primary_wf = raw_url::RawUrl::new(http, &gh_token, wf_loc, None, None)?;
 
-    let id = Uuid::new_v4();
+    let id = config::types::WorkflowId::new_v4();
     let version = "1.0.0".to_string();
     let author = config::types::Author {
-        github_account: config::types::Author::new_from_api(&gh_token)?.github_account,
+        github_account: config::types::Author::new_from_api(http, &gh_token)?.github_account,
         name: None,
         affiliation: None,
         orcid: None,
     };
     let wf_name = primary_wf.file_stem()?;
     let readme = raw_url::RawUrl::new(
+        http,
         &gh_token,
-        &github_api::get_readme_url(&gh_token, &primary_wf.owner, &primary_wf.name)?,
+        &github_api::get_readme_url(http, &gh_token, &primary_wf.owner, &primary_wf.name)?,
         None,
         None,
     )?
-    .to_url(&url_type)?;
-    let language = inspect::inspect_wf_type_version(&primary_wf.to_url(&url_type)?)?;
-    let files = obtain_wf_files(&gh_token, &primary_wf, &url_type)?;
+    .to_url(url_type)?;
+    let (language, mut warnings) =
+        inspect::inspect_wf_type_version(http, &primary_wf.to_url(url_type)?, language_type)?;
+    let mut files = obtain_wf_files(http, &gh_token, &primary_wf, url_type, max_depth, filter)?;
+    if language.r#type == Some(config::types::LanguageType::Wdl) {
+        let (mut imports, import_warnings) = obtain_wdl_remote_imports(
+            http,
+            &gh_token,
+            &primary_wf.to_url(url_type)?,
+            url_type,
+            &files,
+        )?;
+        files.append(&mut imports);
+        warnings.extend(import_warnings);
+    }
     let testing = vec![config::types::Testing::default()];
 
     let config = config::types::Config {
+        schema_version: config::types::SCHEMA_VERSION,
         id,
         version,
+        source_commit: match url_type {
+            raw_url::UrlType::Commit => Some(primary_wf.commit.clone()),
+            raw_url::UrlType::Branch => None,
+        },
         license: None,
         authors: vec![author],
         zenodo: None,
@@ -57,40 +408,193 @@ pub fn make_template(
         },
     };
     debug!("template config: {:?}", config);
+    Ok((config, warnings))
+}
 
-    let file_ext = config::io::parse_file_ext(&output)?;
-    config::io::write_config(&config, &output, &file_ext)?;
-    Ok(())
+/// Diff lines of the auto-generated fields, `-` for the existing config and `+` for the regenerated one.
+/// The user-edited fields (`id`, `version`, `license`, `authors`, `zenodo`, `workflow.name`, `workflow.testing`) are ignored.
+pub fn diff_templates(
+    existing: &config::types::Config,
+    generated: &config::types::Config,
+) -> Result<Vec<String>> {
+    let mut diff = Vec::new();
+    if existing.workflow.readme != generated.workflow.readme {
+        diff.push(format!("- workflow.readme: {}", existing.workflow.readme));
+        diff.push(format!("+ workflow.readme: {}", generated.workflow.readme));
+    }
+    if existing.workflow.language != generated.workflow.language {
+        diff.push(format!(
+            "- workflow.language: {}",
+            language_line(&existing.workflow.language)
+        ));
+        diff.push(format!(
+            "+ workflow.language: {}",
+            language_line(&generated.workflow.language)
+        ));
+    }
+    let existing_files = file_lines(&existing.workflow.files)?;
+    let generated_files = file_lines(&generated.workflow.files)?;
+    for line in existing_files.difference(&generated_files) {
+        diff.push(format!("- workflow.files: {}", line));
+    }
+    for line in generated_files.difference(&existing_files) {
+        diff.push(format!("+ workflow.files: {}", line));
+    }
+    Ok(diff)
+}
+
+fn language_line(language: &config::types::Language) -> String {
+    format!(
+        "{} {}",
+        language
+            .r#type
+            .as_ref()
+            .map_or_else(|| "null".to_string(), |t| t.to_string()),
+        language.version.as_deref().unwrap_or("null")
+    )
+}
+
+/// The target is complemented as in validation, so an omitted target equal to the file name is not a difference.
+fn file_lines(files: &[config::types::File]) -> Result<BTreeSet<String>> {
+    files
+        .iter()
+        .map(|file| {
+            let mut file = file.clone();
+            file.complement_target()?;
+            Ok(format!(
+                "{} (type: {}, target: {})",
+                file.url,
+                match file.r#type {
+                    config::types::FileType::Primary => "primary",
+                    config::types::FileType::Secondary => "secondary",
+                },
+                file.target.unwrap_or_default().display()
+            ))
+        })
+        .collect()
 }
 
 pub fn obtain_wf_files(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     primary_wf: &raw_url::RawUrl,
     url_type: &raw_url::UrlType,
+    max_depth: Option<usize>,
+    filter: &FileFilter,
 ) -> Result<Vec<config::types::File>> {
     let primary_wf_url = primary_wf.to_url(url_type)?;
     let base_dir = primary_wf.base_dir()?;
     let base_url = primary_wf.to_base_url(url_type)?;
     let files = github_api::get_file_list_recursive(
+        http,
         gh_token,
         &primary_wf.owner,
         &primary_wf.name,
         &base_dir,
         &primary_wf.commit,
+        max_depth,
     )?;
-    files
-        .into_iter()
-        .map(|file| -> Result<config::types::File> {
-            let target = file.strip_prefix(&base_dir)?;
-            let url = base_url.join(target.to_str().ok_or_else(|| anyhow!("Invalid URL"))?)?;
-            let r#type = if url == primary_wf_url {
-                config::types::FileType::Primary
-            } else {
-                config::types::FileType::Secondary
+    let mut wf_files = Vec::new();
+    for file in files {
+        let target = file.strip_prefix(&base_dir)?;
+        let url = base_url.join(target.to_str().ok_or_else(|| anyhow!("Invalid URL"))?)?;
+        let r#type = if url == primary_wf_url {
+            config::types::FileType::Primary
+        } else if filter.is_kept(target) {
+            config::types::FileType::Secondary
+        } else {
+            debug!("Skip file {} (filtered out)", target.display());
+            continue;
+        };
+        wf_files.push(config::types::File::new(&url, &Some(target), r#type)?);
+    }
+    Ok(wf_files)
+}
+
+/// Maximum depth of the nested remote imports followed by `obtain_wdl_remote_imports`.
+pub const MAX_WDL_IMPORT_DEPTH: usize = 5;
+
+/// Follow the `import`s of a WDL workflow that point to GitHub URLs (possibly in other repos),
+/// and return them as secondary files with their own raw URLs (`target` is `<owner>/<name>/<path>`).
+/// The relative imports of the primary workflow are already listed by `obtain_wf_files`,
+/// while those of the remote imports are resolved against the URL of the importing file.
+/// Imports that are not GitHub URLs or not fetchable are skipped with a warning.
+pub fn obtain_wdl_remote_imports(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    primary_wf_url: &Url,
+    url_type: &raw_url::UrlType,
+    files: &[config::types::File],
+) -> Result<(Vec<config::types::File>, Vec<Warning>)> {
+    let mut seen: BTreeSet<Url> = files.iter().map(|f| f.url.clone()).collect();
+    let mut imports = Vec::new();
+    let mut warnings = Vec::new();
+    let mut queue = vec![(
+        primary_wf_url.clone(),
+        remote::fetch_raw_content(http, primary_wf_url)?,
+        0,
+    )];
+    while let Some((wf_url, wf_content, depth)) = queue.pop() {
+        for import in inspect::wdl_imports(&wf_content)? {
+            let import_url = match Url::parse(&import) {
+                Ok(import_url) => import_url,
+                // The relative imports of the primary workflow are in its base dir.
+                Err(_) if depth == 0 => continue,
+                Err(_) => wf_url.join(&import)?,
             };
-            config::types::File::new(&url, &Some(target), r#type)
-        })
-        .collect::<Result<Vec<_>>>()
+            if depth >= MAX_WDL_IMPORT_DEPTH {
+                warnings.push(Warning::emit(
+                    "workflow.files",
+                    format!(
+                        "The WDL import {} is nested deeper than {}, so it is not followed",
+                        import_url, MAX_WDL_IMPORT_DEPTH
+                    ),
+                ));
+                continue;
+            }
+            let raw_url = match raw_url::RawUrl::new(http, &gh_token, &import_url, None, None) {
+                Ok(raw_url) => raw_url,
+                Err(e) => {
+                    warnings.push(Warning::emit(
+                        "workflow.files",
+                        format!(
+                            "The WDL import {} is not a fetchable GitHub URL, so it is not added: {}",
+                            import_url, e
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            let url = raw_url.to_url(url_type)?;
+            if !seen.insert(url.clone()) {
+                continue;
+            }
+            let import_content = match remote::fetch_raw_content(http, &url) {
+                Ok(import_content) => import_content,
+                Err(e) => {
+                    warnings.push(Warning::emit(
+                        "workflow.files",
+                        format!(
+                            "Failed to fetch the WDL import {}, so it is not added: {}",
+                            url, e
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            let target = PathBuf::from(&raw_url.owner)
+                .join(&raw_url.name)
+                .join(&raw_url.file_path);
+            debug!("Add WDL import {} as {}", url, target.display());
+            imports.push(config::types::File::new(
+                &url,
+                &Some(target),
+                config::types::FileType::Secondary,
+            )?);
+            queue.push((url, import_content, depth + 1));
+        }
+    }
+    Ok((imports, warnings))
 }
 
 #[cfg(test)]
@@ -98,10 +602,160 @@ pub fn obtain_wf_files(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_graph_workflows() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let wf_content = r##"
+cwlVersion: v1.0
+$graph:
+  - class: CommandLineTool
+    id: "#fastqc"
+  - class: Workflow
+    id: "#main"
+    label: Trimming and QC
+  - class: Workflow
+    id: "#qc_only"
+"##;
+        let entries = graph_workflows(wf_content)?;
+        assert_eq!(
+            entries,
+            vec![
+                GraphWorkflow {
+                    id: "main".to_string(),
+                    label: Some("Trimming and QC".to_string())
+                },
+                GraphWorkflow {
+                    id: "qc_only".to_string(),
+                    label: None
+                },
+            ]
+        );
+        assert!(graph_workflows("cwlVersion: v1.0\nclass: Workflow\n")?.is_empty());
+
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let configs = split_graph_template(&config, &entries)?;
+        assert_eq!(configs.len(), 2);
+        assert_ne!(configs[0].id, configs[1].id);
+        assert_eq!(configs[0].workflow.name, "Trimming and QC");
+        assert_eq!(configs[1].workflow.name, "qc_only");
+        assert_eq!(
+            configs[1].workflow.primary_wf()?.url.fragment(),
+            Some("qc_only")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_no_clobber() -> Result<()> {
+        check_no_clobber("./tests/no_such_config.yml", false)?;
+        check_no_clobber("-", false)?;
+        check_no_clobber("./tests/test_config_CWL.yml", true)?;
+        let err = check_no_clobber("./tests/test_config_CWL.yml", false).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_output_path() {
+        assert_eq!(
+            graph_output_path("gh-trs-config.yml", "main"),
+            PathBuf::from("gh-trs-config_main.yml")
+        );
+        assert_eq!(
+            graph_output_path("out/config.json", "sub/wf"),
+            PathBuf::from("out/config_sub_wf.json")
+        );
+        assert_eq!(graph_output_path("-", "main"), PathBuf::from("-"));
+    }
+
+    #[test]
+    fn test_file_filter() -> Result<()> {
+        let filter = FileFilter::default();
+        assert!(filter.is_kept("tools/test/a.cwl"));
+
+        let filter = FileFilter::new(&["**/*.cwl".to_string()], &["**/test/**".to_string()])?;
+        assert!(filter.is_kept("wf.cwl"));
+        assert!(filter.is_kept("tools/a.cwl"));
+        assert!(!filter.is_kept("tools/test/a.cwl"));
+        assert!(!filter.is_kept("README.md"));
+
+        let filter = FileFilter::new(&[], &["*.md".to_string()])?;
+        assert!(!filter.is_kept("README.md"));
+        assert!(filter.is_kept("docs/README.md"));
+        assert!(filter.is_kept("wf.cwl"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_template_summary() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let summary = TemplateSummary::new(&config, "gh-trs-config.yml", vec![]);
+        let expect = serde_json::json!({
+            "output": "gh-trs-config.yml",
+            "id": "493c4948-987a-482b-993c-733bdb3d1d6c",
+            "name": "trimming_and_qc",
+            "language": {
+                "type": "CWL",
+                "version": "v1.0"
+            },
+            "file_count": 3
+        });
+        assert_eq!(serde_json::to_value(&summary)?, expect);
+
+        let mut config = config;
+        config.source_commit = Some("458d0524e667f2442a5effb730b523c1f15748d4".to_string());
+        let warnings = vec![Warning::emit(
+            "workflow.language.version",
+            "Failed to inspect the language version, so v1.0 is assumed",
+        )];
+        let summary = TemplateSummary::new(&config, "gh-trs-config.yml", warnings);
+        let summary = serde_json::to_value(&summary)?;
+        assert_eq!(
+            summary["source_commit"],
+            "458d0524e667f2442a5effb730b523c1f15748d4"
+        );
+        assert_eq!(summary["warnings"][0]["path"], "workflow.language.version");
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_templates() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let existing =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let mut generated = existing.clone();
+        generated.id = config::types::WorkflowId::new_v4();
+        generated.authors = vec![];
+        generated.workflow.testing = vec![];
+        assert!(diff_templates(&existing, &generated)?.is_empty());
+
+        let removed = generated.workflow.files.pop().unwrap();
+        generated.workflow.language.version = Some("v1.2".to_string());
+        let diff = diff_templates(&existing, &generated)?;
+        assert_eq!(
+            diff,
+            vec![
+                "- workflow.language: CWL v1.0".to_string(),
+                "+ workflow.language: CWL v1.2".to_string(),
+                format!(
+                    "- workflow.files: {} (type: secondary, target: {})",
+                    removed.url,
+                    removed.target.unwrap().display()
+                ),
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_obtain_wf_files() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let primary_wf = raw_url::RawUrl::new(
+            &http,
             &gh_token,
             &Url::parse(
                 "https://github.com/suecharo/gh-trs/blob/main/tests/CWL/wf/trimming_and_qc.cwl",
@@ -109,7 +763,14 @@ mod tests {
             None,
             None,
         )?;
-        let files = obtain_wf_files(&gh_token, &primary_wf, &raw_url::UrlType::Commit)?;
+        let files = obtain_wf_files(
+            &http,
+            &gh_token,
+            &primary_wf,
+            &raw_url::UrlType::Commit,
+            None,
+            &FileFilter::default(),
+        )?;
         assert_eq!(files.len(), 3);
         Ok(())
     }
@@ -0,0 +1,276 @@
+use crate::command::validate;
+use crate::config;
+use crate::env;
+use crate::remote;
+use crate::warning::Warning;
+
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Rewrite the config in the canonical form (field order of the schema, normalized workflow name, and filled-in targets).
+/// If `pin_urls` is true, the GitHub URLs are also pinned to commits as in `validate`, otherwise no network access is needed
+/// except for reading a remote config.
+/// If `keep_comments` is true, only the changed values are rewritten in the original YAML (see `update_in_place`).
+/// The config is written to `output`, or back to `config_loc` if it is a local file and `output` is not specified.
+/// A config with `extends` is written back only in place (see `check_rewrite`).
+#[cfg(not(tarpaulin_include))]
+pub fn normalize(
+    http: &remote::Http,
+    config_loc: impl AsRef<str>,
+    gh_token: &Option<impl AsRef<str>>,
+    output: &Option<PathBuf>,
+    format: &Option<config::io::FileExt>,
+    pin_urls: bool,
+    keep_comments: bool,
+) -> Result<Vec<Warning>> {
+    let output = output_path(&config_loc, output)?;
+    let content = config::io::read_config_content(http, &config_loc)?;
+    let (mut config, mut warnings) =
+        config::io::config_from_content(http, &config_loc, &content, false)?;
+    let original = config.clone();
+
+    warnings.extend(canonicalize(&config_loc, &mut config)?);
+    if pin_urls {
+        let gh_token = env::github_token(gh_token)?;
+        validate::validate_and_update_workflow(
+            http,
+            &gh_token,
+            &mut config,
+            &validate::Memo::default(),
+        )?;
+    }
+
+    let file_ext = match format {
+        Some(format) => format.clone(),
+        None => config::io::parse_file_ext(&output)?,
+    };
+    match (keep_comments, &file_ext) {
+        (true, config::io::FileExt::Yaml) => {
+            match update_in_place(http, &config_loc, &content, &original, &config)? {
+                Some(updated) => config::io::write_content(updated, &output)?,
+                None => {
+                    check_rewrite(
+                        &config_loc,
+                        &content,
+                        &output,
+                        "The changes can not be applied in place (e.g., the value is set in the base config)",
+                    )?;
+                    warn!(
+                        "{}: Failed to update {} in place, so it is rewritten without the comments",
+                        "Warning".yellow(),
+                        config_loc.as_ref()
+                    );
+                    config::io::write_config(&config, &output, &file_ext)?;
+                }
+            }
+        }
+        (true, config::io::FileExt::Json) => {
+            check_rewrite(
+                &config_loc,
+                &content,
+                &output,
+                "A JSON config can not be updated in place",
+            )?;
+            warn!(
+                "{}: JSON has no comments, so --keep-comments is ignored",
+                "Warning".yellow()
+            );
+            config::io::write_config(&config, &output, &file_ext)?;
+        }
+        (false, _) => {
+            check_rewrite(
+                &config_loc,
+                &content,
+                &output,
+                "Use --keep-comments to rewrite only the changed values in place",
+            )?;
+            config::io::write_config(&config, &output, &file_ext)?
+        }
+    }
+    info!("Normalized {} to {}", config_loc.as_ref(), output.display());
+    Ok(warnings)
+}
+
+/// The normalization that does not access the network: the workflow name and the missing targets.
+pub fn canonicalize(
+    config_loc: impl AsRef<str>,
+    config: &mut config::types::Config,
+) -> Result<Vec<Warning>> {
+    let mut warnings = Vec::new();
+    let wf_name = validate::normalize_wf_name(&config.workflow.name);
+    if wf_name != config.workflow.name {
+        warnings.push(
+            Warning::emit(
+                "workflow.name",
+                format!(
+                    "Workflow name is normalized from `{}` to `{}`",
+                    config.workflow.name, wf_name
+                ),
+            )
+            .with_location(config_loc.as_ref()),
+        );
+        config.workflow.name = wf_name;
+    }
+    validate::validate_workflow_offline(config)?;
+    Ok(warnings)
+}
+
+/// Rewrite only the changed values (the version, the workflow name, and the pinned URLs) in the original YAML content,
+/// keeping the comments, the key order, and `extends`. The missing targets are left implicit.
+/// `None` if the result does not read as the normalized config, e.g., a value also appears elsewhere.
+/// Also used by `bump-version` to rewrite the version.
+pub fn update_in_place(
+    http: &remote::Http,
+    config_loc: impl AsRef<str>,
+    content: impl AsRef<str>,
+    original: &config::types::Config,
+    normalized: &config::types::Config,
+) -> Result<Option<String>> {
+    let mut replacements = Vec::new();
+    if original.version != normalized.version {
+        replacements.push((original.version.clone(), normalized.version.clone()));
+    }
+    if original.workflow.name != normalized.workflow.name {
+        replacements.push((
+            original.workflow.name.clone(),
+            normalized.workflow.name.clone(),
+        ));
+    }
+    for (_, from, to) in validate::url_changes(original, normalized) {
+        replacements.push((from.to_string(), to.to_string()));
+    }
+    let updated = config::io::replace_scalars(content, &replacements)?;
+
+    let mut reread = match config::io::config_from_content(http, &config_loc, &updated, false) {
+        Ok((reread, _)) => reread,
+        Err(_) => return Ok(None),
+    };
+    validate::validate_workflow_offline(&mut reread)?;
+    // The targets are filled in on both sides, as the config may not be canonicalized (e.g., by `bump-version`).
+    let mut expected = normalized.clone();
+    validate::validate_workflow_offline(&mut expected)?;
+    match reread == expected {
+        true => Ok(Some(updated)),
+        false => Ok(None),
+    }
+}
+
+/// Writing the resolved config back over a config with `extends` would inline its base configs and drop `extends`,
+/// so it is an error with `hint`. Writing it elsewhere (`--output`) is allowed, as a self-contained config.
+pub fn check_rewrite(
+    config_loc: impl AsRef<str>,
+    content: impl AsRef<str>,
+    output: impl AsRef<Path>,
+    hint: impl AsRef<str>,
+) -> Result<()> {
+    let is_overwrite = config::io::location_key(config_loc.as_ref())
+        == config::io::location_key(output.as_ref().to_string_lossy());
+    if is_overwrite && config::io::has_extends(content)? {
+        bail!(
+            "The config {} extends a base config, so it is not overwritten with the base config inlined and `extends` dropped. {}, or write the resolved config elsewhere with --output",
+            config_loc.as_ref(),
+            hint.as_ref()
+        );
+    }
+    Ok(())
+}
+
+/// A remote config can not be written back, so `output` is required for it (also for `bump-version`).
+pub fn output_path(config_loc: impl AsRef<str>, output: &Option<PathBuf>) -> Result<PathBuf> {
+    match output {
+        Some(output) => Ok(output.clone()),
+        None => match Url::parse(config_loc.as_ref()) {
+            Ok(_) => Err(anyhow!(
+                "The config {} is a remote URL, so specify --output to write the config",
+                config_loc.as_ref()
+            )),
+            Err(_) => Ok(Path::new(config_loc.as_ref()).to_path_buf()),
+        },
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path() -> Result<()> {
+        assert_eq!(
+            output_path("gh-trs-config.yml", &None)?,
+            PathBuf::from("gh-trs-config.yml")
+        );
+        assert_eq!(
+            output_path(
+                "https://example.com/gh-trs-config.yml",
+                &Some(PathBuf::from("out.json"))
+            )?,
+            PathBuf::from("out.json")
+        );
+        assert!(output_path("https://example.com/gh-trs-config.yml", &None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_rewrite() -> Result<()> {
+        let config_loc = "./tests/test_config_CWL_validated.yml";
+        let content = "extends: ./base.yml\nversion: 1.0.1\n";
+        let err = check_rewrite(config_loc, content, config_loc, "hint").unwrap_err();
+        assert!(err.to_string().contains("extends a base config"));
+        assert!(check_rewrite(config_loc, content, "./tests/../out.yml", "hint").is_ok());
+        assert!(check_rewrite(config_loc, "version: 1.0.1\n", config_loc, "hint").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let output =
+            std::env::temp_dir().join(format!("gh-trs-normalize-{}.yml", std::process::id()));
+        normalize(
+            &http,
+            "./tests/test_config_CWL_validated.yml",
+            &None::<String>,
+            &Some(output.clone()),
+            &None,
+            false,
+            false,
+        )?;
+        let expected =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let normalized = config::io::read_config(&http, output.to_str().unwrap(), false)?;
+        std::fs::remove_file(&output)?;
+        assert_eq!(normalized, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_in_place() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config_loc = "./tests/test_config_CWL_validated.yml";
+        let content = format!(
+            "# Maintained by hand\n{}",
+            config::io::read_config_content(&http, config_loc)?
+        );
+        let (original, _) = config::io::config_from_content(&http, config_loc, &content, false)?;
+        let mut normalized = original.clone();
+        normalized.workflow.name = "renamed workflow".to_string();
+        let original_name = original.workflow.name.clone();
+
+        let updated = update_in_place(&http, config_loc, &content, &original, &normalized)?
+            .expect("the name is replaced in place");
+        assert!(updated.starts_with("# Maintained by hand\n"));
+        assert!(updated.contains("name: renamed workflow\n"));
+        assert!(!updated.contains(&format!("name: {}\n", original_name)));
+        // Only the whole scalar is replaced, not a part of the URLs and targets.
+        assert!(updated.contains(&format!("target: {}.cwl\n", original_name)));
+
+        // A change that is not a replaced value can not be applied in place.
+        normalized.authors.clear();
+        assert!(update_in_place(&http, config_loc, &content, &original, &normalized)?.is_none());
+        Ok(())
+    }
+}
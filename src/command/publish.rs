@@ -1,24 +1,402 @@
+use crate::command::bump_version;
+use crate::command::test;
+use crate::command::validate;
 use crate::config;
 use crate::env;
 use crate::github_api;
+use crate::raw_url;
+use crate::remote;
+use crate::s3;
 use crate::trs;
+use crate::warning::Warning;
+use crate::wes;
 
-use anyhow::{anyhow, Result};
-use log::info;
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use colored::Colorize;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use url::Url;
 
+pub const PUBLISH_STATE_FILE: &str = ".gh-trs-publish-state.json";
+
+/// File in the published branch that configures the custom domain of GitHub Pages.
+pub const CNAME_FILE: &str = "CNAME";
+
+/// Progress of a publish, used to resume a failed publish with `--resume`.
+/// Each entry is a config that has been validated (and tested if `with_test`),
+/// keyed by the config location and the checksum of its content.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PublishState {
+    pub with_test: bool,
+    pub entries: Vec<PublishStateEntry>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PublishStateEntry {
+    pub config_loc: String,
+    pub checksum: String,
+    pub config: config::types::Config,
+}
+
+impl PublishState {
+    pub fn new(with_test: bool) -> Self {
+        Self {
+            with_test,
+            entries: vec![],
+        }
+    }
+
+    /// If the state file does not exist, returns an empty state.
+    pub fn load(path: impl AsRef<Path>, with_test: bool) -> Result<Self> {
+        if !path.as_ref().exists() {
+            return Ok(Self::new(with_test));
+        }
+        let state: Self = serde_json::from_str(&fs::read_to_string(path.as_ref())?)?;
+        if state.with_test != with_test {
+            warn!(
+                "{}: The publish state in {} was recorded with with_test: {}, so ignore it",
+                "Warning".yellow(),
+                path.as_ref().display(),
+                state.with_test
+            );
+            return Ok(Self::new(with_test));
+        }
+        Ok(state)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut buffer = BufWriter::new(fs::File::create(path)?);
+        buffer.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn remove(path: impl AsRef<Path>) -> Result<()> {
+        if path.as_ref().exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn find(
+        &self,
+        config_loc: impl AsRef<str>,
+        checksum: impl AsRef<str>,
+    ) -> Option<&config::types::Config> {
+        self.entries
+            .iter()
+            .find(|e| e.config_loc == config_loc.as_ref() && e.checksum == checksum.as_ref())
+            .map(|e| &e.config)
+    }
+
+    pub fn add(
+        &mut self,
+        config_loc: impl AsRef<str>,
+        checksum: impl AsRef<str>,
+        config: &config::types::Config,
+    ) {
+        self.entries.retain(|e| e.config_loc != config_loc.as_ref());
+        self.entries.push(PublishStateEntry {
+            config_loc: config_loc.as_ref().to_string(),
+            checksum: checksum.as_ref().to_string(),
+            config: config.clone(),
+        });
+    }
+}
+
+/// Quantitative summary of a publish, logged at the end and printed as JSON with `--json`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct PublishMetrics {
+    /// Number of files published (only the changed or new ones are sent).
+    pub files: usize,
+    pub bytes: usize,
+    /// Number of HTTP requests (GitHub API, TRS, and raw content) made during the publish.
+    pub api_calls: usize,
+    pub duration_secs: f64,
+}
+
+impl PublishMetrics {
+    pub fn new(files: usize, bytes: usize, api_calls: usize, duration: Duration) -> Self {
+        Self {
+            files,
+            bytes,
+            api_calls,
+            duration_secs: (duration.as_secs_f64() * 1000.0).round() / 1000.0,
+        }
+    }
+
+    pub fn summary_line(&self) -> String {
+        format!(
+            "Published {} files ({} bytes) with {} API calls in {:.2}s",
+            self.files, self.bytes, self.api_calls, self.duration_secs
+        )
+    }
+}
+
+/// Checksum of the config recorded in `PUBLISH_STATE_FILE`.
+/// It covers the base configs of `extends`, so that an edit to a base config is processed again.
+/// If `extends` cannot be resolved, the raw content is used and the error is reported by the validation.
+pub fn config_checksum(
+    http: &remote::Http,
+    config_loc: impl AsRef<str>,
+    content: impl AsRef<str>,
+) -> String {
+    let resolved = config::io::resolve_extends(http, config_loc, &content)
+        .unwrap_or_else(|_| content.as_ref().to_string());
+    trs::types::Checksum::new_from_string(resolved).checksum
+}
+
+/// Validate the configs in parallel with up to `workers` threads (and test them by one WES if `with_test`),
+/// recording each config in `PUBLISH_STATE_FILE` as soon as it is processed.
+/// A config that fails does not abort the others, so that all the processed ones are recorded before the failures are returned.
+/// If `resume` is true, configs already recorded with the same content are skipped.
 #[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_configs(
+    http: &remote::Http,
+    config_locs: Vec<String>,
+    gh_token: &Option<impl AsRef<str>>,
+    strict: bool,
+    validate_params: bool,
+    workers: usize,
+    with_test: bool,
+    wes_loc: &Option<Url>,
+    docker_host: &Url,
+    wes_port: Option<u16>,
+    binds: &[(PathBuf, PathBuf)],
+    run_dir: &Option<PathBuf>,
+    wes_tags: &[(String, String)],
+    test_log: test::TestLog,
+    resume: bool,
+) -> Result<(Vec<config::types::Config>, Vec<Warning>)> {
+    let mut state = if resume {
+        PublishState::load(PUBLISH_STATE_FILE, with_test)?
+    } else {
+        PublishState::new(with_test)
+    };
+    // The configs in the order of `config_locs`, None until validated.
+    let mut configs: Vec<Option<config::types::Config>> = Vec::new();
+    let mut pending = Vec::new();
+    let mut fetch_failures = Vec::new();
+    for config_loc in config_locs {
+        // A failure to fetch one config does not abort the others, reported at the end.
+        let content = match config::io::read_config_content(http, &config_loc) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("{} to fetch {}: {}", "Failed".red(), config_loc, e);
+                fetch_failures.push(format!("{}: {}", config_loc, e));
+                continue;
+            }
+        };
+        let checksum = config_checksum(http, &config_loc, &content);
+        match state.find(&config_loc, &checksum) {
+            Some(config) => {
+                info!("Skip {} (already processed)", config_loc);
+                configs.push(Some(config.clone()));
+            }
+            None => {
+                pending.push((configs.len(), config_loc, checksum));
+                configs.push(None);
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let mut failures = Vec::new();
+    if !pending.is_empty() {
+        let (outcomes, run_warnings) = validate::validate_outcomes(
+            http,
+            pending.iter().map(|(_, loc, _)| loc).collect(),
+            gh_token,
+            strict,
+            validate_params,
+            workers,
+            false,
+            false,
+            false,
+            &validate::Memo::default(),
+            &None,
+        )?;
+        warnings.extend(run_warnings);
+        let mut validated = Vec::new();
+        for ((i, config_loc, checksum), outcome) in pending.iter().zip(outcomes) {
+            match outcome {
+                Ok(validate::ValidateOutcome::Valid(config, config_warnings)) => {
+                    warnings.extend(config_warnings);
+                    if !with_test {
+                        state.add(config_loc, checksum, &config);
+                        state.save(PUBLISH_STATE_FILE)?;
+                    }
+                    configs[*i] = Some(*config);
+                    validated.push((*i, config_loc, checksum));
+                }
+                Ok(validate::ValidateOutcome::FetchFailed(failure)) => fetch_failures.push(failure),
+                Err(e) => {
+                    error!("{} to validate {}: {}", "Failed".red(), config_loc, e);
+                    failures.push((config_loc.clone(), e));
+                }
+            }
+        }
+        // All configs are tested by one WES (sapporo-service is started once), each recorded as soon as it is tested.
+        if with_test && !validated.is_empty() {
+            let (test_wes_loc, use_sapporo) = match wes_loc {
+                Some(wes_loc) => (wes_loc.clone(), false),
+                None => (
+                    test::start_sapporo(http, docker_host, wes_port, binds, run_dir)?,
+                    true,
+                ),
+            };
+            for (i, config_loc, checksum) in validated {
+                let config = configs[i]
+                    .clone()
+                    .ok_or_else(|| anyhow!("{} is not validated", config_loc))?;
+                test::test(
+                    http,
+                    &vec![config.clone()],
+                    &Some(test_wes_loc.clone()),
+                    docker_host,
+                    wes_port,
+                    binds,
+                    run_dir,
+                    true,
+                    wes_tags,
+                    &None,
+                    true,
+                    test_log,
+                )?;
+                state.add(config_loc, checksum, &config);
+                state.save(PUBLISH_STATE_FILE)?;
+            }
+            if use_sapporo {
+                wes::sapporo::stop_wes(docker_host, run_dir)?;
+            }
+        }
+    }
+    ensure!(
+        fetch_failures.is_empty(),
+        "Failed to fetch {} config(s):\n{}",
+        fetch_failures.len(),
+        fetch_failures.join("\n")
+    );
+    if failures.len() == 1 {
+        // Returned as is, to be annotated in GitHub Actions.
+        return Err(failures.remove(0).1);
+    }
+    ensure!(
+        failures.is_empty(),
+        "Failed to validate {} config(s):\n{}",
+        failures.len(),
+        failures
+            .iter()
+            .map(|(config_loc, e)| format!("{}: {}", config_loc, e))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let configs = configs.into_iter().flatten().collect::<Vec<_>>();
+    warnings.extend(validate::validate_across_configs(&configs)?);
+    Ok((configs, warnings))
+}
+
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
 pub fn publish(
+    http: &remote::Http,
     configs: &Vec<config::types::Config>,
     gh_token: &Option<impl AsRef<str>>,
-    repo: impl AsRef<str>,
+    repo: &Option<String>,
     branch: impl AsRef<str>,
     verified: bool,
-) -> Result<()> {
+    manifest: bool,
+    allow_empty: bool,
+    base_branch: &Option<String>,
+    initial_readme: &Option<PathBuf>,
+    with_html: bool,
+    max_inline_size: Option<usize>,
+    strict: bool,
+    allow_cross_repo: bool,
+    base_url: &Option<Url>,
+    cname: &Option<String>,
+    tag: &Option<String>,
+    release: bool,
+    require_increasing_version: bool,
+    allow_id_reuse: bool,
+    enable_pages: bool,
+    print_curl: bool,
+    s3_target: &Option<s3::S3Target>,
+) -> Result<PublishMetrics> {
+    let started = Instant::now();
+    let start_request_count = http.request_count();
     let gh_token = env::github_token(gh_token)?;
+    let initial_readme_content =
+        match initial_readme {
+            Some(path) => Some(fs::read_to_string(path).with_context(|| {
+                format!("Failed to read the initial README: {}", path.display())
+            })?),
+            None => None,
+        };
 
-    let (owner, name) = github_api::parse_repo(repo)?;
-    github_api::get_repos(&gh_token, &owner, &name)
+    let (owner, name) = match repo {
+        Some(repo) => github_api::parse_repo(repo)?,
+        None => {
+            let (owner, name) = infer_repo(configs)?;
+            info!("Inferred repo: {}/{} from the workflow URLs", owner, name);
+            (owner, name)
+        }
+    };
+    if !allow_cross_repo {
+        check_cross_repo(configs, &owner, &name, strict)?;
+    }
+    github_api::get_repos(http, &gh_token, &owner, &name)
         .map_err(|e| anyhow!("Failed to get repo: {}/{} caused by: {}", owner, name, e))?;
+    // Checked before updating the branch not to leave a half-done publish.
+    let tag_target = match tag {
+        Some(tag) => {
+            let sha = source_commit(configs, &owner, &name)?;
+            let exists = github_api::exists_tag(http, &gh_token, &owner, &name, tag)
+                .with_context(|| format!("Failed to check if tag: {} exists", tag))?;
+            ensure!(
+                !exists,
+                "Tag: {} already exists in repo: {}/{}",
+                tag,
+                owner,
+                name
+            );
+            Some((tag, sha))
+        }
+        None => None,
+    };
+    if require_increasing_version {
+        let trs_endpoint =
+            trs::api::TrsEndpoint::new_from_url(&resolve_trs_url(&owner, &name, base_url, cname)?)?;
+        // An unreachable TRS is an error here, not "nothing published", so an outage does not pass the check.
+        for config in configs {
+            let published =
+                bump_version::published_versions(http, &trs_endpoint, config.id.to_string())
+                    .with_context(|| {
+                        format!(
+                            "--require-increasing-version can not check workflow {}",
+                            config.id
+                        )
+                    })?;
+            check_increasing_version(config, &published)?;
+        }
+    }
+    if !allow_id_reuse {
+        let trs_endpoint =
+            trs::api::TrsEndpoint::new_from_url(&resolve_trs_url(&owner, &name, base_url, cname)?)?;
+        for config in configs {
+            if let Some(tool) = published_tool(http, &trs_endpoint, config.id.as_str())? {
+                check_id_reuse(config, &tool)?;
+            }
+        }
+    }
 
     info!(
         "Publishing to repo: {}/{}, branch: {}",
@@ -27,25 +405,130 @@ pub fn publish(
         branch.as_ref(),
     );
 
-    match github_api::exists_branch(&gh_token, &owner, &name, branch.as_ref()) {
-        Ok(_) => {}
+    match github_api::exists_branch(http, &gh_token, &owner, &name, branch.as_ref()) {
+        Ok(_) => {
+            if let Some(base_branch) = base_branch {
+                warn!(
+                    "{}: Branch: {} already exists, so the base {} is ignored",
+                    "Warning".yellow(),
+                    branch.as_ref(),
+                    base_branch
+                );
+            }
+        }
         Err(_) => {
             info!("Branch: {} does not exist, creating it", branch.as_ref());
-            github_api::create_empty_branch(&gh_token, &owner, &name, branch.as_ref())?;
+            match base_branch {
+                Some(base_branch) => github_api::create_branch_from(
+                    http,
+                    &gh_token,
+                    &owner,
+                    &name,
+                    branch.as_ref(),
+                    base_branch,
+                )?,
+                None => github_api::create_empty_branch(
+                    http,
+                    &gh_token,
+                    &owner,
+                    &name,
+                    branch.as_ref(),
+                    initial_readme_content.as_ref(),
+                )?,
+            }
             info!("Branch: {} created", branch.as_ref());
         }
     }
 
-    let branch_sha = github_api::get_branch_sha(&gh_token, &owner, &name, branch.as_ref())?;
+    let branch_sha = github_api::get_branch_sha(http, &gh_token, &owner, &name, branch.as_ref())?;
     let latest_commit_sha =
-        github_api::get_latest_commit_sha(&gh_token, &owner, &name, branch.as_ref(), None)?;
-    let mut trs_response = trs::response::TrsResponse::new(&owner, &name)?;
+        github_api::get_latest_commit_sha(http, &gh_token, &owner, &name, branch.as_ref(), None)?;
+    let trs_url = resolve_trs_url(&owner, &name, base_url, cname)?;
+    let release_assets = match (release, &tag_target) {
+        (true, Some((_, sha))) => Some(generate_release_assets(configs, &trs_url, sha)?),
+        (true, None) => bail!("`--release` requires `--tag`"),
+        (false, _) => None,
+    };
+    let mut trs_response = trs::response::TrsResponse::new(http, &owner, &name, &trs_url)?;
     for config in configs {
-        trs_response.add(&owner, &name, config, verified)?;
+        trs_response.add(http, &trs_url, config, verified, max_inline_size)?;
+    }
+    let mut trs_contents = trs_response.generate_contents(with_html)?;
+    if let Some(cname) = cname {
+        trs_contents.insert(PathBuf::from(CNAME_FILE), format!("{}\n", cname));
+    }
+    if manifest {
+        let manifest_content = trs::response::generate_manifest(&trs_contents)?;
+        trs_contents.insert(
+            PathBuf::from(trs::response::MANIFEST_FILE),
+            manifest_content,
+        );
+    }
+    // Uploaded before updating the branch not to leave a half-done publish.
+    if let Some(s3_target) = s3_target {
+        let uploaded = s3::upload_contents(s3_target, &s3_contents(&trs_contents))?;
+        info!(
+            "Uploaded {} files to S3 bucket: {}",
+            uploaded, s3_target.bucket
+        );
+    }
+    let generated_count = trs_contents.len();
+    let current_tree_sha =
+        github_api::get_tree_sha(http, &gh_token, &owner, &name, branch.as_ref())?;
+    // Compared with the branch itself, not with GitHub Pages, which may not be deployed yet.
+    let trs_contents =
+        match github_api::get_tree_blob_shas(http, &gh_token, &owner, &name, &current_tree_sha)? {
+            Some(branch_blob_shas) => filter_changed_contents(trs_contents, &branch_blob_shas),
+            None => {
+                debug!("The tree of the branch is too large to list, so all files are sent");
+                trs_contents
+            }
+        };
+    info!(
+        "{} of {} generated files are changed or new",
+        trs_contents.len(),
+        generated_count
+    );
+    let published_files = trs_contents.len();
+    let published_bytes = trs_contents.values().map(|c| c.len()).sum();
+    let new_tree_sha = if trs_contents.is_empty() {
+        current_tree_sha.clone()
+    } else {
+        github_api::create_tree(
+            http,
+            &gh_token,
+            &owner,
+            &name,
+            Some(&branch_sha),
+            trs_contents,
+        )?
+    };
+    if new_tree_sha == current_tree_sha && !allow_empty {
+        info!("Nothing to publish, the branch is already up to date");
+        // The branch may be up to date from a previous publish before Pages was enabled.
+        pages_step(
+            http,
+            &gh_token,
+            &owner,
+            &name,
+            branch.as_ref(),
+            enable_pages,
+            &latest_commit_sha,
+            &trs_url,
+        );
+        if let Some((tag, sha)) = &tag_target {
+            tag_and_release(http, &gh_token, &owner, &name, tag, sha, &release_assets)?;
+        }
+        if print_curl {
+            println!("{}", curl_hints(&trs_url, configs)?.join("\n"));
+        }
+        return Ok(PublishMetrics::new(
+            0,
+            0,
+            http.request_count() - start_request_count,
+            started.elapsed(),
+        ));
     }
-    let trs_contents = trs_response.generate_contents()?;
-    let new_tree_sha =
-        github_api::create_tree(&gh_token, &owner, &name, Some(&branch_sha), trs_contents)?;
     let in_ci = env::in_ci();
     let commit_message = if configs.len() == 1 {
         format!(
@@ -61,6 +544,7 @@ pub fn publish(
         )
     };
     let new_commit_sha = github_api::create_commit(
+        http,
         &gh_token,
         &owner,
         &name,
@@ -68,7 +552,14 @@ pub fn publish(
         &new_tree_sha,
         &commit_message,
     )?;
-    github_api::update_ref(&gh_token, &owner, &name, branch.as_ref(), &new_commit_sha)?;
+    github_api::update_ref(
+        http,
+        &gh_token,
+        &owner,
+        &name,
+        branch.as_ref(),
+        &new_commit_sha,
+    )?;
 
     info!(
         "Published to repo: {}/{} branch: {}",
@@ -76,11 +567,944 @@ pub fn publish(
         &name,
         branch.as_ref()
     );
+    pages_step(
+        http,
+        &gh_token,
+        &owner,
+        &name,
+        branch.as_ref(),
+        enable_pages,
+        &new_commit_sha,
+        &trs_url,
+    );
     info!("Please wait for GitHub Pages to be built and published (https://github.com/{}/{}/actions/workflows/pages/pages-build-deployment).", &owner, &name);
+    info!("You can get TRS response as:\n    curl -L {}tools", trs_url);
+    if let Some((tag, sha)) = &tag_target {
+        tag_and_release(http, &gh_token, &owner, &name, tag, sha, &release_assets)?;
+    }
+    if print_curl {
+        println!("{}", curl_hints(&trs_url, configs)?.join("\n"));
+    }
+
+    Ok(PublishMetrics::new(
+        published_files,
+        published_bytes,
+        http.request_count() - start_request_count,
+        started.elapsed(),
+    ))
+}
+
+/// Enable GitHub Pages and wait for the build of `commit_sha` (`--enable-pages`), or only check that Pages serves the branch.
+/// The failures are warnings, as the branch is already published.
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+fn pages_step(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: &str,
+    name: &str,
+    branch: &str,
+    enable_pages: bool,
+    commit_sha: &str,
+    trs_url: &Url,
+) {
+    match enable_pages {
+        true => {
+            if let Err(e) =
+                enable_and_wait_pages(http, &gh_token, owner, name, branch, commit_sha, trs_url)
+            {
+                warn!("{}: {}", "Warning".yellow(), e);
+            }
+        }
+        false => check_pages(http, &gh_token, owner, name, branch),
+    }
+}
+
+/// Warn with the instructions if GitHub Pages is not serving the branch, as the TRS URL 404s even though the branch is updated.
+/// The check is skipped if the Pages API is not accessible (e.g., the token lacks the permission).
+#[cfg(not(tarpaulin_include))]
+fn check_pages(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: &str,
+    name: &str,
+    branch: &str,
+) {
+    match github_api::get_pages(http, &gh_token, owner, name) {
+        Ok(pages) => match pages_warning(&pages, owner, name, branch) {
+            Some(message) => warn!("{}: {}", "Warning".yellow(), message),
+            None => debug!("GitHub Pages is served from the branch {}", branch),
+        },
+        Err(e) => debug!("Skip the GitHub Pages check: {}", e),
+    }
+}
+
+/// Interval and timeout of polling the GitHub Pages build (`--enable-pages`).
+const PAGES_BUILD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const PAGES_BUILD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Enable GitHub Pages from the root of the branch if it is not enabled yet (`--enable-pages`),
+/// and wait until the commit is built to print the live TRS URL.
+/// If Pages is already configured for another source, it is left as is with a warning.
+#[cfg(not(tarpaulin_include))]
+fn enable_and_wait_pages(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: &str,
+    name: &str,
+    branch: &str,
+    commit_sha: &str,
+    trs_url: &Url,
+) -> Result<()> {
+    match github_api::get_pages(http, &gh_token, owner, name)? {
+        None => {
+            github_api::create_pages(http, &gh_token, owner, name, branch)
+                .context("Failed to enable GitHub Pages")?;
+            info!(
+                "Enabled GitHub Pages of {}/{} from the branch {}",
+                owner, name, branch
+            );
+        }
+        Some(pages) => {
+            if let Some(message) = pages_warning(&Some(pages), owner, name, branch) {
+                warn!(
+                    "{}: {} It is not changed by --enable-pages.",
+                    "Warning".yellow(),
+                    message
+                );
+                return Ok(());
+            }
+        }
+    }
+
+    wait_pages_build(
+        http,
+        &gh_token,
+        owner,
+        name,
+        commit_sha,
+        PAGES_BUILD_TIMEOUT,
+    )?;
+    info!("GitHub Pages is live: {}tools", trs_url);
+    Ok(())
+}
+
+/// Poll the latest GitHub Pages build until the commit is built, so that the TRS is fetched only after it is served.
+/// It is an error if the build fails or does not finish in `timeout`.
+#[cfg(not(tarpaulin_include))]
+pub fn wait_pages_build(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: &str,
+    name: &str,
+    commit_sha: &str,
+    timeout: Duration,
+) -> Result<()> {
     info!(
-        "You can get TRS response as:\n    curl -L https://{}.github.io/{}/tools",
-        &owner, &name
+        "Waiting for GitHub Pages to build the commit {}",
+        commit_sha
     );
+    let started = Instant::now();
+    loop {
+        // The latest build may not exist yet right after enabling Pages.
+        let state = match github_api::get_latest_pages_build(http, &gh_token, owner, name) {
+            Ok(build) => pages_build_state(&build, commit_sha),
+            Err(e) => {
+                debug!("Failed to get the latest GitHub Pages build: {}", e);
+                PagesBuildState::Pending
+            }
+        };
+        match state {
+            PagesBuildState::Built => return Ok(()),
+            PagesBuildState::Errored(message) => {
+                bail!("GitHub Pages build failed: {}", message)
+            }
+            PagesBuildState::Pending => {}
+        }
+        ensure!(
+            started.elapsed() < timeout,
+            "GitHub Pages build of the commit {} did not finish in {} seconds",
+            commit_sha,
+            timeout.as_secs()
+        );
+        std::thread::sleep(PAGES_BUILD_POLL_INTERVAL);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PagesBuildState {
+    Built,
+    /// Queued or building, or the latest build is of an older commit.
+    Pending,
+    Errored(String),
+}
 
+/// The state of the build of the commit from the response of the latest Pages build API.
+pub fn pages_build_state(build: &serde_json::Value, commit_sha: &str) -> PagesBuildState {
+    if build.get("commit").and_then(|c| c.as_str()) != Some(commit_sha) {
+        return PagesBuildState::Pending;
+    }
+    match build.get("status").and_then(|s| s.as_str()) {
+        Some("built") => PagesBuildState::Built,
+        Some("errored") => PagesBuildState::Errored(
+            build
+                .get("error")
+                .and_then(|e| e.get("message"))
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error")
+                .to_string(),
+        ),
+        _ => PagesBuildState::Pending,
+    }
+}
+
+/// The warning for the response of the Pages API (`None` if Pages is not enabled), or None if Pages serves the root of the branch.
+pub fn pages_warning(
+    pages: &Option<serde_json::Value>,
+    owner: &str,
+    name: &str,
+    branch: &str,
+) -> Option<String> {
+    let settings_url = format!("https://github.com/{}/{}/settings/pages", owner, name);
+    let pages = match pages {
+        Some(pages) => pages,
+        None => {
+            return Some(format!(
+                "GitHub Pages is not enabled for {}/{}, so the TRS is not served yet. Enable it at {} with the source `Deploy from a branch`, branch `{}`, and folder `/ (root)`.",
+                owner, name, settings_url, branch
+            ))
+        }
+    };
+    let source = pages.get("source");
+    let source_branch = source
+        .and_then(|s| s.get("branch"))
+        .and_then(|b| b.as_str());
+    let source_path = source
+        .and_then(|s| s.get("path"))
+        .and_then(|p| p.as_str())
+        .unwrap_or("/");
+    match source_branch {
+        Some(source_branch) if source_branch == branch && source_path == "/" => None,
+        Some(source_branch) => Some(format!(
+            "GitHub Pages of {}/{} is served from the branch `{}` (folder `{}`), not the root of the published branch `{}`, so the TRS is not served. Change the source at {}.",
+            owner, name, source_branch, source_path, branch, settings_url
+        )),
+        None => Some(format!(
+            "GitHub Pages of {}/{} is not built from a branch (e.g., it is deployed by GitHub Actions), so the published branch `{}` may not be served. Check the source at {}.",
+            owner, name, branch, settings_url
+        )),
+    }
+}
+
+/// Run the `--pre-publish-hook` command for a config with `sh -c`, e.g., to lint or package the workflow.
+/// The config location is passed as the last argument and as `GH_TRS_CONFIG`.
+/// The output is logged, and a non-zero exit status is an error to abort the publish.
+pub fn run_pre_publish_hook(hook: impl AsRef<str>, config_loc: impl AsRef<str>) -> Result<()> {
+    info!(
+        "Running pre-publish hook for {}: {}",
+        config_loc.as_ref(),
+        hook.as_ref()
+    );
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", hook.as_ref()))
+        .arg("gh-trs-pre-publish-hook")
+        .arg(config_loc.as_ref())
+        .env("GH_TRS_CONFIG", config_loc.as_ref())
+        .output()
+        .context("Failed to run the pre-publish hook")?;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        info!("[pre-publish-hook] {}", line);
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        info!("[pre-publish-hook] {}", line);
+    }
+    ensure!(
+        output.status.success(),
+        "The pre-publish hook for {} failed with {}",
+        config_loc.as_ref(),
+        output.status
+    );
+    Ok(())
+}
+
+/// The generated contents without the files only meaningful for GitHub Pages (`CNAME` and `.nojekyll`).
+pub fn s3_contents(trs_contents: &HashMap<PathBuf, String>) -> HashMap<PathBuf, String> {
+    trs_contents
+        .iter()
+        .filter(|(path, _)| {
+            path.as_path() != Path::new(CNAME_FILE) && path.as_path() != Path::new(".nojekyll")
+        })
+        .map(|(path, content)| (path.clone(), content.clone()))
+        .collect()
+}
+
+/// Copy-pasteable curl commands for the TRS endpoints of the published workflows:
+/// service-info, and the tool, versions, version, descriptor, files, and tests of each config.
+pub fn curl_hints(trs_url: &Url, configs: &[config::types::Config]) -> Result<Vec<String>> {
+    let mut hints = vec![
+        "# service-info".to_string(),
+        format!("curl -L {}", trs_url.join("service-info")?),
+    ];
+    for config in configs {
+        let desc_type = config
+            .workflow
+            .language
+            .r#type
+            .clone()
+            .ok_or_else(|| anyhow!("Workflow language type must be set"))?;
+        let version_path = format!("tools/{}/versions/{}/", config.id, config.version);
+        hints.push(format!("# {} version {}", config.id, config.version));
+        for path in [
+            format!("tools/{}", config.id),
+            format!("tools/{}/versions", config.id),
+            version_path.trim_end_matches('/').to_string(),
+            format!("{}{}/descriptor", version_path, desc_type),
+            format!("{}{}/files", version_path, desc_type),
+            format!("{}{}/tests", version_path, desc_type),
+        ] {
+            hints.push(format!("curl -L {}", trs_url.join(&path)?));
+        }
+    }
+    Ok(hints)
+}
+
+/// Create the tag, and a GitHub Release of it with the assets if `release_assets` is given.
+#[cfg(not(tarpaulin_include))]
+fn tag_and_release(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    tag: impl AsRef<str>,
+    sha: impl AsRef<str>,
+    release_assets: &Option<ReleaseAssets>,
+) -> Result<()> {
+    github_api::create_tag(http, &gh_token, &owner, &name, &tag, &sha)?;
+    info!(
+        "Created tag: {} at commit: {} in repo: {}/{}",
+        tag.as_ref(),
+        sha.as_ref(),
+        owner.as_ref(),
+        name.as_ref()
+    );
+    if let Some(release_assets) = release_assets {
+        let release_id = github_api::create_release(
+            http,
+            &gh_token,
+            &owner,
+            &name,
+            &tag,
+            &release_assets.name,
+            &release_assets.body,
+        )?;
+        for (asset_name, content) in &release_assets.assets {
+            github_api::upload_release_asset(
+                http, &gh_token, &owner, &name, release_id, asset_name, content,
+            )?;
+        }
+        info!(
+            "Created release: {} with {} assets",
+            tag.as_ref(),
+            release_assets.assets.len()
+        );
+    }
+    Ok(())
+}
+
+pub const RELEASE_METADATA_FILE: &str = "gh-trs-metadata.json";
+
+/// Name, body, and assets (file name, content) of the GitHub Release created with `--release`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReleaseAssets {
+    pub name: String,
+    pub body: String,
+    pub assets: Vec<(String, String)>,
+}
+
+/// The assets are the configs (`gh-trs-config.json`, or `gh-trs-config_<id>_<version>.json` for multiple configs)
+/// and the metadata file listing the published tool versions, which archives such as Zenodo pick up.
+pub fn generate_release_assets(
+    configs: &[config::types::Config],
+    trs_url: &Url,
+    commit: impl AsRef<str>,
+) -> Result<ReleaseAssets> {
+    let mut assets = Vec::new();
+    let mut tools = Vec::new();
+    for config in configs {
+        let config_file = if configs.len() == 1 {
+            "gh-trs-config.json".to_string()
+        } else {
+            format!("gh-trs-config_{}_{}.json", config.id, config.version)
+        };
+        assets.push((config_file, serde_json::to_string_pretty(config)?));
+        tools.push(serde_json::json!({
+            "id": config.id,
+            "version": config.version,
+            "name": config.workflow.name,
+            "language": config.workflow.language,
+            "url": trs_url.join(&format!("tools/{}/versions/{}", config.id, config.version))?,
+        }));
+    }
+    let metadata = serde_json::json!({
+        "trs_endpoint": trs_url,
+        "commit": commit.as_ref(),
+        "tools": tools,
+    });
+    assets.push((
+        RELEASE_METADATA_FILE.to_string(),
+        serde_json::to_string_pretty(&metadata)?,
+    ));
+    let name = match configs {
+        [config] => format!("{} {}", config.workflow.name, config.version),
+        _ => format!("{} workflows", configs.len()),
+    };
+    let body = format!("Published to the GA4GH TRS API by gh-trs: {}tools", trs_url);
+    Ok(ReleaseAssets { name, body, assets })
+}
+
+/// The commit of the repo that the primary workflows (pinned to commits by validation) are taken from, used as the target of `--tag`.
+/// The primary workflows must live in the repo and share one commit.
+pub fn source_commit(
+    configs: &[config::types::Config],
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+) -> Result<String> {
+    let mut commits = BTreeSet::new();
+    for config in configs {
+        let url = config.workflow.primary_wf()?.url;
+        let (wf_owner, wf_name) = raw_url::RawUrl::owner_name(&url)?;
+        ensure!(
+            wf_owner == owner.as_ref() && wf_name == name.as_ref(),
+            "The tag can not be created as the workflow id: {} lives in repo: {}/{}, not in repo: {}/{}",
+            config.id,
+            wf_owner,
+            wf_name,
+            owner.as_ref(),
+            name.as_ref()
+        );
+        let commit = url
+            .path_segments()
+            .and_then(|mut segments| segments.nth(2))
+            .filter(|commit| raw_url::is_commit_hash(commit).is_ok())
+            .ok_or_else(|| anyhow!("No commit found in the primary workflow URL: {}", url))?;
+        commits.insert(commit.to_string());
+    }
+    ensure!(
+        commits.len() <= 1,
+        "The tag can not be created as the primary workflows are taken from {} commits",
+        commits.len()
+    );
+    commits
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("The tag can not be created as no config is given"))
+}
+
+/// The TRS root URL: `base_url` if given, `https://<cname>/` if a custom domain is given, otherwise the GitHub Pages URL of the repo.
+pub fn resolve_trs_url(
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    base_url: &Option<Url>,
+    cname: &Option<String>,
+) -> Result<Url> {
+    if let Some(cname) = cname {
+        ensure!(
+            !cname.is_empty() && !cname.contains('/') && !cname.contains(':'),
+            "Invalid custom domain: {}. It should be a domain name like `trs.example.com`.",
+            cname
+        );
+    }
+    match (base_url, cname) {
+        (Some(base_url), _) => Ok(trs::api::TrsEndpoint::new_from_url(base_url)?.url),
+        (None, Some(cname)) => Ok(Url::parse(&format!("https://{}/", cname))?),
+        (None, None) => trs::api::gh_pages_trs_url(owner, name, None),
+    }
+}
+
+/// The version of the config must not be lower than the published versions of the workflow (compared as semver),
+/// which catches an accidental downgrade, e.g., a version templated from a branch name.
+/// Re-publishing the highest version is allowed, and the published versions that are not semver are ignored.
+pub fn check_increasing_version(
+    config: &config::types::Config,
+    published: &[String],
+) -> Result<()> {
+    let version = bump_version::SemVer::from_str(&config.version)
+        .with_context(|| format!("--require-increasing-version for workflow {}", config.id))?;
+    let max = published
+        .iter()
+        .filter_map(|v| bump_version::SemVer::from_str(v).ok())
+        .max();
+    if let Some(max) = max {
+        ensure!(
+            version >= max,
+            "Version {} of workflow {} is lower than the published max version {}",
+            config.version,
+            config.id,
+            max
+        );
+    }
+    Ok(())
+}
+
+/// Infer the repo to publish to from the primary workflow URLs of the configs (used when `--repo` is omitted).
+/// Non-GitHub URLs are skipped, and the URLs must reference exactly one repo.
+pub fn infer_repo(configs: &[config::types::Config]) -> Result<(String, String)> {
+    let mut repos = BTreeSet::new();
+    for config in configs {
+        let primary_wf = config.workflow.primary_wf()?;
+        if let Ok(repo) = raw_url::RawUrl::owner_name(&primary_wf.url) {
+            repos.insert(repo);
+        }
+    }
+    match repos.len() {
+        0 => bail!(
+            "Failed to infer the repo as no primary workflow is a GitHub URL. Please specify `--repo`."
+        ),
+        1 => Ok(repos.into_iter().next().unwrap_or_default()),
+        _ => bail!(
+            "Failed to infer the repo as the primary workflows reference multiple repos: {}. Please specify `--repo`.",
+            repos
+                .iter()
+                .map(|(owner, name)| format!("{}/{}", owner, name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Publishing a workflow to a repo other than the one its primary workflow lives in is usually a mistake.
+/// It is a warning, or an error if `strict` is true.
+pub fn check_cross_repo(
+    configs: &[config::types::Config],
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    strict: bool,
+) -> Result<()> {
+    for config in configs {
+        let primary_wf = config.workflow.primary_wf()?;
+        let (wf_owner, wf_name) = match raw_url::RawUrl::owner_name(&primary_wf.url) {
+            Ok(repo) => repo,
+            Err(_) => continue,
+        };
+        if wf_owner != owner.as_ref() || wf_name != name.as_ref() {
+            let message = format!(
+                "The workflow id: {} lives in repo: {}/{}, but is published to repo: {}/{}. Use `--allow-cross-repo` if this is intended.",
+                config.id,
+                wf_owner,
+                wf_name,
+                owner.as_ref(),
+                name.as_ref()
+            );
+            ensure!(!strict, message);
+            warn!("{}: {}", "Warning".yellow(), message);
+        }
+    }
+    Ok(())
+}
+
+/// The tool of the id already published in the TRS, or None if the TRS or the tool is not published yet (404).
+/// A TRS that is not reachable is an error, so that the id is not treated as unused.
+#[cfg(not(tarpaulin_include))]
+fn published_tool(
+    http: &remote::Http,
+    trs_endpoint: &trs::api::TrsEndpoint,
+    wf_id: impl AsRef<str>,
+) -> Result<Option<trs::types::Tool>> {
+    if !trs_endpoint.is_published(http)? {
+        debug!(
+            "Skip the id reuse check as no TRS is published at {} yet",
+            trs_endpoint.url
+        );
+        return Ok(None);
+    }
+    trs::api::find_tool(http, trs_endpoint, wf_id.as_ref()).with_context(|| {
+        format!(
+            "Failed to look up id {} in the TRS {} (use --allow-id-reuse to skip the check)",
+            wf_id.as_ref(),
+            trs_endpoint.url
+        )
+    })
+}
+
+/// The tool already published with the id of the config must be the same workflow (compared by name),
+/// as the TRS response merges the configs of the same id into one tool.
+pub fn check_id_reuse(config: &config::types::Config, tool: &trs::types::Tool) -> Result<()> {
+    if let Some(published_name) = &tool.name {
+        ensure!(
+            published_name == &config.workflow.name,
+            "Id {} is already used by another workflow `{}` in the TRS, so publishing `{}` would overwrite it. Use another id, or --allow-id-reuse if it is the same workflow renamed.",
+            config.id,
+            published_name,
+            config.workflow.name
+        );
+    }
     Ok(())
 }
+
+/// Keep only the contents that differ from the files on the branch, so that the unchanged files are carried over by the base tree.
+/// `branch_blob_shas` is the blob sha of each file on the branch (see `github_api::get_tree_blob_shas`).
+pub fn filter_changed_contents(
+    contents: HashMap<PathBuf, String>,
+    branch_blob_shas: &HashMap<PathBuf, String>,
+) -> HashMap<PathBuf, String> {
+    contents
+        .into_iter()
+        .filter(|(path, content)| branch_blob_shas.get(path) != Some(&git_blob_sha(content)))
+        .collect()
+}
+
+/// The sha of the content as a git blob (`git hash-object`).
+pub fn git_blob_sha(content: impl AsRef<str>) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", content.as_ref().len()));
+    hasher.update(content.as_ref());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use std::env as std_env;
+
+    #[test]
+    fn test_infer_repo() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        assert_eq!(
+            infer_repo(&[config.clone(), config.clone()])?,
+            ("suecharo".to_string(), "gh-trs".to_string())
+        );
+
+        let mut other = config.clone();
+        for file in other.workflow.files.iter_mut() {
+            file.url = Url::parse(
+                &file
+                    .url
+                    .as_str()
+                    .replace("suecharo/gh-trs", "suecharo/other"),
+            )?;
+        }
+        let err = infer_repo(&[config.clone(), other]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("multiple repos: suecharo/gh-trs, suecharo/other."));
+
+        let mut non_github = config;
+        for file in non_github.workflow.files.iter_mut() {
+            file.url = Url::parse("https://example.com/wf.cwl")?;
+        }
+        let err = infer_repo(&[non_github]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no primary workflow is a GitHub URL"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_trs_url() -> Result<()> {
+        assert_eq!(
+            resolve_trs_url("suecharo", "gh-trs", &None, &None)?.as_str(),
+            "https://suecharo.github.io/gh-trs/"
+        );
+        assert_eq!(
+            resolve_trs_url(
+                "suecharo",
+                "gh-trs",
+                &None,
+                &Some("trs.example.com".to_string())
+            )?
+            .as_str(),
+            "https://trs.example.com/"
+        );
+        assert_eq!(
+            resolve_trs_url(
+                "suecharo",
+                "gh-trs",
+                &Some(Url::parse("https://example.com/trs")?),
+                &Some("trs.example.com".to_string())
+            )?
+            .as_str(),
+            "https://example.com/trs/"
+        );
+        assert!(resolve_trs_url(
+            "suecharo",
+            "gh-trs",
+            &None,
+            &Some("https://trs.example.com".to_string())
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_commit() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        assert_eq!(
+            source_commit(&[config.clone(), config.clone()], "suecharo", "gh-trs")?,
+            "458d0524e667f2442a5effb730b523c1f15748d4"
+        );
+        assert!(source_commit(std::slice::from_ref(&config), "suecharo", "other").is_err());
+
+        let mut other_commit = config.clone();
+        for file in other_commit.workflow.files.iter_mut() {
+            file.url = Url::parse(&file.url.as_str().replace(
+                "458d0524e667f2442a5effb730b523c1f15748d4",
+                "f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9",
+            ))?;
+        }
+        assert!(source_commit(&[config, other_commit], "suecharo", "gh-trs").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_checksum() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let dir = std::env::temp_dir().join("gh-trs-test-config-checksum");
+        fs::create_dir_all(&dir)?;
+        let child_path = dir.join("child.yml");
+        let content = "extends: base.yml\nversion: 2.0.0\n";
+        fs::write(&child_path, content)?;
+        fs::write(dir.join("base.yml"), "license: MIT\n")?;
+        let child_loc = child_path.to_string_lossy().to_string();
+        let before = config_checksum(&http, &child_loc, content);
+        fs::write(dir.join("base.yml"), "license: Apache-2.0\n")?;
+        assert_ne!(config_checksum(&http, &child_loc, content), before);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_s3_contents() {
+        let mut contents = HashMap::new();
+        contents.insert(PathBuf::from(".nojekyll"), "".to_string());
+        contents.insert(PathBuf::from(CNAME_FILE), "trs.example.com\n".to_string());
+        contents.insert(PathBuf::from("tools/index.json"), "[]".to_string());
+        let contents = s3_contents(&contents);
+        assert_eq!(
+            contents.keys().collect::<Vec<_>>(),
+            vec![&PathBuf::from("tools/index.json")]
+        );
+    }
+
+    #[test]
+    fn test_publish_metrics() -> Result<()> {
+        let metrics = PublishMetrics::new(12, 34567, 27, Duration::from_millis(8321));
+        assert_eq!(
+            metrics.summary_line(),
+            "Published 12 files (34567 bytes) with 27 API calls in 8.32s"
+        );
+        assert_eq!(
+            serde_json::to_value(&metrics)?,
+            serde_json::json!({
+                "files": 12,
+                "bytes": 34567,
+                "api_calls": 27,
+                "duration_secs": 8.321
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_pre_publish_hook() -> Result<()> {
+        run_pre_publish_hook("test -f", "./tests/test_config_CWL.yml")?;
+        run_pre_publish_hook("test \"$GH_TRS_CONFIG\" =", "./tests/test_config_CWL.yml")?;
+        let err = run_pre_publish_hook("exit 3;", "./tests/test_config_CWL.yml").unwrap_err();
+        assert!(err.to_string().contains("failed with exit status: 3"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_curl_hints() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = Url::parse("https://suecharo.github.io/gh-trs/")?;
+        let hints = curl_hints(&trs_url, std::slice::from_ref(&config))?;
+        assert_eq!(hints.len(), 9);
+        assert_eq!(
+            hints[1],
+            "curl -L https://suecharo.github.io/gh-trs/service-info"
+        );
+        assert_eq!(
+            hints[6],
+            format!(
+                "curl -L https://suecharo.github.io/gh-trs/tools/{}/versions/1.0.0/CWL/descriptor",
+                config.id
+            )
+        );
+        assert!(hints[8].ends_with("/versions/1.0.0/CWL/tests"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_increasing_version() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        check_increasing_version(&config, &[])?;
+        check_increasing_version(&config, &["0.9.0".to_string(), "1.0.0".to_string()])?;
+        check_increasing_version(&config, &["latest".to_string()])?;
+        let err = check_increasing_version(&config, &["0.9.0".to_string(), "1.10.0".to_string()])
+            .unwrap_err();
+        assert!(err.to_string().contains("published max version 1.10.0"));
+
+        let mut draft = config;
+        draft.version = "draft".to_string();
+        assert!(check_increasing_version(&draft, &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pages_warning() -> Result<()> {
+        let warning = pages_warning(&None, "o", "n", "gh-pages").unwrap_or_default();
+        assert!(warning.contains("not enabled"));
+        assert!(warning.contains("https://github.com/o/n/settings/pages"));
+
+        let pages = serde_json::json!({"source": {"branch": "gh-pages", "path": "/"}});
+        assert_eq!(pages_warning(&Some(pages), "o", "n", "gh-pages"), None);
+
+        let pages = serde_json::json!({"source": {"branch": "main", "path": "/docs"}});
+        let warning = pages_warning(&Some(pages), "o", "n", "gh-pages").unwrap_or_default();
+        assert!(warning.contains("served from the branch `main` (folder `/docs`)"));
+
+        let pages = serde_json::json!({"build_type": "workflow"});
+        assert!(pages_warning(&Some(pages), "o", "n", "gh-pages").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pages_build_state() {
+        let sha = "458d0524e667f2442a5effb730b523c1f15748d4";
+        let build = serde_json::json!({"status": "built", "commit": sha});
+        assert_eq!(pages_build_state(&build, sha), PagesBuildState::Built);
+        let build = serde_json::json!({"status": "built", "commit": "0000000"});
+        assert_eq!(pages_build_state(&build, sha), PagesBuildState::Pending);
+        let build = serde_json::json!({"status": "building", "commit": sha});
+        assert_eq!(pages_build_state(&build, sha), PagesBuildState::Pending);
+        let build = serde_json::json!({"status": "errored", "commit": sha, "error": {"message": "Page build failed."}});
+        assert_eq!(
+            pages_build_state(&build, sha),
+            PagesBuildState::Errored("Page build failed.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_id_reuse() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = Url::parse("https://suecharo.github.io/gh-trs/")?;
+        let tool = trs::types::Tool::new(&config, &trs_url)?;
+        check_id_reuse(&config, &tool)?;
+
+        let mut other = config;
+        other.workflow.name = "another_workflow".to_string();
+        let err = check_id_reuse(&other, &tool).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("already used by another workflow `trimming_and_qc`"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_release_assets() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = Url::parse("https://suecharo.github.io/gh-trs/")?;
+        let commit = "458d0524e667f2442a5effb730b523c1f15748d4";
+
+        let release_assets =
+            generate_release_assets(std::slice::from_ref(&config), &trs_url, commit)?;
+        assert_eq!(release_assets.name, "trimming_and_qc 1.0.0");
+        let names = release_assets
+            .assets
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["gh-trs-config.json", RELEASE_METADATA_FILE]);
+        let metadata: serde_json::Value = serde_json::from_str(&release_assets.assets[1].1)?;
+        assert_eq!(metadata["commit"], commit);
+        assert_eq!(
+            metadata["tools"][0]["url"],
+            format!(
+                "https://suecharo.github.io/gh-trs/tools/{}/versions/1.0.0",
+                config.id
+            )
+        );
+
+        let mut new_version = config.clone();
+        new_version.version = "2.0.0".to_string();
+        let release_assets = generate_release_assets(&[config, new_version], &trs_url, commit)?;
+        assert_eq!(release_assets.assets.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_cross_repo() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let configs = vec![config];
+        check_cross_repo(&configs, "suecharo", "gh-trs", true)?;
+        check_cross_repo(&configs, "suecharo", "other", false)?;
+        assert!(check_cross_repo(&configs, "suecharo", "other", true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_blob_sha() {
+        assert_eq!(git_blob_sha(""), "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391");
+        assert_eq!(
+            git_blob_sha("hello\n"),
+            "ce013625030ba8dba906f756967f9e9ca394464a"
+        );
+    }
+
+    #[test]
+    fn test_filter_changed_contents() {
+        let mut contents = HashMap::new();
+        contents.insert(PathBuf::from("unchanged/index.json"), "a".to_string());
+        contents.insert(PathBuf::from("changed/index.json"), "b".to_string());
+        contents.insert(PathBuf::from("new/index.json"), "c".to_string());
+        let mut branch_blob_shas = HashMap::new();
+        branch_blob_shas.insert(PathBuf::from("unchanged/index.json"), git_blob_sha("a"));
+        branch_blob_shas.insert(PathBuf::from("changed/index.json"), git_blob_sha("old"));
+        let changed = filter_changed_contents(contents, &branch_blob_shas);
+        assert_eq!(changed.len(), 2);
+        assert!(changed.contains_key(&PathBuf::from("changed/index.json")));
+        assert!(changed.contains_key(&PathBuf::from("new/index.json")));
+    }
+
+    #[test]
+    fn test_publish_state() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let path = std_env::temp_dir().join("gh-trs-test-publish-state.json");
+        PublishState::remove(&path)?;
+
+        let mut state = PublishState::load(&path, true)?;
+        assert!(state.entries.is_empty());
+        state.add("config.yml", "checksum_1", &config);
+        state.add("config.yml", "checksum_2", &config);
+        state.save(&path)?;
+
+        let state = PublishState::load(&path, true)?;
+        assert_eq!(state.entries.len(), 1);
+        assert!(state.find("config.yml", "checksum_1").is_none());
+        assert_eq!(state.find("config.yml", "checksum_2"), Some(&config));
+
+        let state = PublishState::load(&path, false)?;
+        assert!(state.entries.is_empty());
+
+        PublishState::remove(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use crate::command::export_ro_crate;
+use crate::env;
+use crate::remote;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use url::Url;
+
+/// The workflow registered to WorkflowHub.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct WorkflowhubWorkflow {
+    pub id: String,
+    pub url: Url,
+}
+
+/// Build the RO-Crate of the workflow (as `export-ro-crate`) and submit it to WorkflowHub
+/// as a new workflow of the team (project) `team`.
+/// https://about.workflowhub.eu/developer/api/
+#[cfg(not(tarpaulin_include))]
+pub fn publish_workflowhub(
+    http: &remote::Http,
+    config_loc: impl AsRef<str>,
+    api_url: &Url,
+    token: &Option<impl AsRef<str>>,
+    team: u64,
+) -> Result<WorkflowhubWorkflow> {
+    let token = env::workflowhub_token(token)?;
+    let dir = std::env::temp_dir().join(format!(
+        "gh-trs-ro-crate-{}-{}",
+        std::process::id(),
+        uuid::Uuid::new_v4()
+    ));
+    let archive = export_ro_crate::export_ro_crate(http, &config_loc, &dir, false)
+        .and_then(|_| export_ro_crate::zip_crate(&dir));
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        debug!("Failed to remove {}: {}", dir.display(), e);
+    }
+    let archive = archive?;
+
+    let url = submit_url(api_url)?;
+    info!(
+        "Submitting the RO-Crate ({} bytes) to {} for the team {}",
+        archive.len(),
+        url,
+        team
+    );
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let form = reqwest::blocking::multipart::Form::new()
+        .part(
+            "ro_crate",
+            reqwest::blocking::multipart::Part::bytes(archive)
+                .file_name("ro-crate.crate.zip")
+                .mime_str("application/zip")?,
+        )
+        .text("workflow[project_ids][]", team.to_string());
+    let response = http.send(
+        client
+            .post(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(reqwest::header::AUTHORIZATION, format!("Token {}", token))
+            .multipart(form),
+    )?;
+    let status = response.status();
+    let body = response.text()?;
+    ensure!(
+        status != reqwest::StatusCode::UNAUTHORIZED,
+        "Failed to authenticate with WorkflowHub. Please check your WorkflowHub API token."
+    );
+    ensure!(
+        status.is_success(),
+        "Failed to submit the workflow to {} with status {}: {}",
+        url,
+        status,
+        body
+    );
+    parse_submit_response(api_url, &serde_json::from_str(&body)?)
+}
+
+fn submit_url(api_url: &Url) -> Result<Url> {
+    Ok(Url::parse(&format!(
+        "{}/workflows/submit",
+        api_url.as_str().trim_end_matches('/')
+    ))?)
+}
+
+/// The response is a JSON:API document of the workflow, e.g., `{"data": {"id": "123", "links": {"self": "/workflows/123"}}}`.
+pub fn parse_submit_response(api_url: &Url, res: &Value) -> Result<WorkflowhubWorkflow> {
+    let data = res
+        .get("data")
+        .ok_or_else(|| anyhow!("No `data` in the response of WorkflowHub: {}", res))?;
+    let id = match data.get("id") {
+        Some(Value::String(id)) => id.clone(),
+        Some(Value::Number(id)) => id.to_string(),
+        _ => {
+            return Err(anyhow!(
+                "No workflow id in the response of WorkflowHub: {}",
+                res
+            ))
+        }
+    };
+    let path = data
+        .get("links")
+        .and_then(|l| l.get("self"))
+        .and_then(|s| s.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("/workflows/{}", id));
+    let base = Url::parse(&format!("{}/", api_url.as_str().trim_end_matches('/')))?;
+    let url = base
+        .join(path.trim_start_matches('/'))
+        .context("Invalid workflow URL in the response of WorkflowHub")?;
+    Ok(WorkflowhubWorkflow { id, url })
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_submit_url() -> Result<()> {
+        assert_eq!(
+            submit_url(&Url::parse("https://workflowhub.eu/")?)?.as_str(),
+            "https://workflowhub.eu/workflows/submit"
+        );
+        assert_eq!(
+            submit_url(&Url::parse("https://dev.example.com/seek")?)?.as_str(),
+            "https://dev.example.com/seek/workflows/submit"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_submit_response() -> Result<()> {
+        let api_url = Url::parse("https://workflowhub.eu")?;
+        let res = json!({"data": {"id": "123", "type": "workflows", "links": {"self": "/workflows/123"}}});
+        assert_eq!(
+            parse_submit_response(&api_url, &res)?,
+            WorkflowhubWorkflow {
+                id: "123".to_string(),
+                url: Url::parse("https://workflowhub.eu/workflows/123")?,
+            }
+        );
+        let res = json!({"data": {"id": 7}});
+        assert_eq!(
+            parse_submit_response(&api_url, &res)?.url.as_str(),
+            "https://workflowhub.eu/workflows/7"
+        );
+        assert!(parse_submit_response(&api_url, &json!({"errors": []})).is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use crate::config;
 use crate::env;
+use crate::remote;
 use crate::wes;
 
 use anyhow::{anyhow, bail, ensure, Result};
@@ -8,41 +9,133 @@ use log::{debug, info, warn};
 use std::env as std_env;
 use std::fs;
 use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::thread;
 use std::time;
 use url::Url;
 
 pub struct TestResult {
+    pub workflow_id: String,
+    pub version: String,
     pub id: String,
-    pub status: wes::RunStatus,
+    pub status: wes::client::RunStatus,
     pub run_log: String,
+    pub duration: time::Duration,
 }
 
+/// Number of the last lines of the run log printed with `--test-log tail`.
+pub const DEFAULT_TEST_LOG_TAIL_LINES: usize = 50;
+
+/// How much of each run log is printed to the console.
+/// The full run log is always written to `test-logs/` in CI.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TestLog {
+    Full,
+    Tail(usize),
+    None,
+}
+
+/// `full`, `tail` (the last 50 lines), `tail:<lines>`, or `none`.
+impl FromStr for TestLog {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "full" => Ok(TestLog::Full),
+            "tail" => Ok(TestLog::Tail(DEFAULT_TEST_LOG_TAIL_LINES)),
+            "none" => Ok(TestLog::None),
+            s => match s.strip_prefix("tail:").map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => Ok(TestLog::Tail(n)),
+                _ => Err(anyhow!(
+                    "Unknown test log mode: {} (expected one of full, tail, tail:<lines>, none)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl TestLog {
+    /// The part of the run log printed inline, or None if it is not printed.
+    pub fn format(&self, run_log: &str) -> Option<String> {
+        match self {
+            TestLog::Full => Some(run_log.to_string()),
+            TestLog::Tail(n) => {
+                let lines = run_log.lines().collect::<Vec<_>>();
+                let start = lines.len().saturating_sub(*n);
+                let tail = lines[start..].join("\n");
+                if start == 0 {
+                    Some(tail)
+                } else {
+                    Some(format!("... ({} lines omitted)\n{}", start, tail))
+                }
+            }
+            TestLog::None => None,
+        }
+    }
+}
+
+/// If `profile` is given, only the test cases with the profile are run.
+/// The failures of all configs are reported together at the end, unless `fail_fast` is true.
+#[allow(clippy::too_many_arguments)]
 pub fn test(
+    http: &remote::Http,
     configs: &Vec<config::types::Config>,
     wes_loc: &Option<Url>,
     docker_host: &Url,
+    wes_port: Option<u16>,
+    binds: &[(PathBuf, PathBuf)],
+    run_dir: &Option<PathBuf>,
     ignore_fail: bool,
+    wes_tags: &[(String, String)],
+    profile: &Option<String>,
+    fail_fast: bool,
+    test_log: TestLog,
 ) -> Result<()> {
-    let wes_loc = match wes_loc {
-        Some(wes_loc) => wes_loc.clone(),
-        None => {
-            wes::start_wes(docker_host)?;
-            Url::parse(&wes::default_wes_location())?
-        }
+    if let Some(profile) = profile {
+        // Catch a typo of the profile before starting WES.
+        ensure!(
+            configs
+                .iter()
+                .flat_map(|c| &c.workflow.testing)
+                .any(|t| t.profiles.contains(profile)),
+            "No test case has the profile: {}",
+            profile
+        );
+    }
+
+    // sapporo-service is started (and stopped) only if the WES location is not specified.
+    let (wes_loc, use_sapporo) = match wes_loc {
+        Some(wes_loc) => (wes_loc.clone(), false),
+        None => (
+            start_sapporo(http, docker_host, wes_port, binds, run_dir)?,
+            true,
+        ),
     };
     info!("Use WES location: {} for testing", wes_loc);
 
-    let supported_wes_versions = wes::get_supported_wes_versions(&wes_loc)?;
-    ensure!(
-        supported_wes_versions
-            .into_iter()
-            .any(|v| &v == "sapporo-wes-1.0.1"),
-        "gh-trs only supports WES version sapporo-wes-1.0.1"
-    );
+    let supported_wes_versions = wes::client::get_supported_wes_versions(http, &wes_loc)?;
+    let is_sapporo = supported_wes_versions
+        .iter()
+        .any(|v| v == "sapporo-wes-1.0.1");
+    if use_sapporo {
+        ensure!(
+            is_sapporo,
+            "gh-trs only supports WES version sapporo-wes-1.0.1"
+        );
+    } else if !is_sapporo {
+        warn!(
+            "{}: WES at {} does not support sapporo-wes-1.0.1 (supported: {}), so some features such as URL-based workflow_attachment may not work",
+            "Warning".yellow(),
+            wes_loc,
+            supported_wes_versions.join(", ")
+        );
+    }
 
     let in_ci = env::in_ci();
 
+    let mut all_results = vec![];
     for config in configs {
         info!(
             "Test workflow_id: {}, version: {}",
@@ -50,19 +143,29 @@ pub fn test(
         );
         let mut test_results = vec![];
         for test_case in &config.workflow.testing {
+            if !test_case.has_profile(profile) {
+                debug!(
+                    "Skip test case: {} (not in profile {})",
+                    test_case.id,
+                    profile.as_deref().unwrap_or_default()
+                );
+                continue;
+            }
             info!("Testing test case: {}", test_case.id);
 
-            let form = wes::test_case_to_form(&config.workflow, test_case)?;
+            let started_at = time::Instant::now();
+            let form = wes::client::test_case_to_form(http, config, test_case, wes_tags)?;
             debug!("Form:\n{:#?}", &form);
-            let run_id = wes::post_run(&wes_loc, form)?;
+            let run_id = wes::client::post_run(http, &wes_loc, form)?;
             info!("WES run_id: {}", run_id);
-            let mut status = wes::RunStatus::Running;
-            while status == wes::RunStatus::Running {
-                status = wes::get_run_status(&wes_loc, &run_id)?;
+            let mut status = wes::client::RunStatus::Running;
+            while status == wes::client::RunStatus::Running {
+                status = wes::client::get_run_status(http, &wes_loc, &run_id)?;
                 debug!("WES run status: {:?}", status);
                 thread::sleep(time::Duration::from_secs(5));
             }
-            let run_log = serde_json::to_string_pretty(&wes::get_run_log(&wes_loc, &run_id)?)?;
+            let run_log =
+                serde_json::to_string_pretty(&wes::client::get_run_log(http, &wes_loc, &run_id)?)?;
             if in_ci {
                 let test_log_file = std_env::current_dir()?.join(format!(
                     "test-logs/{}_{}_{}.log",
@@ -77,27 +180,32 @@ pub fn test(
                 buffer.write_all(run_log.as_bytes())?;
             }
             match status {
-                wes::RunStatus::Complete => {
+                wes::client::RunStatus::Complete => {
                     info!("Complete test case: {}", test_case.id);
-                    debug!("Run log:\n{}", run_log);
-                }
-                wes::RunStatus::Failed => {
-                    info!(
-                        "Failed test case: {} with run_log:\n{}",
-                        test_case.id, run_log
-                    );
+                    if let Some(log) = test_log.format(&run_log) {
+                        debug!("Run log:\n{}", log);
+                    }
                 }
+                wes::client::RunStatus::Failed => match test_log.format(&run_log) {
+                    Some(log) => info!("Failed test case: {} with run_log:\n{}", test_case.id, log),
+                    None => info!("Failed test case: {}", test_case.id),
+                },
                 _ => {
                     unreachable!("WES run status: {:?}", status);
                 }
             }
             test_results.push(TestResult {
+                workflow_id: config.id.to_string(),
+                version: config.version.clone(),
                 id: test_case.id.clone(),
                 status,
                 run_log,
+                duration: started_at.elapsed(),
             });
         }
-        match check_test_results(&test_results) {
+        let result = check_test_results(&test_results);
+        all_results.append(&mut test_results);
+        match result {
             Ok(()) => {
                 info!(
                     "Passed all test cases in workflow_id: {}, version: {}",
@@ -107,21 +215,116 @@ pub fn test(
             Err(e) => {
                 if ignore_fail {
                     warn!("{}, but ignore_fail is true", e);
-                } else {
+                } else if fail_fast {
+                    print_summary(&all_results);
                     bail!(e);
+                } else {
+                    warn!(
+                        "{} in workflow_id: {}, version: {}, continue to the next config",
+                        e, config.id, config.version
+                    );
                 }
             }
         }
     }
+    print_summary(&all_results);
+    if !ignore_fail {
+        check_all_test_results(&all_results)?;
+    }
 
-    wes::stop_wes(docker_host)?;
+    if use_sapporo {
+        wes::sapporo::stop_wes(docker_host, run_dir)?;
+    }
     Ok(())
 }
 
+/// Start sapporo-service on `wes_port` (a free port if None) and return its location.
+pub fn start_sapporo(
+    http: &remote::Http,
+    docker_host: &Url,
+    wes_port: Option<u16>,
+    binds: &[(PathBuf, PathBuf)],
+    run_dir: &Option<PathBuf>,
+) -> Result<Url> {
+    let wes_port = match wes_port {
+        Some(wes_port) => wes_port,
+        None => wes::sapporo::free_port()?,
+    };
+    wes::sapporo::start_wes(http, docker_host, wes_port, binds, run_dir)?;
+    Ok(Url::parse(&wes::sapporo::default_wes_location(wes_port))?)
+}
+
+#[cfg(not(tarpaulin_include))]
+fn print_summary(test_results: &[TestResult]) {
+    for line in format_summary(test_results) {
+        info!("{}", line);
+    }
+}
+
+/// The table of all the test results (workflow id/version, test id, status, and duration) with the totals line.
+/// The status is colored unless disabled (`--no-color` or the `NO_COLOR` env var).
+pub fn format_summary(test_results: &[TestResult]) -> Vec<String> {
+    let header = ["WORKFLOW_ID", "VERSION", "TEST_ID", "STATUS", "DURATION"];
+    let rows = test_results
+        .iter()
+        .map(|r| {
+            [
+                r.workflow_id.clone(),
+                r.version.clone(),
+                r.id.clone(),
+                match r.status {
+                    wes::client::RunStatus::Complete => "PASSED".to_string(),
+                    _ => "FAILED".to_string(),
+                },
+                format!("{}s", r.duration.as_secs()),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut widths = header.map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    // Pad before coloring, since the escape sequences have no width.
+    let format_row = |cells: [String; 5]| -> String {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                let padded = format!("{:<width$}", cell, width = width);
+                match (i, cell.as_str()) {
+                    (3, "PASSED") => padded.green().to_string(),
+                    (3, "FAILED") => padded.red().to_string(),
+                    _ => padded,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec!["Test summary:".to_string()];
+    lines.push(format_row(header.map(|h| h.to_string())));
+    let passed = rows.iter().filter(|row| row[3] == "PASSED").count();
+    for row in rows {
+        lines.push(format_row(row));
+    }
+    lines.push(format!(
+        "Total: {}, Passed: {}, Failed: {}",
+        test_results.len(),
+        passed,
+        test_results.len() - passed
+    ));
+    lines
+}
+
 pub fn check_test_results(test_results: &[TestResult]) -> Result<()> {
     let failed_tests = test_results
         .iter()
-        .filter(|r| r.status == wes::RunStatus::Failed)
+        .filter(|r| r.status == wes::client::RunStatus::Failed)
         .collect::<Vec<_>>();
     if !failed_tests.is_empty() {
         bail!(
@@ -137,3 +340,85 @@ pub fn check_test_results(test_results: &[TestResult]) -> Result<()> {
     }
     Ok(())
 }
+
+/// Same as `check_test_results`, but across configs, listing each failure as `<workflow_id>@<version>/<test_id>`.
+pub fn check_all_test_results(test_results: &[TestResult]) -> Result<()> {
+    let failed_tests = test_results
+        .iter()
+        .filter(|r| r.status == wes::client::RunStatus::Failed)
+        .map(|r| format!("{}@{}/{}", r.workflow_id, r.version, r.id))
+        .collect::<Vec<_>>();
+    if !failed_tests.is_empty() {
+        bail!(
+            "{} {} tests: {}",
+            "Failed".red(),
+            failed_tests.len(),
+            failed_tests.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_log() -> Result<()> {
+        assert_eq!(TestLog::from_str("full")?, TestLog::Full);
+        assert_eq!(
+            TestLog::from_str("tail")?,
+            TestLog::Tail(DEFAULT_TEST_LOG_TAIL_LINES)
+        );
+        assert_eq!(TestLog::from_str("tail:2")?, TestLog::Tail(2));
+        assert_eq!(TestLog::from_str("NONE")?, TestLog::None);
+        assert!(TestLog::from_str("tail:x").is_err());
+        assert!(TestLog::from_str("head").is_err());
+
+        let run_log = "a\nb\nc";
+        assert_eq!(TestLog::Full.format(run_log), Some(run_log.to_string()));
+        assert_eq!(
+            TestLog::Tail(2).format(run_log),
+            Some("... (1 lines omitted)\nb\nc".to_string())
+        );
+        assert_eq!(TestLog::Tail(5).format(run_log), Some(run_log.to_string()));
+        assert_eq!(TestLog::None.format(run_log), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_summary() {
+        colored::control::set_override(false);
+        let result = |id: &str, status, secs| TestResult {
+            workflow_id: "493c4948-987a-482b-993c-733bdb3d1d6c".to_string(),
+            version: "1.0.0".to_string(),
+            id: id.to_string(),
+            status,
+            run_log: "".to_string(),
+            duration: time::Duration::from_secs(secs),
+        };
+        let results = [
+            result("test_1", wes::client::RunStatus::Complete, 62),
+            result("test_full", wes::client::RunStatus::Failed, 5),
+        ];
+        let lines = format_summary(&results);
+        assert_eq!(
+            lines,
+            vec![
+                "Test summary:",
+                "WORKFLOW_ID                           VERSION  TEST_ID    STATUS  DURATION",
+                "493c4948-987a-482b-993c-733bdb3d1d6c  1.0.0    test_1     PASSED  62s",
+                "493c4948-987a-482b-993c-733bdb3d1d6c  1.0.0    test_full  FAILED  5s",
+                "Total: 2, Passed: 1, Failed: 1",
+            ]
+        );
+
+        let err = check_all_test_results(&results).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed 1 tests: 493c4948-987a-482b-993c-733bdb3d1d6c@1.0.0/test_full"
+        );
+        assert!(check_all_test_results(&results[..1]).is_ok());
+    }
+}
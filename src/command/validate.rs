@@ -1,34 +1,647 @@
 use crate::config;
 use crate::env;
+use crate::language_validator;
 use crate::raw_url;
+use crate::remote;
+use crate::warning::{ConfigError, Warning};
 
-use anyhow::{ensure, Context, Result};
-use log::{debug, info};
-use std::collections::{HashMap, HashSet};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use colored::Colorize;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use url::Url;
 
+/// Branch and commit lookups shared across the configs validated in parallel,
+/// so that the same owner/name/branch is not resolved repeatedly.
+#[derive(Debug, Default)]
+pub struct Memo {
+    pub branch: Mutex<HashMap<String, String>>,
+    pub commit: Mutex<HashMap<String, String>>,
+}
+
+impl Memo {
+    /// Add the lookups of `other`. The existing entries take precedence.
+    pub fn extend(&self, other: Memo) {
+        let merge = |memo: &Mutex<HashMap<String, String>>,
+                     other: Mutex<HashMap<String, String>>| {
+            let mut memo = memo.lock().unwrap_or_else(|e| e.into_inner());
+            for (k, v) in other.into_inner().unwrap_or_else(|e| e.into_inner()) {
+                memo.entry(k).or_insert(v);
+            }
+        };
+        merge(&self.branch, other.branch);
+        merge(&self.commit, other.commit);
+    }
+}
+
+pub const LOOKUP_CACHE_FILE: &str = ".gh-trs-cache.json";
+
+/// Branch heads move, so the commit shas resolved from branches expire soon.
+pub const BRANCH_HEAD_TTL_SECS: i64 = 10 * 60;
+
+/// The default branches of repos rarely change.
+pub const DEFAULT_BRANCH_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// On-disk cache of `Memo` across runs, used with `--cache`.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct LookupCache {
+    /// `<owner>/<name>` -> default branch
+    pub branch: HashMap<String, CacheEntry>,
+    /// `<owner>/<name>/<branch>` -> latest commit sha
+    pub commit: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub value: String,
+    pub cached_at: DateTime<Utc>,
+}
+
+impl LookupCache {
+    /// If the cache file does not exist or is broken, returns an empty cache (with a warning if broken).
+    /// The expired entries are dropped.
+    pub fn load(path: impl AsRef<Path>) -> Result<(Self, Vec<Warning>)> {
+        if !path.as_ref().exists() {
+            return Ok((Self::default(), vec![]));
+        }
+        let mut cache: Self = match serde_json::from_str(&fs::read_to_string(path.as_ref())?) {
+            Ok(cache) => cache,
+            Err(e) => {
+                let warning = Warning::emit(
+                    "--cache",
+                    format!("Failed to parse the cache, so ignore it: {}", e),
+                )
+                .with_location(path.as_ref().to_string_lossy());
+                return Ok((Self::default(), vec![warning]));
+            }
+        };
+        cache.expire(Utc::now());
+        Ok((cache, vec![]))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut buffer = BufWriter::new(fs::File::create(path)?);
+        buffer.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        self.branch
+            .retain(|_, e| now - e.cached_at < Duration::seconds(DEFAULT_BRANCH_TTL_SECS));
+        self.commit
+            .retain(|_, e| now - e.cached_at < Duration::seconds(BRANCH_HEAD_TTL_SECS));
+    }
+
+    pub fn to_memo(&self) -> Memo {
+        let values = |entries: &HashMap<String, CacheEntry>| {
+            entries
+                .iter()
+                .map(|(k, e)| (k.clone(), e.value.clone()))
+                .collect::<HashMap<_, _>>()
+        };
+        Memo {
+            branch: Mutex::new(values(&self.branch)),
+            commit: Mutex::new(values(&self.commit)),
+        }
+    }
+
+    /// Record the lookups in the memo. The entries already cached with the same value keep their time.
+    pub fn update(&mut self, memo: &Memo, now: DateTime<Utc>) {
+        let merge = |entries: &mut HashMap<String, CacheEntry>,
+                     memo: &Mutex<HashMap<String, String>>| {
+            for (k, v) in memo.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+                if entries.get(k).map(|e| &e.value) != Some(v) {
+                    entries.insert(
+                        k.clone(),
+                        CacheEntry {
+                            value: v.clone(),
+                            cached_at: now,
+                        },
+                    );
+                }
+            }
+        };
+        merge(&mut self.branch, &memo.branch);
+        merge(&mut self.commit, &memo.commit);
+    }
+}
+
+/// The outcome of a config that did not fail validation: valid with its warnings, or not fetched (`<location>: <error>`).
+pub enum ValidateOutcome {
+    Valid(Box<config::types::Config>, Vec<Warning>),
+    FetchFailed(String),
+}
+
+/// Validate the configs in parallel with up to `workers` threads.
+/// The returned configs are in the same order as `config_locs`.
+/// The soft issues found in the configs are returned as warnings along with them.
+///
+/// If `offline` is true, only the schema and the contents of the configs are checked without a GitHub token;
+/// the URLs are not pinned to commits and their reachability is not checked.
+///
+/// If `cache` is true, the branch and commit lookups are cached in `LOOKUP_CACHE_FILE` across runs.
+///
+/// If `deep` is `Some` (the `--validator` entries), the workflows are also validated by the validators of their languages.
 #[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
 pub fn validate(
+    http: &remote::Http,
     config_locs: Vec<impl AsRef<str>>,
     gh_token: &Option<impl AsRef<str>>,
-) -> Result<Vec<config::types::Config>> {
-    let gh_token = env::github_token(gh_token)?;
+    strict: bool,
+    validate_params: bool,
+    workers: usize,
+    show_url_changes: bool,
+    offline: bool,
+    cache: bool,
+    deep: &Option<Vec<(config::types::LanguageType, String)>>,
+) -> Result<(Vec<config::types::Config>, Vec<Warning>)> {
+    let (configs, mut warnings) = validate_configs(
+        http,
+        config_locs,
+        gh_token,
+        strict,
+        validate_params,
+        workers,
+        show_url_changes,
+        offline,
+        cache,
+        deep,
+    )?;
+    warnings.extend(validate_across_configs(&configs)?);
+    Ok((configs, warnings))
+}
 
+/// `validate` without the checks across the configs, for the callers checking them together with other configs.
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn validate_configs(
+    http: &remote::Http,
+    config_locs: Vec<impl AsRef<str>>,
+    gh_token: &Option<impl AsRef<str>>,
+    strict: bool,
+    validate_params: bool,
+    workers: usize,
+    show_url_changes: bool,
+    offline: bool,
+    cache: bool,
+    deep: &Option<Vec<(config::types::LanguageType, String)>>,
+) -> Result<(Vec<config::types::Config>, Vec<Warning>)> {
     let mut configs = Vec::new();
+    let (outcomes, mut warnings) = validate_outcomes(
+        http,
+        config_locs,
+        gh_token,
+        strict,
+        validate_params,
+        workers,
+        show_url_changes,
+        offline,
+        cache,
+        &Memo::default(),
+        deep,
+    )?;
+    let mut fetch_failures = Vec::new();
+    for outcome in outcomes {
+        match outcome? {
+            ValidateOutcome::Valid(config, config_warnings) => {
+                configs.push(*config);
+                warnings.extend(config_warnings);
+            }
+            ValidateOutcome::FetchFailed(failure) => fetch_failures.push(failure),
+        }
+    }
+    ensure!(
+        fetch_failures.is_empty(),
+        "Failed to fetch {} config(s):\n{}",
+        fetch_failures.len(),
+        fetch_failures.join("\n")
+    );
+    Ok((configs, warnings))
+}
+
+/// The outcome of each config, in the same order as `config_locs`,
+/// and the warnings not about a config (e.g., a broken cache file).
+/// The branch and commit lookups are shared through `memo` (e.g., by the callers validating configs in batches).
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn validate_outcomes(
+    http: &remote::Http,
+    config_locs: Vec<impl AsRef<str>>,
+    gh_token: &Option<impl AsRef<str>>,
+    strict: bool,
+    validate_params: bool,
+    workers: usize,
+    show_url_changes: bool,
+    offline: bool,
+    cache: bool,
+    memo: &Memo,
+    deep: &Option<Vec<(config::types::LanguageType, String)>>,
+) -> Result<(Vec<Result<ValidateOutcome>>, Vec<Warning>)> {
+    // No token means offline in the following.
+    let gh_token = match offline {
+        true => None,
+        false => Some(env::github_token(gh_token)?),
+    };
+    let mut warnings = Vec::new();
+    if offline && validate_params {
+        warnings.push(Warning::emit(
+            "--validate-params",
+            "The params files are not checked in offline mode",
+        ));
+    }
+    let config_locs = config_locs
+        .iter()
+        .map(|config_loc| config_loc.as_ref().to_string())
+        .collect::<Vec<_>>();
+
+    let mut lookup_cache = match cache {
+        true => {
+            let (lookup_cache, cache_warnings) = LookupCache::load(LOOKUP_CACHE_FILE)?;
+            warnings.extend(cache_warnings);
+            Some(lookup_cache)
+        }
+        false => None,
+    };
+    if let Some(lookup_cache) = &lookup_cache {
+        memo.extend(lookup_cache.to_memo());
+    }
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<Result<ValidateOutcome>>>> =
+        Mutex::new(config_locs.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, config_locs.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= config_locs.len() {
+                    break;
+                }
+                let outcome = validate_config(
+                    http,
+                    &config_locs[i],
+                    gh_token.as_deref(),
+                    strict,
+                    validate_params && !offline,
+                    show_url_changes,
+                    memo,
+                    deep,
+                );
+                outcomes.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(outcome);
+            });
+        }
+    });
+
+    if let Some(lookup_cache) = &mut lookup_cache {
+        lookup_cache.update(memo, Utc::now());
+        if let Err(e) = lookup_cache.save(LOOKUP_CACHE_FILE) {
+            warnings.push(
+                Warning::emit("--cache", format!("Failed to save the cache: {}", e))
+                    .with_location(LOOKUP_CACHE_FILE),
+            );
+        }
+    }
+
+    let outcomes = outcomes
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|outcome| outcome.unwrap_or_else(|| Err(anyhow!("A config was not validated"))))
+        .collect();
+    Ok((outcomes, warnings))
+}
+
+/// Result of validating a config, printed as JSON with `--json`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ConfigReport {
+    pub location: String,
+    pub valid: bool,
+    pub id: Option<config::types::WorkflowId>,
+    pub version: Option<String>,
+    pub name: Option<String>,
+    pub language: Option<config::types::Language>,
+    pub file_count: Option<usize>,
+    pub warnings: Vec<Warning>,
+    pub error: Option<String>,
+}
+
+impl ConfigReport {
+    pub fn new_valid(
+        location: impl AsRef<str>,
+        config: &config::types::Config,
+        warnings: Vec<Warning>,
+    ) -> Self {
+        Self {
+            location: location.as_ref().to_string(),
+            valid: true,
+            id: Some(config.id.clone()),
+            version: Some(config.version.clone()),
+            name: Some(config.workflow.name.clone()),
+            language: Some(config.workflow.language.clone()),
+            file_count: Some(config.workflow.files.len()),
+            warnings,
+            error: None,
+        }
+    }
+
+    pub fn new_invalid(location: impl AsRef<str>, error: &anyhow::Error) -> Self {
+        Self {
+            location: location.as_ref().to_string(),
+            valid: false,
+            id: None,
+            version: None,
+            name: None,
+            language: None,
+            file_count: None,
+            warnings: vec![],
+            error: Some(error.to_string()),
+        }
+    }
+}
 
-    for config_loc in config_locs {
-        info!("Validating {}", config_loc.as_ref());
-        let mut config = config::io::read_config(config_loc.as_ref())?;
+/// Report of `validate --json`. The issues across the configs (e.g., duplicate ids) and those not about a config
+/// (e.g., a broken cache file) are reported at the top level.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ValidateReport {
+    pub valid: bool,
+    pub configs: Vec<ConfigReport>,
+    pub warnings: Vec<Warning>,
+    pub error: Option<String>,
+}
 
-        validate_authors(&config.authors)?;
-        validate_language(&config.workflow.language)?;
-        validate_wf_name(&config.workflow.name)?;
-        validate_and_update_workflow(&gh_token, &mut config)?;
+impl ValidateReport {
+    pub fn new(configs: Vec<ConfigReport>, across_configs: Result<Vec<Warning>>) -> Self {
+        let (warnings, error) = match across_configs {
+            Ok(warnings) => (warnings, None),
+            Err(e) => (vec![], Some(e.to_string())),
+        };
+        Self {
+            valid: error.is_none() && configs.iter().all(|c| c.valid),
+            configs,
+            warnings,
+            error,
+        }
+    }
+}
 
-        debug!("updated config: {:?}", config);
+/// Validate the configs in parallel with up to `workers` threads, reporting each config separately
+/// (so that a failure of one config does not hide the results of the others),
+/// then check the valid ones across the configs once.
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn validate_report(
+    http: &remote::Http,
+    config_locs: Vec<impl AsRef<str>>,
+    gh_token: &Option<impl AsRef<str>>,
+    strict: bool,
+    validate_params: bool,
+    workers: usize,
+    show_url_changes: bool,
+    offline: bool,
+    cache: bool,
+    deep: &Option<Vec<(config::types::LanguageType, String)>>,
+) -> ValidateReport {
+    let config_locs = config_locs
+        .iter()
+        .map(|config_loc| config_loc.as_ref().to_string())
+        .collect::<Vec<_>>();
+    let (outcomes, run_warnings) = match validate_outcomes(
+        http,
+        config_locs.clone(),
+        gh_token,
+        strict,
+        validate_params,
+        workers,
+        show_url_changes,
+        offline,
+        cache,
+        &Memo::default(),
+        deep,
+    ) {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            error!("{} to validate with error: {}", "Failed".red(), e);
+            return ValidateReport::new(vec![], Err(e));
+        }
+    };
+    let mut valid_configs = Vec::new();
+    let mut reports = Vec::new();
+    for (config_loc, outcome) in config_locs.iter().zip(outcomes) {
+        match outcome {
+            Ok(ValidateOutcome::Valid(config, warnings)) => {
+                reports.push(ConfigReport::new_valid(config_loc, &config, warnings));
+                valid_configs.push(*config);
+            }
+            Ok(ValidateOutcome::FetchFailed(failure)) => {
+                reports.push(ConfigReport::new_invalid(
+                    config_loc,
+                    &anyhow!("Failed to fetch {}", failure),
+                ));
+            }
+            Err(e) => {
+                error!("{} to validate {}: {}", "Failed".red(), config_loc, e);
+                reports.push(ConfigReport::new_invalid(config_loc, &e));
+            }
+        }
+    }
+    let mut report = ValidateReport::new(reports, validate_across_configs(&valid_configs));
+    report.warnings.splice(0..0, run_warnings);
+    report
+}
 
-        configs.push(config);
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+fn validate_config(
+    http: &remote::Http,
+    config_loc: &str,
+    gh_token: Option<&str>,
+    strict: bool,
+    validate_params: bool,
+    show_url_changes: bool,
+    memo: &Memo,
+    deep: &Option<Vec<(config::types::LanguageType, String)>>,
+) -> Result<ValidateOutcome> {
+    info!("Validating {}", config_loc);
+    // A failure to fetch one config does not abort the others, reported at the end.
+    let content = match config::io::read_config_content(http, config_loc) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("{} to fetch {}: {}", "Failed".red(), config_loc, e);
+            return Ok(ValidateOutcome::FetchFailed(format!(
+                "{}: {}",
+                config_loc, e
+            )));
+        }
+    };
+    let (config, warnings) = validate_content(
+        http,
+        config_loc,
+        &content,
+        gh_token,
+        strict,
+        validate_params,
+        show_url_changes,
+        memo,
+        deep,
+    )
+    .map_err(|e| anyhow::Error::new(ConfigError::new(config_loc, e)))?;
+    Ok(ValidateOutcome::Valid(Box::new(config), warnings))
+}
+
+/// The errors are wrapped in `ConfigError` by the caller to carry `config_loc`.
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+fn validate_content(
+    http: &remote::Http,
+    config_loc: &str,
+    content: &str,
+    gh_token: Option<&str>,
+    strict: bool,
+    validate_params: bool,
+    show_url_changes: bool,
+    memo: &Memo,
+    deep: &Option<Vec<(config::types::LanguageType, String)>>,
+) -> Result<(config::types::Config, Vec<Warning>)> {
+    let (mut config, mut warnings) =
+        config::io::config_from_content(http, config_loc, content, strict)?;
+
+    validate_authors(&config.authors)?;
+    validate_language(&config.workflow.language)?;
+    validate_wf_name(&config.workflow.name, strict)?;
+    let wf_name = normalize_wf_name(&config.workflow.name);
+    if wf_name != config.workflow.name {
+        warnings.push(
+            Warning::emit(
+                "workflow.name",
+                format!(
+                    "Workflow name is normalized from `{}` to `{}`",
+                    config.workflow.name, wf_name
+                ),
+            )
+            .with_location(config_loc),
+        );
+        config.workflow.name = wf_name;
     }
-    Ok(configs)
+    match gh_token {
+        Some(gh_token) => {
+            let before = config.clone();
+            validate_and_update_workflow(http, &gh_token, &mut config, memo)?;
+            for (path, from, to) in url_changes(&before, &config) {
+                if show_url_changes {
+                    info!("{}: {} -> {}", path, from, to);
+                } else {
+                    debug!("{}: {} -> {}", path, from, to);
+                }
+            }
+        }
+        None => validate_workflow_offline(&mut config)?,
+    }
+    warnings.extend(
+        validate_source_commit(&config)?
+            .into_iter()
+            .chain(validate_single_commit(&config, strict)?)
+            .map(|w| w.with_location(config_loc)),
+    );
+    if validate_params {
+        validate_test_params(http, &config.workflow.testing)?;
+    }
+    if let Some(validator_map) = deep {
+        warnings.extend(
+            language_validator::deep_validate(http, &config, validator_map)?
+                .into_iter()
+                .map(|w| w.with_location(config_loc)),
+        );
+    }
+
+    debug!("updated config: {:?}", config);
+
+    Ok((config, warnings))
+}
+
+/// Fetch the `wf_params` and `wf_engine_params` files and check that they can be parsed as JSON or YAML.
+pub fn validate_test_params(http: &remote::Http, testing: &[config::types::Testing]) -> Result<()> {
+    for test_case in testing {
+        for file in &test_case.files {
+            if file.r#type == config::types::TestFileType::Other {
+                continue;
+            }
+            let content = remote::fetch_raw_content(http, &file.url)?;
+            parse_params(&content).with_context(|| {
+                format!(
+                    "Failed to parse the {:?} file: {} in test: {} as JSON or YAML",
+                    file.r#type, file.url, test_case.id
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// YAML is a superset of JSON, so both can be parsed with the YAML parser.
+pub fn parse_params(content: impl AsRef<str>) -> Result<serde_yaml::Value> {
+    Ok(serde_yaml::from_str(content.as_ref())?)
+}
+
+/// Cross-config checks for a batch (multiple config locations or `--from-trs`).
+///
+/// - Same `id` and `version`: error (duplicate)
+/// - Same `id`, but different tool-level fields (`workflow.name`, `workflow.language.type`): error
+/// - Same `workflow.name`, but different `id`: warning
+pub fn validate_across_configs(configs: &[config::types::Config]) -> Result<Vec<Warning>> {
+    let mut warnings = Vec::new();
+    let mut id_version_set: HashSet<(config::types::WorkflowId, &str)> = HashSet::new();
+    let mut id_map: HashMap<config::types::WorkflowId, &config::types::Config> = HashMap::new();
+    let mut name_map: HashMap<&str, config::types::WorkflowId> = HashMap::new();
+    for config in configs {
+        ensure!(
+            id_version_set.insert((config.id.clone(), config.version.as_str())),
+            "Duplicate workflow id: {} and version: {} in the configs",
+            config.id,
+            config.version
+        );
+        match id_map.get(&config.id) {
+            Some(prev) => {
+                ensure!(
+                    prev.workflow.name == config.workflow.name,
+                    "Workflow id: {} has different names in the configs: {} and {}",
+                    config.id,
+                    prev.workflow.name,
+                    config.workflow.name
+                );
+                ensure!(
+                    prev.workflow.language.r#type == config.workflow.language.r#type,
+                    "Workflow id: {} has different language types in the configs",
+                    config.id
+                );
+            }
+            None => {
+                id_map.insert(config.id.clone(), config);
+            }
+        }
+        match name_map.get(config.workflow.name.as_str()) {
+            Some(id) if id != &config.id => {
+                warnings.push(Warning::emit(
+                    "workflow.name",
+                    format!(
+                        "Workflow name: {} is shared by different workflow ids: {} and {}",
+                        config.workflow.name, id, config.id
+                    ),
+                ));
+            }
+            Some(_) => {}
+            None => {
+                name_map.insert(config.workflow.name.as_str(), config.id.clone());
+            }
+        }
+    }
+    Ok(warnings)
 }
 
 pub fn validate_authors(authors: &[config::types::Author]) -> Result<()> {
@@ -57,29 +670,71 @@ pub fn validate_language(language: &config::types::Language) -> Result<()> {
     Ok(())
 }
 
+pub const MAX_WF_NAME_LEN: usize = 100;
+
 /// allow characters
 /// - alphabet
 /// - number
 /// - ~!@#$%^&*()_+-={}[]|:;,.<>?
 /// - space
-pub fn validate_wf_name(wf_name: impl AsRef<str>) -> Result<()> {
+///
+/// In strict mode, also reject names longer than `MAX_WF_NAME_LEN`, leading/trailing spaces, and consecutive spaces.
+pub fn validate_wf_name(wf_name: impl AsRef<str>, strict: bool) -> Result<()> {
+    let wf_name = wf_name.as_ref();
     let wf_name_re =
         regex::Regex::new(r"^[a-zA-Z0-9\~!@\#\$%\^\&\*\(\)_\+\-=\{\}\[\]\|:;,\.<>\? ]+$")?;
     ensure!(
-        wf_name_re.is_match(wf_name.as_ref()),
+        wf_name_re.is_match(wf_name),
         "Workflow name contains invalid characters, only alphanumeric, space and ~!@#$%^&*()_+-={{}}[]|:;,.<>? are allowed"
     );
+    if strict {
+        ensure!(
+            wf_name.len() <= MAX_WF_NAME_LEN,
+            "Workflow name is too long ({} characters), the maximum is {}",
+            wf_name.len(),
+            MAX_WF_NAME_LEN
+        );
+        ensure!(
+            wf_name.trim() == wf_name,
+            "Workflow name must not start or end with spaces"
+        );
+        ensure!(
+            !wf_name.contains("  "),
+            "Workflow name must not contain consecutive spaces"
+        );
+    }
     Ok(())
 }
 
+/// Trim and collapse the whitespace in the workflow name.
+pub fn normalize_wf_name(wf_name: impl AsRef<str>) -> String {
+    wf_name
+        .as_ref()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub fn validate_and_update_workflow(
+    http: &remote::Http,
     gh_token: &impl AsRef<str>,
     config: &mut config::types::Config,
+    memo: &Memo,
 ) -> Result<()> {
-    let mut branch_memo = HashMap::new();
-    let mut commit_memo = HashMap::new();
+    // Work on copies of the shared memo not to hold the locks during the requests.
+    let mut branch_memo = memo
+        .branch
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let mut commit_memo = memo
+        .commit
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
 
     config.workflow.readme = raw_url::RawUrl::new(
+        http,
         gh_token,
         &config.workflow.readme,
         Some(&mut branch_memo),
@@ -88,46 +743,561 @@ pub fn validate_and_update_workflow(
     .context("Failed to convert readme url to raw url")?
     .to_url(&raw_url::UrlType::Commit)?;
 
-    ensure!(
-        config.workflow.primary_wf().is_ok(),
-        "Expected to contain one primary workflow file."
-    );
+    config.workflow.primary_wf()?;
 
     for file in &mut config.workflow.files {
-        file.update_url(gh_token, Some(&mut branch_memo), Some(&mut commit_memo))?;
+        file.update_url(
+            http,
+            gh_token,
+            Some(&mut branch_memo),
+            Some(&mut commit_memo),
+        )?;
         file.complement_target()?;
     }
 
-    let mut test_id_set: HashSet<&str> = HashSet::new();
+    validate_test_ids(&config.workflow.testing)?;
+    validate_test_profiles(&config.workflow.testing)?;
+    validate_inline_test_params(&config.workflow.testing)?;
+    for testing in &mut config.workflow.testing {
+        for file in &mut testing.files {
+            file.update_url(
+                http,
+                gh_token,
+                Some(&mut branch_memo),
+                Some(&mut commit_memo),
+            )?;
+            file.complement_target()?;
+        }
+    }
+
+    memo.branch
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .extend(branch_memo);
+    memo.commit
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .extend(commit_memo);
+
+    Ok(())
+}
+
+/// The checks of `validate_and_update_workflow` that do not access the network.
+/// The URLs are left as they are (not pinned to commits).
+pub fn validate_workflow_offline(config: &mut config::types::Config) -> Result<()> {
+    config.workflow.primary_wf()?;
+    for file in &mut config.workflow.files {
+        file.complement_target()?;
+    }
+    validate_test_ids(&config.workflow.testing)?;
+    validate_test_profiles(&config.workflow.testing)?;
+    validate_inline_test_params(&config.workflow.testing)?;
     for testing in &mut config.workflow.testing {
+        for file in &mut testing.files {
+            file.complement_target()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_test_ids(testing: &[config::types::Testing]) -> Result<()> {
+    let mut test_id_set: HashSet<&str> = HashSet::new();
+    for test_case in testing {
         ensure!(
-            !test_id_set.contains(testing.id.as_str()),
+            test_id_set.insert(test_case.id.as_str()),
             "Duplicate test id: {}",
-            testing.id.as_str()
+            test_case.id.as_str()
         );
-        test_id_set.insert(testing.id.as_str());
+    }
+    Ok(())
+}
 
-        for file in &mut testing.files {
-            file.update_url(gh_token, Some(&mut branch_memo), Some(&mut commit_memo))?;
-            file.complement_target()?;
+/// `source_commit` must be a commit hash, and the workflow files in the repo of the primary workflow
+/// pinned to another commit are warned, as they are not captured at the same commit.
+pub fn validate_source_commit(config: &config::types::Config) -> Result<Vec<Warning>> {
+    let source_commit = match &config.source_commit {
+        Some(source_commit) => source_commit,
+        None => return Ok(vec![]),
+    };
+    raw_url::is_commit_hash(source_commit)
+        .with_context(|| format!("Invalid source_commit: {}", source_commit))?;
+    let repo = match raw_url::RawUrl::owner_name(&config.workflow.primary_wf()?.url) {
+        Ok(repo) => repo,
+        Err(_) => return Ok(vec![]),
+    };
+    let mut warnings = Vec::new();
+    for (i, file) in config.workflow.files.iter().enumerate() {
+        if let Some((owner, name, commit, _)) = remote::parse_raw_github_url(&file.url) {
+            if (owner, name) == repo
+                && raw_url::is_commit_hash(&commit).is_ok()
+                && &commit != source_commit
+            {
+                warnings.push(Warning::emit(
+                    format!("workflow.files.{}.url", i),
+                    format!(
+                        "File is taken from commit {}, not from the source_commit {}",
+                        commit, source_commit
+                    ),
+                ));
+            }
         }
     }
+    Ok(warnings)
+}
 
+/// The workflow files of a repo should be captured at one commit, otherwise "the workflow at commit X" is ambiguous.
+/// The files pinned to different commits in a repo are warned (an error if `strict`), listing the distinct commits.
+/// Files in other repos (e.g., remote WDL imports) are compared only with the files of the same repo.
+pub fn validate_single_commit(
+    config: &config::types::Config,
+    strict: bool,
+) -> Result<Vec<Warning>> {
+    let mut repo_commits: BTreeMap<(String, String), BTreeSet<String>> = BTreeMap::new();
+    for file in &config.workflow.files {
+        if let Some((owner, name, commit, _)) = remote::parse_raw_github_url(&file.url) {
+            if raw_url::is_commit_hash(&commit).is_ok() {
+                repo_commits
+                    .entry((owner, name))
+                    .or_default()
+                    .insert(commit);
+            }
+        }
+    }
+    let mut warnings = Vec::new();
+    for ((owner, name), commits) in repo_commits {
+        if commits.len() <= 1 {
+            continue;
+        }
+        let message = format!(
+            "The workflow files in {}/{} are taken from {} commits: {}",
+            owner,
+            name,
+            commits.len(),
+            commits.into_iter().collect::<Vec<_>>().join(", ")
+        );
+        if strict {
+            bail!(message);
+        }
+        warnings.push(Warning::emit("workflow.files", message));
+    }
+    Ok(warnings)
+}
+
+/// The inline `wf_params` and `wf_engine_params` must parse as a mapping,
+/// and can not be given together with the file of the same type.
+pub fn validate_inline_test_params(testing: &[config::types::Testing]) -> Result<()> {
+    for test_case in testing {
+        let inline_params = [
+            (&test_case.wf_params, config::types::TestFileType::WfParams),
+            (
+                &test_case.wf_engine_params,
+                config::types::TestFileType::WfEngineParams,
+            ),
+        ];
+        for (params, file_type) in inline_params {
+            let params = match params {
+                Some(params) => params,
+                None => continue,
+            };
+            ensure!(
+                !test_case.files.iter().any(|f| f.r#type == file_type),
+                "Both the inline {:?} and the {:?} file are given in test case: {}",
+                file_type,
+                file_type,
+                test_case.id
+            );
+            config::types::inline_params_to_json(params).with_context(|| {
+                format!(
+                    "Failed to parse the inline {:?} in test case: {} as JSON or YAML",
+                    file_type, test_case.id
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// A profile name is made of alphanumerics, `-`, and `_`, and is not repeated in a test case.
+pub fn validate_test_profiles(testing: &[config::types::Testing]) -> Result<()> {
+    for test_case in testing {
+        let mut profile_set: HashSet<&str> = HashSet::new();
+        for profile in &test_case.profiles {
+            ensure!(
+                !profile.is_empty()
+                    && profile
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+                "Invalid profile `{}` in test case: {} (only alphanumerics, `-`, and `_` are allowed)",
+                profile,
+                test_case.id
+            );
+            ensure!(
+                profile_set.insert(profile.as_str()),
+                "Duplicate profile `{}` in test case: {}",
+                profile,
+                test_case.id
+            );
+        }
+    }
     Ok(())
 }
 
+/// The URLs rewritten by `validate_and_update_workflow` (e.g., a branch resolved to a commit), as (path, before, after).
+pub fn url_changes(
+    before: &config::types::Config,
+    after: &config::types::Config,
+) -> Vec<(String, Url, Url)> {
+    let mut changes = Vec::new();
+    if before.workflow.readme != after.workflow.readme {
+        changes.push((
+            "workflow.readme".to_string(),
+            before.workflow.readme.clone(),
+            after.workflow.readme.clone(),
+        ));
+    }
+    for (i, (from, to)) in before
+        .workflow
+        .files
+        .iter()
+        .zip(after.workflow.files.iter())
+        .enumerate()
+    {
+        if from.url != to.url {
+            changes.push((
+                format!("workflow.files.{}.url", i),
+                from.url.clone(),
+                to.url.clone(),
+            ));
+        }
+    }
+    for (i, (from_testing, to_testing)) in before
+        .workflow
+        .testing
+        .iter()
+        .zip(after.workflow.testing.iter())
+        .enumerate()
+    {
+        for (j, (from, to)) in from_testing
+            .files
+            .iter()
+            .zip(to_testing.files.iter())
+            .enumerate()
+        {
+            if from.url != to.url {
+                changes.push((
+                    format!("workflow.testing.{}.files.{}.url", i, j),
+                    from.url.clone(),
+                    to.url.clone(),
+                ));
+            }
+        }
+    }
+    changes
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_validate_wf_name() -> Result<()> {
-        validate_wf_name("abc")?;
-        validate_wf_name("abcABC123")?;
-        validate_wf_name("abcABC123~!@#$%^&*()_+-={{}}[]|:;,.<>? ")?;
-        validate_wf_name("Workflow name: example_workflow-123.cwl (for example)")?;
-        let err = validate_wf_name("`");
+        validate_wf_name("abc", false)?;
+        validate_wf_name("abcABC123", false)?;
+        validate_wf_name("abcABC123~!@#$%^&*()_+-={{}}[]|:;,.<>? ", false)?;
+        validate_wf_name(
+            "Workflow name: example_workflow-123.cwl (for example)",
+            false,
+        )?;
+        let err = validate_wf_name("`", false);
+        assert!(err.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_wf_name_strict() -> Result<()> {
+        validate_wf_name("Workflow name: example_workflow-123.cwl", true)?;
+        validate_wf_name("trailing space ", false)?;
+        let err = validate_wf_name("trailing space ", true).unwrap_err();
+        assert!(err.to_string().contains("start or end with spaces"));
+        let err = validate_wf_name("consecutive  spaces", true).unwrap_err();
+        assert!(err.to_string().contains("consecutive spaces"));
+        let long_name = "a".repeat(MAX_WF_NAME_LEN + 1);
+        validate_wf_name(&long_name, false)?;
+        let err = validate_wf_name(&long_name, true).unwrap_err();
+        assert!(err.to_string().contains("too long"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_wf_name() {
+        assert_eq!(
+            normalize_wf_name("  trailing  and leading "),
+            "trailing and leading"
+        );
+        assert_eq!(normalize_wf_name("clean name"), "clean name");
+    }
+
+    #[test]
+    fn test_parse_params() -> Result<()> {
+        parse_params(r#"{"fastq_1": {"class": "File", "location": "a.fq"}}"#)?;
+        parse_params("fastq_1:\n  class: File\n  location: a.fq\n")?;
+        assert!(parse_params(r#"{"fastq_1": {"class": "File""#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_across_configs() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+
+        let mut new_version = config.clone();
+        new_version.version = "2.0.0".to_string();
+        validate_across_configs(&[config.clone(), new_version.clone()])?;
+
+        let err = validate_across_configs(&[config.clone(), config.clone()]);
         assert!(err.is_err());
+
+        let mut renamed = new_version;
+        renamed.workflow.name = "renamed".to_string();
+        let err = validate_across_configs(&[config.clone(), renamed]);
+        assert!(err.is_err());
+
+        let mut other_id = config.clone();
+        other_id.id = config::types::WorkflowId::new_v4();
+        let warnings = validate_across_configs(&[config, other_id])?;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "workflow.name");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_workflow_offline() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let mut config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        validate_workflow_offline(&mut config)?;
+
+        let mut duplicated = config.clone();
+        let test_case = duplicated.workflow.testing[0].clone();
+        duplicated.workflow.testing.push(test_case);
+        assert!(validate_workflow_offline(&mut duplicated).is_err());
+
+        let mut no_primary = config;
+        no_primary.workflow.files.retain(|f| !f.is_primary());
+        assert!(validate_workflow_offline(&mut no_primary).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_report() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let configs = vec![
+            ConfigReport::new_valid(
+                "a.yml",
+                &config,
+                vec![Warning::emit("lisence", "Unknown field is ignored").with_location("a.yml")],
+            ),
+            ConfigReport::new_invalid("b.yml", &anyhow!("No authors found in config file")),
+        ];
+        let report = ValidateReport::new(configs, validate_across_configs(&[config]));
+        assert!(!report.valid);
+        let json = serde_json::to_value(&report)?;
+        assert_eq!(json["configs"][0]["valid"], true);
+        assert_eq!(json["configs"][0]["name"], "trimming_and_qc");
+        assert_eq!(json["configs"][0]["file_count"], 3);
+        assert_eq!(json["configs"][0]["warnings"][0]["path"], "lisence");
+        assert_eq!(json["configs"][1]["valid"], false);
+        assert_eq!(
+            json["configs"][1]["error"],
+            "No authors found in config file"
+        );
+        assert_eq!(json["error"], serde_json::Value::Null);
+
+        let report = ValidateReport::new(vec![], Err(anyhow!("Duplicate workflow id")));
+        assert!(!report.valid);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_source_commit() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let mut config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        assert!(validate_source_commit(&config)?.is_empty());
+        config.source_commit = Some("458d0524e667f2442a5effb730b523c1f15748d4".to_string());
+        assert!(validate_source_commit(&config)?.is_empty());
+
+        config.workflow.files[0].url = Url::parse(
+            "https://raw.githubusercontent.com/suecharo/gh-trs/4e7e2e3ddb42bdaaf5e294f4bf67319f23c4eaa4/tests/CWL/wf/fastqc.cwl",
+        )?;
+        let warnings = validate_source_commit(&config)?;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "workflow.files.0.url");
+
+        config.source_commit = Some("main".to_string());
+        assert!(validate_source_commit(&config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_single_commit() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let mut config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        assert!(validate_single_commit(&config, true)?.is_empty());
+
+        config.workflow.files[0].url = Url::parse(
+            "https://raw.githubusercontent.com/suecharo/gh-trs/4e7e2e3ddb42bdaaf5e294f4bf67319f23c4eaa4/tests/CWL/wf/fastqc.cwl",
+        )?;
+        let warnings = validate_single_commit(&config, false)?;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains(
+            "2 commits: 458d0524e667f2442a5effb730b523c1f15748d4, 4e7e2e3ddb42bdaaf5e294f4bf67319f23c4eaa4"
+        ));
+        assert!(validate_single_commit(&config, true).is_err());
+
+        // A file in another repo is not compared.
+        config.workflow.files[0].url = Url::parse(
+            "https://raw.githubusercontent.com/other/repo/4e7e2e3ddb42bdaaf5e294f4bf67319f23c4eaa4/fastqc.cwl",
+        )?;
+        assert!(validate_single_commit(&config, true)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_inline_test_params() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let yaml = r#"
+id: test_inline
+wf_params:
+  fastq_1:
+    class: File
+    location: ERR034597_1.small.fq.gz
+wf_engine_params: '{"--debug": true}'
+files: []
+"#;
+        let mut testing: config::types::Testing = serde_yaml::from_str(yaml)?;
+        validate_inline_test_params(std::slice::from_ref(&testing))?;
+        assert_eq!(
+            testing.wf_params(&http)?,
+            r#"{"fastq_1":{"class":"File","location":"ERR034597_1.small.fq.gz"}}"#
+        );
+        assert_eq!(testing.wf_engine_params(&http)?, r#"{"--debug":true}"#);
+
+        testing.wf_engine_params = Some(serde_json::Value::String("- a\n- b".to_string()));
+        assert!(validate_inline_test_params(std::slice::from_ref(&testing)).is_err());
+        testing.wf_engine_params = Some(serde_json::Value::String("{not closed".to_string()));
+        assert!(validate_inline_test_params(std::slice::from_ref(&testing)).is_err());
+
+        testing.wf_engine_params = None;
+        testing.files = config::types::Testing::default().files;
+        let err = validate_inline_test_params(&[testing]).unwrap_err();
+        assert!(err.to_string().contains("Both the inline"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_test_profiles() -> Result<()> {
+        let mut testing = config::types::Testing {
+            profiles: vec!["ci".to_string(), "nightly-full".to_string()],
+            ..Default::default()
+        };
+        validate_test_profiles(std::slice::from_ref(&testing))?;
+        assert!(testing.has_profile(&Some("ci")));
+        assert!(!testing.has_profile(&Some("cd")));
+        assert!(testing.has_profile(&None::<String>));
+
+        testing.profiles.push("ci".to_string());
+        let err = validate_test_profiles(std::slice::from_ref(&testing)).unwrap_err();
+        assert!(err.to_string().contains("Duplicate profile"));
+        testing.profiles = vec!["ci stage".to_string()];
+        assert!(validate_test_profiles(&[testing]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_memo_extend() {
+        let memo = Memo::default();
+        memo.branch
+            .lock()
+            .unwrap()
+            .insert("o/n".to_string(), "main".to_string());
+        let other = Memo::default();
+        other
+            .branch
+            .lock()
+            .unwrap()
+            .insert("o/n".to_string(), "master".to_string());
+        other
+            .commit
+            .lock()
+            .unwrap()
+            .insert("o/n/main".to_string(), "abc".to_string());
+        memo.extend(other);
+        assert_eq!(memo.branch.lock().unwrap()["o/n"], "main");
+        assert_eq!(memo.commit.lock().unwrap()["o/n/main"], "abc");
+    }
+
+    #[test]
+    fn test_lookup_cache() -> Result<()> {
+        let path = std::env::temp_dir().join("gh-trs-test-lookup-cache.json");
+        let now = Utc::now();
+        let memo = Memo::default();
+        memo.branch
+            .lock()
+            .unwrap()
+            .insert("suecharo/gh-trs".to_string(), "main".to_string());
+        memo.commit.lock().unwrap().insert(
+            "suecharo/gh-trs/main".to_string(),
+            "f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9".to_string(),
+        );
+
+        let mut cache = LookupCache::default();
+        cache.update(&memo, now - Duration::seconds(BRANCH_HEAD_TTL_SECS + 1));
+        cache.save(&path)?;
+        let (mut cache, warnings) = LookupCache::load(&path)?;
+        assert!(warnings.is_empty());
+        assert_eq!(cache.branch.len(), 1);
+        assert!(cache.commit.is_empty());
+
+        cache.update(&memo, now);
+        let memo = cache.to_memo();
+        assert_eq!(
+            memo.commit.lock().unwrap().get("suecharo/gh-trs/main"),
+            Some(&"f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9f9".to_string())
+        );
+
+        cache.expire(now + Duration::seconds(DEFAULT_BRANCH_TTL_SECS));
+        assert_eq!(cache, LookupCache::default());
+
+        // A broken cache is ignored with a warning.
+        fs::write(&path, "{")?;
+        let (cache, warnings) = LookupCache::load(&path)?;
+        assert_eq!(cache, LookupCache::default());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "--cache");
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_changes() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let after = config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        assert!(url_changes(&after, &after).is_empty());
+
+        let mut before = after.clone();
+        before.workflow.files[1].url = Url::parse(
+            "https://github.com/suecharo/gh-trs/blob/main/tests/CWL/wf/trimming_and_qc.cwl",
+        )?;
+        let changes = url_changes(&before, &after);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "workflow.files.1.url");
+        assert_eq!(changes[0].1, before.workflow.files[1].url);
+        assert_eq!(changes[0].2, after.workflow.files[1].url);
         Ok(())
     }
 }
@@ -0,0 +1,133 @@
+use crate::command::publish;
+use crate::config;
+use crate::env;
+use crate::github_api;
+use crate::remote;
+use crate::trs;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use log::info;
+use std::time::Duration;
+use url::Url;
+
+/// Verify that the versions of the configs are served by the published TRS, e.g., right after `publish`.
+/// Unless `wait` is false, the GitHub Pages build of the head of `branch` is polled first (up to `timeout`),
+/// so that a build still in progress is not reported as a missing version.
+/// Returns the verified `<id>@<version>`s.
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn verify(
+    http: &remote::Http,
+    config_locs: &[impl AsRef<str>],
+    gh_token: &Option<impl AsRef<str>>,
+    repo: &Option<String>,
+    branch: impl AsRef<str>,
+    base_url: &Option<Url>,
+    cname: &Option<String>,
+    wait: bool,
+    timeout: Duration,
+) -> Result<Vec<String>> {
+    let configs = config_locs
+        .iter()
+        .map(|loc| config::io::read_config(http, loc, false))
+        .collect::<Result<Vec<_>>>()?;
+    let (owner, name) = match repo {
+        Some(repo) => github_api::parse_repo(repo)?,
+        None => publish::infer_repo(&configs)?,
+    };
+    if wait {
+        let gh_token = env::github_token(gh_token)?;
+        let commit_sha = github_api::get_latest_commit_sha(
+            http,
+            &gh_token,
+            &owner,
+            &name,
+            branch.as_ref(),
+            None,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to get the head of the branch {} of {}/{}",
+                branch.as_ref(),
+                owner,
+                name
+            )
+        })?;
+        publish::wait_pages_build(http, &gh_token, &owner, &name, &commit_sha, timeout)?;
+    }
+
+    let trs_endpoint = trs::api::TrsEndpoint::new_from_url(&publish::resolve_trs_url(
+        &owner, &name, base_url, cname,
+    )?)?;
+    info!("Verifying the TRS {}", trs_endpoint.url);
+    trs_endpoint.is_valid(http)?;
+    let mut verified = vec![];
+    for config in &configs {
+        let versions = trs_endpoint.find_versions(http, config.id.to_string())?;
+        check_published_version(config, &versions)?;
+        let desc_type = config
+            .workflow
+            .language
+            .r#type
+            .clone()
+            .ok_or_else(|| anyhow!("Workflow language type must be set"))?;
+        let files = trs::api::get_tool_files(
+            http,
+            &trs_endpoint,
+            config.id.to_string(),
+            &config.version,
+            desc_type.to_string(),
+        )?;
+        ensure!(
+            !files.is_empty(),
+            "No files are served for workflow {} version {}",
+            config.id,
+            config.version
+        );
+        verified.push(format!("{}@{}", config.id, config.version));
+    }
+    Ok(verified)
+}
+
+/// The version of the config must be in the versions of the workflow served by the TRS (None if the tool is not served).
+pub fn check_published_version(
+    config: &config::types::Config,
+    versions: &Option<Vec<String>>,
+) -> Result<()> {
+    let versions = versions
+        .as_ref()
+        .ok_or_else(|| anyhow!("Workflow {} is not served by the TRS", config.id))?;
+    ensure!(
+        versions.contains(&config.version),
+        "Version {} of workflow {} is not served by the TRS (served versions: {})",
+        config.version,
+        config.id,
+        versions.join(", ")
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_published_version() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        assert!(check_published_version(
+            &config,
+            &Some(vec!["0.9.0".to_string(), "1.0.0".to_string()])
+        )
+        .is_ok());
+
+        let err = check_published_version(&config, &Some(vec!["0.9.0".to_string()])).unwrap_err();
+        assert!(err.to_string().contains("served versions: 0.9.0"));
+
+        let err = check_published_version(&config, &None).unwrap_err();
+        assert!(err.to_string().contains("is not served by the TRS"));
+        Ok(())
+    }
+}
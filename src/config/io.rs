@@ -1,22 +1,39 @@
 use crate::config;
 use crate::remote;
 use crate::trs;
+use crate::warning::Warning;
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use log::debug;
+use regex::Regex;
 use serde_json;
 use serde_yaml;
 use std::fs;
-use std::io::BufReader;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use url::Url;
 
+#[derive(Debug, PartialEq, Clone)]
 pub enum FileExt {
     Yaml,
     Json,
 }
 
+impl std::str::FromStr for FileExt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "yaml" | "yml" => Ok(FileExt::Yaml),
+            "json" => Ok(FileExt::Json),
+            _ => bail!(
+                "Unsupported format: {}, only yaml and json are supported",
+                s
+            ),
+        }
+    }
+}
+
 pub fn parse_file_ext(path: impl AsRef<Path>) -> Result<FileExt> {
     match path.as_ref().extension() {
         Some(ext) => match ext.to_str() {
@@ -30,6 +47,7 @@ pub fn parse_file_ext(path: impl AsRef<Path>) -> Result<FileExt> {
     }
 }
 
+/// Write the config in the format of `ext` to the path, or to stdout if the path is `-`.
 pub fn write_config(
     config: &config::types::Config,
     path: impl AsRef<Path>,
@@ -39,37 +57,561 @@ pub fn write_config(
         FileExt::Yaml => serde_yaml::to_string(&config)?,
         FileExt::Json => serde_json::to_string_pretty(&config)?,
     };
+    write_content(content, path)
+}
+
+/// Write the content as is to the path, or to stdout if the path is `-`.
+/// The parent directories of the path are created if they do not exist.
+pub fn write_content(content: impl AsRef<str>, path: impl AsRef<Path>) -> Result<()> {
+    let content = content.as_ref();
+    if path.as_ref() == Path::new("-") {
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        handle.write_all(content.as_bytes())?;
+        handle.write_all(b"\n")?;
+        return Ok(());
+    }
+    if let Some(parent) = path.as_ref().parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create the directory: {}", parent.display()))?;
+    }
     let mut buffer = BufWriter::new(fs::File::create(path)?);
     buffer.write_all(content.as_bytes())?;
 
     Ok(())
 }
 
-pub fn read_config(location: impl AsRef<str>) -> Result<config::types::Config> {
-    match Url::parse(location.as_ref()) {
-        Ok(url) => {
-            // as remote url
-            // Even json can be read with yaml reader
-            let content = remote::fetch_json_content(&url)?;
-            Ok(serde_yaml::from_str(&content)?)
+/// Replace the scalar values in the YAML content as text, keeping the comments and the layout of the rest.
+/// Each value is replaced only where it appears as a whole scalar (plain or quoted), not as a part of a longer one.
+pub fn replace_scalars(
+    content: impl AsRef<str>,
+    replacements: &[(String, String)],
+) -> Result<String> {
+    let mut content = content.as_ref().to_string();
+    for (from, to) in replacements {
+        let pattern = Regex::new(&format!(
+            r#"(?m)(^|[\s"'\[,])({})(["'\],]|\s|$)"#,
+            regex::escape(from)
+        ))?;
+        content = pattern
+            .replace_all(&content, |caps: &regex::Captures| {
+                format!("{}{}{}", &caps[1], to, &caps[3])
+            })
+            .to_string();
+    }
+    Ok(content)
+}
+
+/// If `strict` is true, unknown fields and a newer schema version than supported are errors.
+pub fn read_config(
+    http: &remote::Http,
+    location: impl AsRef<str>,
+    strict: bool,
+) -> Result<config::types::Config> {
+    // Even json can be read with yaml reader
+    let content = read_config_content(http, &location)?;
+    let (config, _) = config_from_content(http, location, &content, strict)?;
+    Ok(config)
+}
+
+/// Resolve `extends` of the content read from `location` by `read_config_content`, parse it, and check its schema version.
+/// The warnings found on the way are returned with `location` attached.
+pub fn config_from_content(
+    http: &remote::Http,
+    location: impl AsRef<str>,
+    content: impl AsRef<str>,
+    strict: bool,
+) -> Result<(config::types::Config, Vec<Warning>)> {
+    let content = resolve_extends(http, &location, content)?;
+    let (config, mut warnings) = parse_config_with_warnings(content, strict)?;
+    warnings.extend(check_schema_version(&config, strict)?);
+    Ok((
+        config,
+        warnings
+            .into_iter()
+            .map(|w| w.with_location(&location))
+            .collect(),
+    ))
+}
+
+/// Key of the base config (local file path or remote URL) that the config extends.
+/// A relative path is resolved against the location of the extending config.
+pub const EXTENDS_KEY: &str = "extends";
+
+/// Maximum length of a chain of `extends`, so that a cycle missed by the location check cannot recurse forever.
+pub const MAX_EXTENDS_DEPTH: usize = 16;
+
+/// Deep-merge the base configs referenced by `extends` (recursively) into the content.
+/// Fields of the extending config take precedence. Mappings are merged key by key, and other values including arrays are replaced as a whole.
+pub fn resolve_extends(
+    http: &remote::Http,
+    location: impl AsRef<str>,
+    content: impl AsRef<str>,
+) -> Result<String> {
+    if !has_extends(&content)? {
+        // Keep the original content so that the lines in parse errors point to the config file.
+        return Ok(content.as_ref().to_string());
+    }
+    let value = resolve_extends_value(http, location.as_ref(), content.as_ref(), &mut Vec::new())?;
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+/// Check if the content (not resolved yet) extends a base config.
+pub fn has_extends(content: impl AsRef<str>) -> Result<bool> {
+    let value: serde_yaml::Value = serde_yaml::from_str(content.as_ref())?;
+    Ok(value.get(EXTENDS_KEY).is_some())
+}
+
+fn resolve_extends_value(
+    http: &remote::Http,
+    location: &str,
+    content: &str,
+    stack: &mut Vec<String>,
+) -> Result<serde_yaml::Value> {
+    let mut value: serde_yaml::Value = serde_yaml::from_str(content)?;
+    let base_loc = match value.as_mapping_mut() {
+        Some(mapping) => match mapping.remove(&serde_yaml::Value::from(EXTENDS_KEY)) {
+            Some(serde_yaml::Value::String(base_loc)) => base_loc,
+            Some(_) => bail!("`{}` in {} must be a string", EXTENDS_KEY, location),
+            None => return Ok(value),
+        },
+        None => return Ok(value),
+    };
+    let base_loc = resolve_location(location, &base_loc)?;
+    stack.push(location.to_string());
+    let base_key = location_key(&base_loc);
+    ensure!(
+        !stack.iter().any(|loc| location_key(loc) == base_key),
+        "Cycle of `{}` found: {} -> {}",
+        EXTENDS_KEY,
+        stack.join(" -> "),
+        base_loc
+    );
+    ensure!(
+        stack.len() <= MAX_EXTENDS_DEPTH,
+        "Too deep `{}` (more than {}): {} -> {}",
+        EXTENDS_KEY,
+        MAX_EXTENDS_DEPTH,
+        stack.join(" -> "),
+        base_loc
+    );
+    let base_content = read_config_content(http, &base_loc)
+        .map_err(|e| anyhow!("Failed to read the base config {}: {}", base_loc, e))?;
+    let base = resolve_extends_value(http, &base_loc, &base_content, stack)?;
+    stack.pop();
+    Ok(merge_value(base, value))
+}
+
+/// The location in a canonical form, so that different spellings of the same config compare equal.
+/// A local path (or a `file://` URL) is canonicalized, and a remote URL is normalized (e.g., `..` segments and the fragment).
+pub fn location_key(location: impl AsRef<str>) -> String {
+    let path = match Url::parse(location.as_ref()) {
+        Ok(url) if url.scheme() == "file" => match url.to_file_path() {
+            Ok(path) => path,
+            Err(_) => return url.to_string(),
+        },
+        Ok(mut url) => {
+            url.set_fragment(None);
+            return url.to_string();
         }
+        Err(_) => Path::new(location.as_ref()).to_path_buf(),
+    };
+    fs::canonicalize(&path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Resolve `target` against the location of the referencing config (remote URL or local file path).
+pub fn resolve_location(location: impl AsRef<str>, target: impl AsRef<str>) -> Result<String> {
+    if Url::parse(target.as_ref()).is_ok() {
+        return Ok(target.as_ref().to_string());
+    }
+    match Url::parse(location.as_ref()) {
+        Ok(url) => Ok(url.join(target.as_ref())?.to_string()),
         Err(_) => {
-            // as local file path
-            let reader = BufReader::new(fs::File::open(location.as_ref())?);
-            Ok(serde_yaml::from_reader(reader)?)
+            let target = Path::new(target.as_ref());
+            if target.is_absolute() {
+                return Ok(target.to_string_lossy().to_string());
+            }
+            let base_dir = Path::new(location.as_ref())
+                .parent()
+                .unwrap_or_else(|| Path::new(""));
+            Ok(base_dir.join(target).to_string_lossy().to_string())
         }
     }
 }
 
-pub fn find_config_loc_recursively_from_trs(trs_loc: impl AsRef<str>) -> Result<Vec<String>> {
-    let trs_endpoint = trs::api::TrsEndpoint::new_from_url(&Url::parse(trs_loc.as_ref())?)?;
-    trs_endpoint.is_valid()?;
-    let config_locs: Vec<String> = trs::api::get_tools(&trs_endpoint)?
-        .into_iter()
-        .flat_map(|tool| tool.versions)
-        .map(|version| version.url)
-        .map(|url| format!("{}/gh-trs-config.json", url.as_str()))
+/// Deep-merge `child` into `base`. Values of `child` take precedence, and arrays are replaced.
+pub fn merge_value(base: serde_yaml::Value, child: serde_yaml::Value) -> serde_yaml::Value {
+    match (base, child) {
+        (serde_yaml::Value::Mapping(mut base), serde_yaml::Value::Mapping(child)) => {
+            for (key, child_value) in child {
+                let merged = match base.remove(&key) {
+                    Some(base_value) => merge_value(base_value, child_value),
+                    None => child_value,
+                };
+                base.insert(key, merged);
+            }
+            serde_yaml::Value::Mapping(base)
+        }
+        (_, child) => child,
+    }
+}
+
+/// Read the raw content of the config as remote url or local file path.
+pub fn read_config_content(http: &remote::Http, location: impl AsRef<str>) -> Result<String> {
+    match Url::parse(location.as_ref()) {
+        Ok(url) => remote::fetch_json_content(http, &url),
+        Err(_) => Ok(fs::read_to_string(location.as_ref())?),
+    }
+}
+
+/// Serde ignores unknown fields by default, so typos in keys are silently dropped.
+/// They are collected with their paths (e.g. `workflow.files.0.taget`) and rejected in strict mode.
+pub fn parse_config(content: impl AsRef<str>, strict: bool) -> Result<config::types::Config> {
+    let (config, _) = parse_config_with_warnings(content, strict)?;
+    Ok(config)
+}
+
+/// Same as `parse_config`, but each ignored unknown field is returned as a warning.
+pub fn parse_config_with_warnings(
+    content: impl AsRef<str>,
+    strict: bool,
+) -> Result<(config::types::Config, Vec<Warning>)> {
+    let deserializer = serde_yaml::Deserializer::from_str(content.as_ref());
+    let mut unknown_fields: Vec<String> = Vec::new();
+    let config: config::types::Config =
+        serde_ignored::deserialize(deserializer, |path| unknown_fields.push(path.to_string()))?;
+    if strict && !unknown_fields.is_empty() {
+        bail!(
+            "Unknown fields found in the config: {}",
+            unknown_fields.join(", ")
+        );
+    }
+    let warnings = unknown_fields
+        .iter()
+        .map(|path| Warning::emit(path, "Unknown field is ignored"))
         .collect();
+    Ok((config, warnings))
+}
+
+pub fn check_schema_version(config: &config::types::Config, strict: bool) -> Result<Vec<Warning>> {
+    if config.schema_version > config::types::SCHEMA_VERSION {
+        let message = format!(
+            "The schema version of the config ({}) is newer than the version supported by this gh-trs ({}). Please update gh-trs.",
+            config.schema_version,
+            config::types::SCHEMA_VERSION
+        );
+        if strict {
+            bail!(message);
+        }
+        return Ok(vec![Warning::emit("schema_version", message)]);
+    }
+    Ok(vec![])
+}
+
+/// Split the TRS location into the TRS endpoint and the optional tool id and version.
+/// The location is the TRS root, a tool URL (`<root>/tools/<id>`), or a tool version URL (`<root>/tools/<id>/versions/<version>`).
+pub fn parse_trs_loc(
+    trs_loc: impl AsRef<str>,
+) -> Result<(trs::api::TrsEndpoint, Option<String>, Option<String>)> {
+    let url = Url::parse(trs_loc.as_ref().trim().trim_end_matches('/'))?;
+    let segments = url
+        .path_segments()
+        .ok_or_else(|| anyhow!("Invalid url: {}", url))?
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    let len = segments.len();
+    if len >= 4 && segments[len - 4] == "tools" && segments[len - 2] == "versions" {
+        let trs_endpoint = trs::api::TrsEndpoint::new_from_tool_version_url(&url)?;
+        Ok((
+            trs_endpoint,
+            Some(segments[len - 3].clone()),
+            Some(segments[len - 1].clone()),
+        ))
+    } else if len >= 2 && segments[len - 2] == "tools" {
+        let mut root = url.clone();
+        root.set_path(&segments[..len - 2].join("/"));
+        let trs_endpoint = trs::api::TrsEndpoint::new_from_url(&root)?;
+        Ok((trs_endpoint, Some(segments[len - 1].clone()), None))
+    } else {
+        let trs_endpoint = trs::api::TrsEndpoint::new_from_url(&url)?;
+        Ok((trs_endpoint, None, None))
+    }
+}
+
+/// Check that the TRS location is reachable and serves a gh-trs TRS, before iterating over it.
+pub fn check_trs_loc(http: &remote::Http, trs_loc: impl AsRef<str>) -> Result<()> {
+    let (trs_endpoint, _, _) = parse_trs_loc(trs_loc)?;
+    trs_endpoint.is_valid(http)
+}
+
+/// The TRS location is expected to be checked with `check_trs_loc` beforehand.
+pub fn find_config_loc_recursively_from_trs(
+    http: &remote::Http,
+    trs_loc: impl AsRef<str>,
+) -> Result<Vec<String>> {
+    let (trs_endpoint, wf_id, wf_version) = parse_trs_loc(trs_loc)?;
+    let config_locs: Vec<String> = match (wf_id, wf_version) {
+        (Some(wf_id), Some(wf_version)) => {
+            ensure!(
+                trs_endpoint
+                    .all_versions(http, &wf_id)?
+                    .contains(&wf_version),
+                "Version {} of tool {} is not found in the TRS endpoint {}",
+                wf_version,
+                wf_id,
+                trs_endpoint.url
+            );
+            vec![trs_endpoint.to_config_url(&wf_id, &wf_version)?.to_string()]
+        }
+        (Some(wf_id), None) => trs::api::get_tool(http, &trs_endpoint, &wf_id)?
+            .versions
+            .into_iter()
+            .map(|version| format!("{}/gh-trs-config.json", version.url.as_str()))
+            .collect(),
+        _ => trs::api::get_tools(http, &trs_endpoint)?
+            .into_iter()
+            .flat_map(|tool| tool.versions)
+            .map(|version| version.url)
+            .map(|url| format!("{}/gh-trs-config.json", url.as_str()))
+            .collect(),
+    };
     debug!("Found config locations: {:?}", config_locs);
     Ok(config_locs)
 }
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_scalars() -> Result<()> {
+        let content = r#"# The primary workflow
+workflow:
+  name: my  workflow # renamed later
+  readme: "https://github.com/o/n/blob/main/README.md"
+  files:
+    - url: https://github.com/o/n/blob/main/wf.cwl
+    - url: https://github.com/o/n/blob/main/wf.cwl.bak
+"#;
+        let replaced = replace_scalars(
+            content,
+            &[
+                ("my  workflow".to_string(), "my workflow".to_string()),
+                (
+                    "https://github.com/o/n/blob/main/README.md".to_string(),
+                    "https://raw.githubusercontent.com/o/n/abc/README.md".to_string(),
+                ),
+                (
+                    "https://github.com/o/n/blob/main/wf.cwl".to_string(),
+                    "https://raw.githubusercontent.com/o/n/abc/wf.cwl".to_string(),
+                ),
+            ],
+        )?;
+        assert_eq!(
+            replaced,
+            r#"# The primary workflow
+workflow:
+  name: my workflow # renamed later
+  readme: "https://raw.githubusercontent.com/o/n/abc/README.md"
+  files:
+    - url: https://raw.githubusercontent.com/o/n/abc/wf.cwl
+    - url: https://github.com/o/n/blob/main/wf.cwl.bak
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_ext_from_str() -> Result<()> {
+        assert_eq!("yaml".parse::<FileExt>()?, FileExt::Yaml);
+        assert_eq!("yml".parse::<FileExt>()?, FileExt::Yaml);
+        assert_eq!("json".parse::<FileExt>()?, FileExt::Json);
+        assert!("txt".parse::<FileExt>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_trs_loc() -> Result<()> {
+        let (trs_endpoint, wf_id, wf_version) =
+            parse_trs_loc("https://suecharo.github.io/gh-trs/")?;
+        assert_eq!(
+            trs_endpoint.url.as_str(),
+            "https://suecharo.github.io/gh-trs/"
+        );
+        assert_eq!(wf_id, None);
+        assert_eq!(wf_version, None);
+
+        let (trs_endpoint, wf_id, wf_version) =
+            parse_trs_loc("https://suecharo.github.io/gh-trs/tools/foo")?;
+        assert_eq!(
+            trs_endpoint.url.as_str(),
+            "https://suecharo.github.io/gh-trs/"
+        );
+        assert_eq!(wf_id, Some("foo".to_string()));
+        assert_eq!(wf_version, None);
+
+        let (trs_endpoint, wf_id, wf_version) =
+            parse_trs_loc("https://suecharo.github.io/gh-trs/tools/foo/versions/1.0.0/")?;
+        assert_eq!(
+            trs_endpoint.url.as_str(),
+            "https://suecharo.github.io/gh-trs/"
+        );
+        assert_eq!(wf_id, Some("foo".to_string()));
+        assert_eq!(wf_version, Some("1.0.0".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_value() -> Result<()> {
+        let base: serde_yaml::Value = serde_yaml::from_str(
+            "license: MIT\nauthors:\n  - github_account: a\nworkflow:\n  name: base\n  readme: https://example.com/README.md\n",
+        )?;
+        let child: serde_yaml::Value =
+            serde_yaml::from_str("authors:\n  - github_account: b\nworkflow:\n  name: child\n")?;
+        let expect: serde_yaml::Value = serde_yaml::from_str(
+            "license: MIT\nauthors:\n  - github_account: b\nworkflow:\n  name: child\n  readme: https://example.com/README.md\n",
+        )?;
+        assert_eq!(merge_value(base, child), expect);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_location() -> Result<()> {
+        assert_eq!(
+            resolve_location("https://example.com/configs/a.yml", "base.yml")?,
+            "https://example.com/configs/base.yml"
+        );
+        assert_eq!(
+            resolve_location("./configs/a.yml", "https://example.com/base.yml")?,
+            "https://example.com/base.yml"
+        );
+        assert_eq!(
+            resolve_location("./configs/a.yml", "base.yml")?,
+            "./configs/base.yml"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_extends() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let dir = std::env::temp_dir().join("gh-trs-test-extends");
+        fs::create_dir_all(&dir)?;
+        let base_path = dir.join("base.yml");
+        let child_path = dir.join("child.yml");
+        fs::write(&base_path, "license: MIT\nversion: 1.0.0\n")?;
+        fs::write(&child_path, "extends: base.yml\nversion: 2.0.0\n")?;
+        let child_loc = child_path.to_string_lossy().to_string();
+        let merged: serde_yaml::Value = serde_yaml::from_str(&resolve_extends(
+            &http,
+            &child_loc,
+            fs::read_to_string(&child_path)?,
+        )?)?;
+        let expect: serde_yaml::Value = serde_yaml::from_str("license: MIT\nversion: 2.0.0\n")?;
+        assert_eq!(merged, expect);
+
+        // cycle
+        fs::write(&base_path, "extends: child.yml\nlicense: MIT\n")?;
+        let err = resolve_extends(&http, &child_loc, fs::read_to_string(&child_path)?).unwrap_err();
+        assert!(err.to_string().contains("Cycle of `extends` found"));
+
+        // cycle through another spelling of the same file
+        fs::create_dir_all(dir.join("sub"))?;
+        fs::write(&base_path, "extends: ./sub/../child.yml\nlicense: MIT\n")?;
+        let err = resolve_extends(&http, &child_loc, fs::read_to_string(&child_path)?).unwrap_err();
+        assert!(err.to_string().contains("Cycle of `extends` found"));
+        let child_url = Url::from_file_path(fs::canonicalize(&child_path)?)
+            .map_err(|_| anyhow!("Invalid path"))?;
+        fs::write(
+            &base_path,
+            format!("extends: {}\nlicense: MIT\n", child_url),
+        )?;
+        let err = resolve_extends(&http, &child_loc, fs::read_to_string(&child_path)?).unwrap_err();
+        assert!(err.to_string().contains("Cycle of `extends` found"));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_extends_too_deep() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let dir = std::env::temp_dir().join("gh-trs-test-extends-deep");
+        fs::create_dir_all(&dir)?;
+        for i in 0..=MAX_EXTENDS_DEPTH + 1 {
+            fs::write(
+                dir.join(format!("{}.yml", i)),
+                format!("extends: {}.yml\nversion: 1.0.{}\n", i + 1, i),
+            )?;
+        }
+        let loc = dir.join("0.yml").to_string_lossy().to_string();
+        let err = resolve_extends(&http, &loc, fs::read_to_string(&loc)?).unwrap_err();
+        assert!(err.to_string().contains("Too deep `extends`"));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_location_key() {
+        assert_eq!(
+            location_key("https://example.com/a/../configs/base.yml#top"),
+            "https://example.com/configs/base.yml"
+        );
+        assert_eq!(
+            location_key("./src/../Cargo.toml"),
+            location_key("Cargo.toml")
+        );
+    }
+
+    #[test]
+    fn test_write_config_nested_dir() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config = read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let dir = std::env::temp_dir().join("gh-trs-test-write-config");
+        let path = dir.join("configs/nested/config.yml");
+        write_config(&config, &path, &FileExt::Yaml)?;
+        assert_eq!(read_config(&http, path.to_string_lossy(), false)?, config);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_config_default_schema_version() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config = read_config(&http, "./tests/test_config_CWL_validated.yml", true)?;
+        assert_eq!(config.schema_version, config::types::SCHEMA_VERSION);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_unknown_fields() -> Result<()> {
+        let content = fs::read_to_string("./tests/test_config_CWL_validated.yml")?
+            .replacen("version: 1.0.0", "version: 1.0.0\nlisence: MIT", 1)
+            .replacen("target: fastqc.cwl", "taget: fastqc.cwl", 1);
+        let (_, warnings) = parse_config_with_warnings(&content, false)?;
+        assert_eq!(
+            warnings.iter().map(|w| w.path.as_str()).collect::<Vec<_>>(),
+            vec!["lisence", "workflow.files.0.taget"]
+        );
+        let err = parse_config(&content, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown fields found in the config: lisence, workflow.files.0.taget"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_schema_version_newer() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let mut config = read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        config.schema_version = config::types::SCHEMA_VERSION + 1;
+        assert_eq!(check_schema_version(&config, false)?.len(), 1);
+        assert!(check_schema_version(&config, true).is_err());
+        Ok(())
+    }
+}
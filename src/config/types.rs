@@ -2,26 +2,106 @@ use crate::github_api;
 use crate::raw_url;
 use crate::remote;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use url::Url;
 use uuid::Uuid;
 
+/// The schema version of the gh-trs configuration file supported by this binary.
+/// Increment it when the shape of `Config` changes.
+pub const SCHEMA_VERSION: u64 = 1;
+
+fn default_schema_version() -> u64 {
+    SCHEMA_VERSION
+}
+
 #[skip_serializing_none]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub id: Uuid,
+    /// Files lacking this field are treated as the current schema version.
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u64,
+    pub id: WorkflowId,
     pub version: String,
+    /// Commit the workflow files were captured at by `make-template --use-commit-url`, for provenance.
+    pub source_commit: Option<String>,
     pub license: Option<String>,
     pub authors: Vec<Author>,
     pub zenodo: Option<Zenodo>,
     pub workflow: Workflow,
 }
 
+/// Id of a workflow: a UUID (generated by `make-template`) or a human-readable slug like `rnaseq-qc`
+/// (lowercase alphanumerics separated by single hyphens), used as is in the TRS URLs (`/tools/<id>`).
+/// A UUID is kept in the canonical (lowercase hyphenated) form.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct WorkflowId(String);
+
+impl WorkflowId {
+    pub fn new_v4() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_uuid(&self) -> bool {
+        Uuid::parse_str(&self.0).is_ok()
+    }
+}
+
+impl FromStr for WorkflowId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(uuid) = Uuid::parse_str(s) {
+            return Ok(Self(uuid.to_string()));
+        }
+        let re = Regex::new(r"^[a-z0-9]+(-[a-z0-9]+)*$")?;
+        ensure!(
+            re.is_match(s),
+            "Invalid id: `{}`. It should be a UUID or a slug of lowercase alphanumerics and hyphens (e.g., `rnaseq-qc`).",
+            s
+        );
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for WorkflowId {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<WorkflowId> for String {
+    fn from(id: WorkflowId) -> Self {
+        id.0
+    }
+}
+
+impl From<Uuid> for WorkflowId {
+    fn from(uuid: Uuid) -> Self {
+        Self(uuid.to_string())
+    }
+}
+
+impl fmt::Display for WorkflowId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Author {
@@ -32,8 +112,8 @@ pub struct Author {
 }
 
 impl Author {
-    pub fn new_from_api(gh_token: impl AsRef<str>) -> Result<Self> {
-        let (github_account, name, affiliation) = github_api::get_author_info(gh_token)?;
+    pub fn new_from_api(http: &remote::Http, gh_token: impl AsRef<str>) -> Result<Self> {
+        let (github_account, name, affiliation) = github_api::get_author_info(http, gh_token)?;
         Ok(Self {
             github_account,
             name: Some(name),
@@ -53,16 +133,56 @@ pub struct Workflow {
 }
 
 impl Workflow {
-    pub fn primary_wf(&self) -> Result<File> {
-        Ok(self
+    /// The one file of `type: primary` in `workflow.files`.
+    pub fn primary_wf(&self) -> std::result::Result<File, PrimaryWfError> {
+        let primaries = self
             .files
             .iter()
-            .find(|f| f.is_primary())
-            .ok_or_else(|| anyhow!("No primary workflow file"))?
-            .clone())
+            .filter(|f| f.is_primary())
+            .collect::<Vec<_>>();
+        match primaries.as_slice() {
+            [] => Err(PrimaryWfError::NotFound),
+            [primary] => Ok((*primary).clone()),
+            _ => Err(PrimaryWfError::Multiple(
+                primaries
+                    .iter()
+                    .map(|f| match &f.target {
+                        Some(target) => target.display().to_string(),
+                        None => f.url.to_string(),
+                    })
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// The reason why `workflow.files` does not have exactly one primary workflow file.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PrimaryWfError {
+    NotFound,
+    /// The targets (or the URLs if the target is not set) of the primary files.
+    Multiple(Vec<String>),
+}
+
+impl fmt::Display for PrimaryWfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrimaryWfError::NotFound => write!(
+                f,
+                "No primary workflow file in workflow.files. Set `type: primary` to the main workflow document."
+            ),
+            PrimaryWfError::Multiple(targets) => write!(
+                f,
+                "Expected one primary workflow file in workflow.files, but found {}: {}. Set `type: secondary` to the others.",
+                targets.len(),
+                targets.join(", ")
+            ),
+        }
     }
 }
 
+impl std::error::Error for PrimaryWfError {}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Language {
     pub r#type: Option<LanguageType>,
@@ -89,6 +209,23 @@ impl fmt::Display for LanguageType {
     }
 }
 
+impl FromStr for LanguageType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "CWL" => Ok(LanguageType::Cwl),
+            "WDL" => Ok(LanguageType::Wdl),
+            "NFL" => Ok(LanguageType::Nfl),
+            "SMK" => Ok(LanguageType::Smk),
+            _ => Err(anyhow!(
+                "Unknown language type: {} (expected one of CWL, WDL, NFL, SMK)",
+                s
+            )),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct File {
     pub url: Url,
@@ -103,7 +240,7 @@ impl File {
             None => url
                 .path_segments()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", url.as_ref()))?
-                .last()
+                .next_back()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", url.as_ref()))?
                 .to_string()
                 .into(),
@@ -123,13 +260,17 @@ impl File {
     /// If it isn't these hosts, raise an error.
     pub fn update_url(
         &mut self,
+        http: &remote::Http,
         gh_token: impl AsRef<str>,
         branch_memo: Option<&mut HashMap<String, String>>,
         commit_memo: Option<&mut HashMap<String, String>>,
     ) -> Result<()> {
-        self.url = raw_url::RawUrl::new(gh_token, &self.url, branch_memo, commit_memo)
+        let mut url = raw_url::RawUrl::new(http, gh_token, &self.url, branch_memo, commit_memo)
             .with_context(|| format!("Failed to update URL: {} to raw URL", self.url.as_ref()))?
             .to_url(&raw_url::UrlType::Commit)?;
+        // The fragment selects an entry of a packed CWL `$graph`, e.g., `packed.cwl#main`.
+        url.set_fragment(self.url.fragment());
+        self.url = url;
         Ok(())
     }
 
@@ -139,7 +280,7 @@ impl File {
                 .url
                 .path_segments()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", self.url.as_ref()))?
-                .last()
+                .next_back()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", self.url.as_ref()))?
                 .to_string()
                 .into();
@@ -159,6 +300,15 @@ pub enum FileType {
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Testing {
     pub id: String,
+    /// Profiles (e.g., `ci`, `nightly`) to select the test case with `test --profile`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// `wf_params` given inline (a mapping, or a JSON or YAML string) instead of a `wf_params` file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wf_params: Option<serde_json::Value>,
+    /// `wf_engine_params` given inline, as `wf_params`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wf_engine_params: Option<serde_json::Value>,
     pub files: Vec<TestFile>,
 }
 
@@ -166,6 +316,9 @@ impl Default for Testing {
     fn default() -> Self {
         Self {
             id: "test_1".to_string(),
+            profiles: vec![],
+            wf_params: None,
+            wf_engine_params: None,
             files: vec![
                 TestFile::new(
                     &Url::parse("https://example.com/path/to/wf_params.json").unwrap(),
@@ -191,29 +344,60 @@ impl Default for Testing {
 }
 
 impl Testing {
-    pub fn wf_params(&self) -> Result<String> {
+    /// All test cases match if `profile` is `None`.
+    pub fn has_profile(&self, profile: &Option<impl AsRef<str>>) -> bool {
+        match profile {
+            Some(profile) => self.profiles.iter().any(|p| p == profile.as_ref()),
+            None => true,
+        }
+    }
+
+    /// The inline `wf_params` is used as is (as JSON), otherwise the `wf_params` file is fetched.
+    pub fn wf_params(&self, http: &remote::Http) -> Result<String> {
+        if let Some(params) = &self.wf_params {
+            return inline_params_to_json(params)
+                .with_context(|| format!("Invalid inline wf_params in test: {}", self.id));
+        }
         match self
             .files
             .iter()
             .find(|f| f.r#type == TestFileType::WfParams)
         {
-            Some(f) => remote::fetch_raw_content(&f.url),
+            Some(f) => remote::fetch_raw_content(http, &f.url),
             None => Ok("{}".to_string()),
         }
     }
 
-    pub fn wf_engine_params(&self) -> Result<String> {
+    pub fn wf_engine_params(&self, http: &remote::Http) -> Result<String> {
+        if let Some(params) = &self.wf_engine_params {
+            return inline_params_to_json(params)
+                .with_context(|| format!("Invalid inline wf_engine_params in test: {}", self.id));
+        }
         match self
             .files
             .iter()
             .find(|f| f.r#type == TestFileType::WfEngineParams)
         {
-            Some(f) => remote::fetch_raw_content(&f.url),
+            Some(f) => remote::fetch_raw_content(http, &f.url),
             None => Ok("{}".to_string()),
         }
     }
 }
 
+/// A string is parsed as JSON or YAML (YAML is a superset of JSON), and the params must be a mapping.
+pub fn inline_params_to_json(params: &serde_json::Value) -> Result<String> {
+    let params = match params {
+        serde_json::Value::String(content) => serde_yaml::from_str(content)?,
+        _ => params.clone(),
+    };
+    ensure!(
+        params.is_object(),
+        "The params must be a mapping, but got: {}",
+        params
+    );
+    Ok(serde_json::to_string(&params)?)
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TestFile {
     pub url: Url,
@@ -228,7 +412,7 @@ impl TestFile {
             None => url
                 .path_segments()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", url.as_ref()))?
-                .last()
+                .next_back()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", url.as_ref()))?
                 .to_string()
                 .into(),
@@ -244,11 +428,14 @@ impl TestFile {
     /// If it isn't these hosts, do nothing.
     pub fn update_url(
         &mut self,
+        http: &remote::Http,
         gh_token: impl AsRef<str>,
         branch_memo: Option<&mut HashMap<String, String>>,
         commit_memo: Option<&mut HashMap<String, String>>,
     ) -> Result<()> {
-        if let Ok(raw_url) = raw_url::RawUrl::new(gh_token, &self.url, branch_memo, commit_memo) {
+        if let Ok(raw_url) =
+            raw_url::RawUrl::new(http, gh_token, &self.url, branch_memo, commit_memo)
+        {
             self.url = raw_url.to_url(&raw_url::UrlType::Commit)?;
         };
         Ok(())
@@ -260,7 +447,7 @@ impl TestFile {
                 .url
                 .path_segments()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", self.url.as_ref()))?
-                .last()
+                .next_back()
                 .ok_or_else(|| anyhow!("Invalid URL: {}", self.url.as_ref()))?
                 .to_string()
                 .into();
@@ -312,6 +499,62 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_primary_wf() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            crate::config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let primary_wf = config.workflow.primary_wf()?;
+        assert!(primary_wf.is_primary());
+
+        let mut multiple = config.workflow.clone();
+        for file in &mut multiple.files {
+            file.r#type = FileType::Primary;
+        }
+        match multiple.primary_wf() {
+            Err(PrimaryWfError::Multiple(targets)) => {
+                assert_eq!(targets.len(), config.workflow.files.len())
+            }
+            other => panic!("Unexpected: {:?}", other),
+        }
+
+        let mut none = config.workflow;
+        none.files.retain(|f| !f.is_primary());
+        assert_eq!(none.primary_wf(), Err(PrimaryWfError::NotFound));
+        Ok(())
+    }
+
+    #[test]
+    fn test_workflow_id_from_str() -> Result<()> {
+        let id = WorkflowId::from_str("rnaseq-qc")?;
+        assert_eq!(id.as_str(), "rnaseq-qc");
+        assert!(!id.is_uuid());
+        let id = WorkflowId::from_str("C9B4BF5B-0C5B-4B3C-8E2A-5D5A4F0E7C11")?;
+        assert_eq!(id.as_str(), "c9b4bf5b-0c5b-4b3c-8e2a-5d5a4f0e7c11");
+        assert!(id.is_uuid());
+        assert!(WorkflowId::new_v4().is_uuid());
+        for invalid in &[
+            "",
+            "RNAseq",
+            "rnaseq_qc",
+            "-rnaseq",
+            "rnaseq-",
+            "rna--seq",
+            "rna/seq",
+        ] {
+            assert!(WorkflowId::from_str(invalid).is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_workflow_id_serde() -> Result<()> {
+        let id: WorkflowId = serde_yaml::from_str("rnaseq-qc")?;
+        assert_eq!(serde_json::to_string(&id)?, "\"rnaseq-qc\"");
+        assert!(serde_yaml::from_str::<WorkflowId>("RNAseq QC").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_testing_default() -> Result<()> {
         let testing = Testing::default();
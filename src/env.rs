@@ -1,42 +1,112 @@
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use dotenv::dotenv;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 use url::Url;
 
+/// Precedence: `--gh-token` > `--github-token-file` > `GITHUB_TOKEN_FILE` > `GITHUB_TOKEN`
 #[cfg(not(tarpaulin_include))]
 pub fn github_token(arg_token: &Option<impl AsRef<str>>) -> Result<String> {
     dotenv().ok();
     match arg_token {
         Some(token) => Ok(token.as_ref().to_string()),
-        None => match env::var("GITHUB_TOKEN") {
-            Ok(token) => Ok(token),
-            Err(_) => bail!("No GitHub token provided. Please set the environment variable `GITHUB_TOKEN` or pass the `--gh-token` flag."),
+        None => match env::var("GITHUB_TOKEN_FILE") {
+            Ok(token_file) => read_token_file(token_file),
+            Err(_) => match env::var("GITHUB_TOKEN") {
+                Ok(token) => Ok(token),
+                Err(_) => bail!("No GitHub token provided. Please set the environment variable `GITHUB_TOKEN` or `GITHUB_TOKEN_FILE`, or pass the `--gh-token` or `--github-token-file` flag."),
+            },
         },
     }
 }
 
+/// Precedence: `--workflowhub-token` > `WORKFLOWHUB_API_TOKEN`
 #[cfg(not(tarpaulin_include))]
-pub fn sapporo_run_dir() -> Result<String> {
+pub fn workflowhub_token(arg_token: &Option<impl AsRef<str>>) -> Result<String> {
     dotenv().ok();
+    match arg_token {
+        Some(token) => Ok(token.as_ref().to_string()),
+        None => env::var("WORKFLOWHUB_API_TOKEN").map_err(|_| {
+            anyhow!("No WorkflowHub API token provided. Please set the environment variable `WORKFLOWHUB_API_TOKEN`, or pass the `--workflowhub-token` flag.")
+        }),
+    }
+}
+
+/// Resolve the token passed by the command-line flags.
+/// If neither is given, returns None so that `github_token` falls back to the environment variables.
+#[cfg(not(tarpaulin_include))]
+pub fn arg_github_token(
+    arg_token: Option<String>,
+    arg_token_file: Option<PathBuf>,
+) -> Result<Option<String>> {
+    match (arg_token, arg_token_file) {
+        (Some(token), _) => Ok(Some(token)),
+        (None, Some(token_file)) => Ok(Some(read_token_file(token_file)?)),
+        (None, None) => Ok(None),
+    }
+}
+
+pub fn read_token_file(path: impl AsRef<Path>) -> Result<String> {
+    let token = fs::read_to_string(path.as_ref())
+        .with_context(|| {
+            format!(
+                "Failed to read GitHub token file: {}",
+                path.as_ref().display()
+            )
+        })?
+        .trim()
+        .to_string();
+    ensure!(
+        !token.is_empty(),
+        "GitHub token file is empty: {}",
+        path.as_ref().display()
+    );
+    Ok(token)
+}
+
+/// Precedence: `--run-dir` > `SAPPORO_RUN_DIR` > `default_sapporo_run_dir()`
+/// A relative `--run-dir` is resolved against the cwd.
+#[cfg(not(tarpaulin_include))]
+pub fn sapporo_run_dir(arg_run_dir: &Option<PathBuf>) -> Result<String> {
+    dotenv().ok();
+    if let Some(run_dir) = arg_run_dir {
+        let run_dir = match run_dir.is_relative() {
+            true => env::current_dir()?.join(run_dir),
+            false => run_dir.clone(),
+        };
+        return Ok(run_dir
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid path"))?
+            .to_string());
+    }
     match env::var("SAPPORO_RUN_DIR") {
         Ok(run_dir) => Ok(run_dir),
-        Err(_) => {
-            let cwd = env::current_dir()?;
-            Ok(cwd
-                .join("sapporo_run")
-                .to_str()
-                .ok_or_else(|| anyhow!("Invalid path"))?
-                .to_string())
-        }
+        Err(_) => Ok(default_sapporo_run_dir()
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid path"))?
+            .to_string()),
     }
 }
 
+/// A temp dir unique to this process, so that concurrent invocations (e.g., parallel CI jobs) do not share runs.
+pub fn default_sapporo_run_dir() -> PathBuf {
+    env::temp_dir().join(format!("gh-trs-sapporo-run-{}", process::id()))
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn in_ci() -> bool {
     dotenv().ok();
     env::var("CI").is_ok()
 }
 
+#[cfg(not(tarpaulin_include))]
+pub fn in_github_actions() -> bool {
+    dotenv().ok();
+    env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn gh_actions_url() -> Result<Url> {
     dotenv().ok();
@@ -48,3 +118,27 @@ pub fn gh_actions_url() -> Result<Url> {
         gh_server_url, gh_repo, gh_run_id
     ))?)
 }
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_default_sapporo_run_dir() {
+        let run_dir = default_sapporo_run_dir();
+        assert!(run_dir.starts_with(env::temp_dir()));
+        assert_eq!(run_dir, default_sapporo_run_dir());
+    }
+
+    #[test]
+    fn test_read_token_file() -> Result<()> {
+        let path = env::temp_dir().join("gh-trs-test-token-file");
+        let mut file = fs::File::create(&path)?;
+        file.write_all(b"  ghp_dummy_token\n")?;
+        assert_eq!(read_token_file(&path)?, "ghp_dummy_token");
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
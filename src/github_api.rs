@@ -1,4 +1,8 @@
+use crate::remote;
+
 use anyhow::{anyhow, bail, ensure, Result};
+use colored::Colorize;
+use log::{debug, warn};
 use regex::Regex;
 use reqwest;
 use serde_json::json;
@@ -18,18 +22,24 @@ pub fn parse_repo(repo: impl AsRef<str>) -> Result<(String, String)> {
     Ok((parts[0].to_string(), parts[1].to_string()))
 }
 
-pub fn get_request(gh_token: impl AsRef<str>, url: &Url, query: &[(&str, &str)]) -> Result<Value> {
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(url.as_str())
-        .header(reqwest::header::USER_AGENT, "gh-trs")
-        .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("token {}", gh_token.as_ref()),
-        )
-        .query(query)
-        .send()?;
+pub fn get_request(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    url: &Url,
+    query: &[(&str, &str)],
+) -> Result<Value> {
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .get(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", gh_token.as_ref()),
+            )
+            .query(query),
+    )?;
     let status = response.status();
     let res_body = response.json::<Value>()?;
     ensure!(
@@ -48,18 +58,24 @@ pub fn get_request(gh_token: impl AsRef<str>, url: &Url, query: &[(&str, &str)])
     Ok(res_body)
 }
 
-pub fn post_request(gh_token: impl AsRef<str>, url: &Url, body: &Value) -> Result<Value> {
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .post(url.as_str())
-        .header(reqwest::header::USER_AGENT, "gh-trs")
-        .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("token {}", gh_token.as_ref()),
-        )
-        .json(body)
-        .send()?;
+pub fn post_request(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    url: &Url,
+    body: &Value,
+) -> Result<Value> {
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .post(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", gh_token.as_ref()),
+            )
+            .json(body),
+    )?;
     let status = response.status();
     let res_body = response.json::<Value>()?;
     ensure!(
@@ -78,18 +94,24 @@ pub fn post_request(gh_token: impl AsRef<str>, url: &Url, body: &Value) -> Resul
     Ok(res_body)
 }
 
-pub fn patch_request(gh_token: impl AsRef<str>, url: &Url, body: &Value) -> Result<Value> {
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .patch(url.as_str())
-        .header(reqwest::header::USER_AGENT, "gh-trs")
-        .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("token {}", gh_token.as_ref()),
-        )
-        .json(body)
-        .send()?;
+pub fn patch_request(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    url: &Url,
+    body: &Value,
+) -> Result<Value> {
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .patch(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", gh_token.as_ref()),
+            )
+            .json(body),
+    )?;
     let status = response.status();
     let res_body = response.json::<Value>()?;
     ensure!(
@@ -110,6 +132,7 @@ pub fn patch_request(gh_token: impl AsRef<str>, url: &Url, body: &Value) -> Resu
 
 /// https://docs.github.com/ja/rest/reference/repos#get-a-repository
 pub fn get_repos(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -119,10 +142,90 @@ pub fn get_repos(
         owner.as_ref(),
         name.as_ref()
     ))?;
-    get_request(gh_token, &url, &[])
+    get_request(http, gh_token, &url, &[])
+}
+
+/// https://docs.github.com/en/rest/pages#get-a-github-pages-site
+/// `None` if GitHub Pages is not enabled for the repo (404).
+pub fn get_pages(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+) -> Result<Option<Value>> {
+    let url = Url::parse(&format!(
+        "https://api.github.com/repos/{}/{}/pages",
+        owner.as_ref(),
+        name.as_ref()
+    ))?;
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .get(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", gh_token.as_ref()),
+            ),
+    )?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let res_body = response.json::<Value>()?;
+    ensure!(
+        status.is_success(),
+        "Failed to get request to {}. Response: {}",
+        url,
+        match res_body.get("message") {
+            Some(message) => message.as_str().unwrap_or_else(|| status.as_str()),
+            None => status.as_str(),
+        }
+    );
+    Ok(Some(res_body))
+}
+
+/// https://docs.github.com/en/rest/pages#create-a-github-pages-site
+/// Serve the root of the branch.
+pub fn create_pages(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    branch: impl AsRef<str>,
+) -> Result<Value> {
+    let url = Url::parse(&format!(
+        "https://api.github.com/repos/{}/{}/pages",
+        owner.as_ref(),
+        name.as_ref()
+    ))?;
+    let body = json!({
+        "source": {
+            "branch": branch.as_ref(),
+            "path": "/",
+        }
+    });
+    post_request(http, gh_token, &url, &body)
+}
+
+/// https://docs.github.com/en/rest/pages#get-latest-pages-build
+pub fn get_latest_pages_build(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+) -> Result<Value> {
+    let url = Url::parse(&format!(
+        "https://api.github.com/repos/{}/{}/pages/builds/latest",
+        owner.as_ref(),
+        name.as_ref()
+    ))?;
+    get_request(http, gh_token, &url, &[])
 }
 
 pub fn get_default_branch(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -135,7 +238,7 @@ pub fn get_default_branch(
             match memo.get(&key) {
                 Some(default_branch) => Ok(default_branch.to_string()),
                 None => {
-                    let res = get_repos(gh_token, owner, name)?;
+                    let res = get_repos(http, gh_token, owner, name)?;
                     let default_branch = res
                         .get("default_branch")
                         .ok_or_else(|| anyhow!(err_message))?
@@ -148,7 +251,7 @@ pub fn get_default_branch(
             }
         }
         None => {
-            let res = get_repos(gh_token, owner, name)?;
+            let res = get_repos(http, gh_token, owner, name)?;
             Ok(res
                 .get("default_branch")
                 .ok_or_else(|| anyhow!(err_message))?
@@ -161,6 +264,7 @@ pub fn get_default_branch(
 
 /// https://docs.github.com/ja/rest/reference/branches#get-a-branch
 pub fn get_branches(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -172,10 +276,11 @@ pub fn get_branches(
         name.as_ref(),
         branch_name.as_ref()
     ))?;
-    get_request(gh_token, &url, &[])
+    get_request(http, gh_token, &url, &[])
 }
 
 pub fn get_latest_commit_sha(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -194,7 +299,7 @@ pub fn get_latest_commit_sha(
             match memo.get(&key) {
                 Some(latest_commit_hash) => Ok(latest_commit_hash.to_string()),
                 None => {
-                    let res = get_branches(gh_token, owner, name, branch_name)?;
+                    let res = get_branches(http, gh_token, owner, name, branch_name)?;
                     let latest_commit_hash = res
                         .get("commit")
                         .ok_or_else(|| anyhow!(err_message))?
@@ -209,7 +314,7 @@ pub fn get_latest_commit_sha(
             }
         }
         None => {
-            let res = get_branches(gh_token, owner, name, branch_name)?;
+            let res = get_branches(http, gh_token, owner, name, branch_name)?;
             Ok(res
                 .get("commit")
                 .ok_or_else(|| anyhow!(err_message))?
@@ -222,14 +327,81 @@ pub fn get_latest_commit_sha(
     }
 }
 
+/// Tree sha of the latest commit of the branch.
+pub fn get_tree_sha(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    branch_name: impl AsRef<str>,
+) -> Result<String> {
+    let res = get_branches(http, gh_token, owner, name, branch_name)?;
+    let err_message = "Failed to parse the response to get the tree sha";
+    Ok(res
+        .get("commit")
+        .ok_or_else(|| anyhow!(err_message))?
+        .get("commit")
+        .ok_or_else(|| anyhow!(err_message))?
+        .get("tree")
+        .ok_or_else(|| anyhow!(err_message))?
+        .get("sha")
+        .ok_or_else(|| anyhow!(err_message))?
+        .as_str()
+        .ok_or_else(|| anyhow!(err_message))?
+        .to_string())
+}
+
+/// The blob shas of the files in the tree (path -> sha), listed recursively.
+/// `None` if the list is truncated by GitHub (e.g., too many files).
+/// https://docs.github.com/en/rest/git/trees#get-a-tree
+pub fn get_tree_blob_shas(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    tree_sha: impl AsRef<str>,
+) -> Result<Option<HashMap<PathBuf, String>>> {
+    let url = Url::parse(&format!(
+        "https://api.github.com/repos/{}/{}/git/trees/{}",
+        owner.as_ref(),
+        name.as_ref(),
+        tree_sha.as_ref()
+    ))?;
+    let res = get_request(http, gh_token, &url, &[("recursive", "1")])?;
+    if res.get("truncated").and_then(|t| t.as_bool()) == Some(true) {
+        return Ok(None);
+    }
+    let err_message = "Failed to parse the response to get the tree";
+    let mut shas = HashMap::new();
+    for entry in res
+        .get("tree")
+        .and_then(|t| t.as_array())
+        .ok_or_else(|| anyhow!(err_message))?
+    {
+        if entry.get("type").and_then(|t| t.as_str()) != Some("blob") {
+            continue;
+        }
+        let path = entry.get("path").and_then(|p| p.as_str());
+        let sha = entry.get("sha").and_then(|s| s.as_str());
+        match (path, sha) {
+            (Some(path), Some(sha)) => shas.insert(PathBuf::from(path), sha.to_string()),
+            _ => bail!(err_message),
+        };
+    }
+    Ok(Some(shas))
+}
+
 /// https://docs.github.com/ja/rest/reference/users#get-a-user
-pub fn get_user(gh_token: impl AsRef<str>) -> Result<Value> {
+pub fn get_user(http: &remote::Http, gh_token: impl AsRef<str>) -> Result<Value> {
     let url = Url::parse("https://api.github.com/user")?;
-    get_request(gh_token, &url, &[])
+    get_request(http, gh_token, &url, &[])
 }
 
-pub fn get_author_info(gh_token: impl AsRef<str>) -> Result<(String, String, String)> {
-    let res = get_user(gh_token)?;
+pub fn get_author_info(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+) -> Result<(String, String, String)> {
+    let res = get_user(http, gh_token)?;
     let err_message = "Failed to parse the response to get the author";
     let gh_account = res
         .get("login")
@@ -254,6 +426,7 @@ pub fn get_author_info(gh_token: impl AsRef<str>) -> Result<(String, String, Str
 
 /// https://docs.github.com/ja/rest/reference/repos#get-a-repository-readme
 pub fn get_readme_url(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -263,7 +436,7 @@ pub fn get_readme_url(
         owner.as_ref(),
         name.as_ref()
     ))?;
-    let res = get_request(gh_token, &url, &[])?;
+    let res = get_request(http, gh_token, &url, &[])?;
     let err_message = "Failed to parse the response to get a readme URL.";
     Ok(Url::parse(
         res.get("html_url")
@@ -275,6 +448,7 @@ pub fn get_readme_url(
 
 /// https://docs.github.com/ja/rest/reference/repos#get-repository-content
 pub fn get_contents(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -287,69 +461,156 @@ pub fn get_contents(
         name.as_ref(),
         path.as_ref().display()
     ))?;
-    get_request(gh_token, &url, &[("ref", commit.as_ref())])
+    get_request(http, gh_token, &url, &[("ref", commit.as_ref())])
+}
+
+/// The raw content of a file fetched through the contents API, which works for private repos with the token.
+/// The response is returned as is (with the raw media type, up to 100 MB) with the request permit,
+/// so that the caller can stream the body within `--concurrency`.
+pub fn get_file_raw(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    path: impl AsRef<Path>,
+    commit: impl AsRef<str>,
+) -> Result<remote::PermittedResponse> {
+    let url = Url::parse(&format!(
+        "https://api.github.com/repos/{}/{}/contents/{}",
+        owner.as_ref(),
+        name.as_ref(),
+        path.as_ref().display()
+    ))?;
+    let permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .get(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/vnd.github.v3.raw")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", gh_token.as_ref()),
+            )
+            .query(&[("ref", commit.as_ref())]),
+    )?;
+    let status = response.status();
+    ensure!(
+        status != reqwest::StatusCode::UNAUTHORIZED,
+        "Failed to authenticate with GitHub. Please check your GitHub token."
+    );
+    ensure!(
+        status.is_success(),
+        "Failed to get the content of {} with status: {}",
+        url,
+        status
+    );
+    Ok(remote::PermittedResponse::new(response, permit))
 }
 
+/// `max_depth` limits how many levels of subdirectories are descended into (`Some(0)` lists only `path` itself).
+/// `None` means unlimited.
 pub fn get_file_list_recursive(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
     path: impl AsRef<Path>,
     commit: impl AsRef<str>,
+    max_depth: Option<usize>,
 ) -> Result<Vec<PathBuf>> {
     let res = get_contents(
+        http,
         gh_token.as_ref(),
         owner.as_ref(),
         name.as_ref(),
         path,
         commit.as_ref(),
     )?;
-    let err_message = "Failed to parse the response to get the file list.";
-    match res.as_array() {
-        Some(files) => {
-            let mut file_list: Vec<PathBuf> = Vec::new();
-            for file in files {
-                let path = PathBuf::from(
-                    file.get("path")
-                        .ok_or_else(|| anyhow!(err_message))?
-                        .as_str()
-                        .ok_or_else(|| anyhow!(err_message))?,
-                );
-                let r#type = file
-                    .get("type")
-                    .ok_or_else(|| anyhow!(err_message))?
-                    .as_str()
-                    .ok_or_else(|| anyhow!(err_message))?;
-                match r#type {
-                    "file" => file_list.push(path),
-                    "dir" => {
-                        let mut sub_file_list = get_file_list_recursive(
-                            gh_token.as_ref(),
-                            owner.as_ref(),
-                            name.as_ref(),
-                            path,
-                            commit.as_ref(),
-                        )?;
-                        file_list.append(&mut sub_file_list);
-                    }
-                    _ => {
-                        unreachable!("Unknown file type: {}", r#type);
-                    }
-                }
+    let mut file_list: Vec<PathBuf> = Vec::new();
+    for entry in parse_contents_entries(&res)? {
+        match entry {
+            ContentsEntry::File(path) => file_list.push(path),
+            ContentsEntry::Dir(path) if max_depth == Some(0) => {
+                debug!("Skip directory {} (max depth reached)", path.display());
             }
-            Ok(file_list)
+            ContentsEntry::Dir(path) => {
+                let mut sub_file_list = get_file_list_recursive(
+                    http,
+                    gh_token.as_ref(),
+                    owner.as_ref(),
+                    name.as_ref(),
+                    path,
+                    commit.as_ref(),
+                    max_depth.map(|depth| depth - 1),
+                )?;
+                file_list.append(&mut sub_file_list);
+            }
+        }
+    }
+    Ok(file_list)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ContentsEntry {
+    File(PathBuf),
+    Dir(PathBuf),
+}
+
+/// Parse the directory listing of the contents API in order.
+/// The other types (`submodule`, `symlink`, and unknown ones) are skipped with a warning.
+pub fn parse_contents_entries(res: &Value) -> Result<Vec<ContentsEntry>> {
+    let err_message = "Failed to parse the response to get the file list.";
+    let files = res.as_array().ok_or_else(|| anyhow!(err_message))?;
+    let mut entries = Vec::new();
+    for file in files {
+        let path = PathBuf::from(
+            file.get("path")
+                .ok_or_else(|| anyhow!(err_message))?
+                .as_str()
+                .ok_or_else(|| anyhow!(err_message))?,
+        );
+        let r#type = file
+            .get("type")
+            .ok_or_else(|| anyhow!(err_message))?
+            .as_str()
+            .ok_or_else(|| anyhow!(err_message))?;
+        match r#type {
+            "file" => entries.push(ContentsEntry::File(path)),
+            "dir" => entries.push(ContentsEntry::Dir(path)),
+            // The files in a submodule live in another repo, so they are not served from the raw URLs of this repo.
+            "submodule" => warn!(
+                "{}: Skip the submodule: {} ({}). Add its files to `workflow.files` with the URLs of the submodule repo if needed.",
+                "Warning".yellow(),
+                path.display(),
+                file.get("submodule_git_url")
+                    .and_then(|url| url.as_str())
+                    .unwrap_or("unknown url")
+            ),
+            // The raw URL of a symlink serves the link path, not the content of the target.
+            "symlink" => warn!(
+                "{}: Skip the symlink: {}. Add its target to `workflow.files` if needed.",
+                "Warning".yellow(),
+                path.display()
+            ),
+            _ => warn!(
+                "{}: Skip {} of unknown type: {}",
+                "Warning".yellow(),
+                path.display(),
+                r#type
+            ),
         }
-        None => bail!(err_message),
     }
+    Ok(entries)
 }
 
 pub fn exists_branch(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
     branch_name: impl AsRef<str>,
 ) -> Result<()> {
-    match get_branches(&gh_token, &owner, &name, &branch_name) {
+    match get_branches(http, &gh_token, &owner, &name, &branch_name) {
         Ok(_) => Ok(()),
         Err(err) => bail!("Branch {} does not exist: {}", branch_name.as_ref(), err),
     }
@@ -357,6 +618,7 @@ pub fn exists_branch(
 
 /// https://docs.github.com/en/rest/reference/git#get-a-reference
 pub fn get_ref(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -368,16 +630,18 @@ pub fn get_ref(
         name.as_ref(),
         r#ref.as_ref()
     ))?;
-    get_request(gh_token, &url, &[])
+    get_request(http, gh_token, &url, &[])
 }
 
 pub fn get_branch_sha(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
     branch_name: impl AsRef<str>,
 ) -> Result<String> {
     let res = get_ref(
+        http,
         gh_token.as_ref(),
         owner.as_ref(),
         name.as_ref(),
@@ -396,6 +660,7 @@ pub fn get_branch_sha(
 
 /// https://docs.github.com/en/rest/reference/git#create-a-reference
 pub fn create_ref(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -411,11 +676,12 @@ pub fn create_ref(
         "ref": r#ref.as_ref(),
         "sha": sha.as_ref(),
     });
-    post_request(gh_token, &url, &body)
+    post_request(http, gh_token, &url, &body)
 }
 
 /// https://docs.github.com/en/rest/reference/git#update-a-reference
 pub fn update_ref(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -431,37 +697,211 @@ pub fn update_ref(
     let body = json!({
         "sha": sha.as_ref(),
     });
-    patch_request(gh_token, &url, &body)?;
+    patch_request(http, gh_token, &url, &body)?;
     Ok(())
 }
 
+/// Create a lightweight tag pointing at the commit.
+pub fn create_tag(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    tag: impl AsRef<str>,
+    sha: impl AsRef<str>,
+) -> Result<()> {
+    create_ref(
+        http,
+        &gh_token,
+        &owner,
+        &name,
+        format!("refs/tags/{}", tag.as_ref()),
+        sha,
+    )?;
+    Ok(())
+}
+
+/// https://docs.github.com/en/rest/releases/releases#create-a-release
+/// Returns the id of the created release.
+pub fn create_release(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    tag: impl AsRef<str>,
+    release_name: impl AsRef<str>,
+    body: impl AsRef<str>,
+) -> Result<u64> {
+    let url = Url::parse(&format!(
+        "https://api.github.com/repos/{}/{}/releases",
+        owner.as_ref(),
+        name.as_ref(),
+    ))?;
+    let body = json!({
+        "tag_name": tag.as_ref(),
+        "name": release_name.as_ref(),
+        "body": body.as_ref(),
+    });
+    let res = post_request(http, gh_token, &url, &body)?;
+    res.get("id")
+        .and_then(|id| id.as_u64())
+        .ok_or_else(|| anyhow!("Failed to parse the response to create a release"))
+}
+
+/// https://docs.github.com/en/rest/releases/assets#upload-a-release-asset
+/// The assets are uploaded to `uploads.github.com`, not `api.github.com`.
+pub fn upload_release_asset(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    release_id: u64,
+    asset_name: impl AsRef<str>,
+    content: impl AsRef<str>,
+) -> Result<()> {
+    let url = Url::parse(&format!(
+        "https://uploads.github.com/repos/{}/{}/releases/{}/assets",
+        owner.as_ref(),
+        name.as_ref(),
+        release_id
+    ))?;
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .post(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", gh_token.as_ref()),
+            )
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .query(&[("name", asset_name.as_ref())])
+            .body(content.as_ref().to_string()),
+    )?;
+    let status = response.status();
+    let res_body = response.json::<Value>()?;
+    ensure!(
+        status.is_success(),
+        "Failed to upload the release asset {} to {}. Response: {}",
+        asset_name.as_ref(),
+        url,
+        match res_body.get("message") {
+            Some(message) => message.as_str().unwrap_or_else(|| status.as_str()),
+            None => status.as_str(),
+        }
+    );
+    Ok(())
+}
+
+/// Only a 404 means that the tag does not exist.
+/// The other failures (e.g., an invalid token or the rate limit) are errors, not to be mistaken for a missing tag.
+pub fn exists_tag(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    tag: impl AsRef<str>,
+) -> Result<bool> {
+    let url = Url::parse(&format!(
+        "https://api.github.com/repos/{}/{}/git/ref/tags/{}",
+        owner.as_ref(),
+        name.as_ref(),
+        tag.as_ref()
+    ))?;
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .get(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", gh_token.as_ref()),
+            ),
+    )?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(false);
+    }
+    let res_body = response.json::<Value>()?;
+    ensure!(
+        status != reqwest::StatusCode::UNAUTHORIZED,
+        "Failed to authenticate with GitHub. Please check your GitHub token."
+    );
+    ensure!(
+        status.is_success(),
+        "Failed to get request to {}. Response: {}",
+        url,
+        match res_body.get("message") {
+            Some(message) => message.as_str().unwrap_or_else(|| status.as_str()),
+            None => status.as_str(),
+        }
+    );
+    Ok(true)
+}
+
 pub fn create_branch(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    branch_name: impl AsRef<str>,
+) -> Result<()> {
+    let default_branch = get_default_branch(http, &gh_token, &owner, &name, None)?;
+    create_branch_from(http, &gh_token, &owner, &name, branch_name, &default_branch)
+}
+
+/// Create a branch from the base, which is a branch name or a commit sha.
+pub fn create_branch_from(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
     branch_name: impl AsRef<str>,
+    base: impl AsRef<str>,
 ) -> Result<()> {
-    let default_branch = get_default_branch(&gh_token, &owner, &name, None)?;
-    let default_branch_sha = get_branch_sha(&gh_token, &owner, &name, &default_branch)?;
+    let base_sha = resolve_base_sha(http, &gh_token, &owner, &name, &base)?;
     create_ref(
+        http,
         &gh_token,
         &owner,
         &name,
         format!("refs/heads/{}", branch_name.as_ref()),
-        &default_branch_sha,
+        &base_sha,
     )?;
     Ok(())
 }
 
-pub fn create_empty_branch(
+/// Resolve the base (a branch name or a commit sha) to a commit sha, checking that it exists.
+pub fn resolve_base_sha(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
-    branch_name: impl AsRef<str>,
-) -> Result<()> {
-    let mut empty_contents: HashMap<PathBuf, String> = HashMap::new();
+    base: impl AsRef<str>,
+) -> Result<String> {
+    if is_commit_sha(&base) {
+        let url = Url::parse(&format!(
+            "https://api.github.com/repos/{}/{}/git/commits/{}",
+            owner.as_ref(),
+            name.as_ref(),
+            base.as_ref()
+        ))?;
+        get_request(http, &gh_token, &url, &[])
+            .map_err(|e| anyhow!("Commit {} does not exist: {}", base.as_ref(), e))?;
+        Ok(base.as_ref().to_string())
+    } else {
+        exists_branch(http, &gh_token, &owner, &name, &base)?;
+        get_branch_sha(http, &gh_token, &owner, &name, &base)
+    }
+}
+
+fn is_commit_sha(s: impl AsRef<str>) -> bool {
+    s.as_ref().len() == 40 && s.as_ref().chars().all(|c| c.is_ascii_hexdigit())
+}
 
-    let readme_content = r#"
+const DEFAULT_INITIAL_README: &str = r#"
 # GA4GH Tool Registry Service generated by gh-trs or yevis
 
 ## Docs
@@ -470,11 +910,59 @@ pub fn create_empty_branch(
 - [GitHub - ddbj/yevis-cli](https://github.com/ddbj/yevis-cli)
 - [GA4GH - Tool Registry Service API](https://www.ga4gh.org/news/tool-registry-service-api-enabling-an-interoperable-library-of-genomics-analysis-tools/)
 - [GitHub - ga4gh/tool-registry-service-schemas](https://github.com/ga4gh/tool-registry-service-schemas)
-"#.to_string();
+"#;
+
+const INITIAL_INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>GA4GH Tool Registry Service</title>
+  </head>
+  <body>
+    <h1>GA4GH Tool Registry Service</h1>
+    <ul>
+      <li><a href="./service-info">service-info</a></li>
+      <li><a href="./tools">tools</a></li>
+    </ul>
+  </body>
+</html>
+"#;
+
+/// Contents of the initial commit of an empty branch: the README (custom or default) and an `index.html` linking to the TRS API.
+pub fn initial_branch_contents(
+    readme_content: Option<impl AsRef<str>>,
+) -> HashMap<PathBuf, String> {
+    let mut contents: HashMap<PathBuf, String> = HashMap::new();
+    contents.insert(
+        PathBuf::from("README.md"),
+        match readme_content {
+            Some(readme_content) => readme_content.as_ref().to_string(),
+            None => DEFAULT_INITIAL_README.to_string(),
+        },
+    );
+    contents.insert(PathBuf::from("index.html"), INITIAL_INDEX_HTML.to_string());
+    contents
+}
 
-    empty_contents.insert(PathBuf::from("README.md"), readme_content);
-    let empty_tree_sha = create_tree(&gh_token, &owner, &name, None::<String>, empty_contents)?;
+pub fn create_empty_branch(
+    http: &remote::Http,
+    gh_token: impl AsRef<str>,
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    branch_name: impl AsRef<str>,
+    readme_content: Option<impl AsRef<str>>,
+) -> Result<()> {
+    let empty_contents = initial_branch_contents(readme_content);
+    let empty_tree_sha = create_tree(
+        http,
+        &gh_token,
+        &owner,
+        &name,
+        None::<String>,
+        empty_contents,
+    )?;
     let empty_commit_sha = create_commit(
+        http,
         &gh_token,
         &owner,
         &name,
@@ -483,6 +971,7 @@ pub fn create_empty_branch(
         "Initial commit",
     )?;
     create_ref(
+        http,
         &gh_token,
         &owner,
         &name,
@@ -494,6 +983,7 @@ pub fn create_empty_branch(
 
 /// https://docs.github.com/en/rest/reference/git#create-a-tree
 pub fn create_tree(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -529,7 +1019,7 @@ pub fn create_tree(
             })
         }
     };
-    let res = post_request(gh_token, &url, &body)?;
+    let res = post_request(http, gh_token, &url, &body)?;
     let err_message = "Failed to parse the response to create a tree.";
     Ok(res
         .get("sha")
@@ -541,6 +1031,7 @@ pub fn create_tree(
 
 /// https://docs.github.com/ja/rest/reference/git#create-a-commit
 pub fn create_commit(
+    http: &remote::Http,
     gh_token: impl AsRef<str>,
     owner: impl AsRef<str>,
     name: impl AsRef<str>,
@@ -568,7 +1059,7 @@ pub fn create_commit(
             })
         }
     };
-    let res = post_request(gh_token, &url, &body)?;
+    let res = post_request(http, gh_token, &url, &body)?;
     let err_message = "Failed to parse the response to create a commit.";
     Ok(res
         .get("sha")
@@ -584,10 +1075,33 @@ mod tests {
     use super::*;
     use crate::env;
 
+    #[test]
+    fn test_parse_contents_entries() -> Result<()> {
+        let res = json!([
+            {"path": "wf/main.cwl", "type": "file"},
+            {"path": "wf/link.cwl", "type": "symlink"},
+            {"path": "wf/tools", "type": "dir"},
+            {"path": "wf/shared", "type": "submodule", "submodule_git_url": "https://github.com/example/shared.git"},
+            {"path": "wf/unknown", "type": "unknown"},
+            {"path": "wf/README.md", "type": "file"}
+        ]);
+        assert_eq!(
+            parse_contents_entries(&res)?,
+            vec![
+                ContentsEntry::File(PathBuf::from("wf/main.cwl")),
+                ContentsEntry::Dir(PathBuf::from("wf/tools")),
+                ContentsEntry::File(PathBuf::from("wf/README.md")),
+            ]
+        );
+        assert!(parse_contents_entries(&json!({"message": "Not Found"})).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_get_default_branch() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
-        let branch = get_default_branch(&gh_token, "suecharo", "gh-trs", None)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let branch = get_default_branch(&http, &gh_token, "suecharo", "gh-trs", None)?;
         assert_eq!(branch, "main");
         Ok(())
     }
@@ -595,25 +1109,51 @@ mod tests {
     #[test]
     fn test_get_default_branch_with_memo() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let mut memo = HashMap::new();
-        get_default_branch(&gh_token, "suecharo", "gh-trs", Some(&mut memo))?;
-        get_default_branch(&gh_token, "suecharo", "gh-trs", Some(&mut memo))?;
+        get_default_branch(&http, &gh_token, "suecharo", "gh-trs", Some(&mut memo))?;
+        get_default_branch(&http, &gh_token, "suecharo", "gh-trs", Some(&mut memo))?;
         Ok(())
     }
 
     #[test]
     fn test_get_latest_commit_sha() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
-        get_latest_commit_sha(&gh_token, "suecharo", "gh-trs", "main", None)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        get_latest_commit_sha(&http, &gh_token, "suecharo", "gh-trs", "main", None)?;
         Ok(())
     }
 
     #[test]
     fn test_get_latest_commit_sha_with_memo() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let mut memo = HashMap::new();
-        get_latest_commit_sha(&gh_token, "suecharo", "gh-trs", "main", Some(&mut memo))?;
-        get_latest_commit_sha(&gh_token, "suecharo", "gh-trs", "main", Some(&mut memo))?;
+        get_latest_commit_sha(
+            &http,
+            &gh_token,
+            "suecharo",
+            "gh-trs",
+            "main",
+            Some(&mut memo),
+        )?;
+        get_latest_commit_sha(
+            &http,
+            &gh_token,
+            "suecharo",
+            "gh-trs",
+            "main",
+            Some(&mut memo),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_tree_sha() -> Result<()> {
+        let gh_token = env::github_token(&None::<String>)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let tree_sha = get_tree_sha(&http, &gh_token, "suecharo", "gh-trs", "main")?;
+        assert_eq!(tree_sha.len(), 40);
         Ok(())
     }
 
@@ -621,14 +1161,16 @@ mod tests {
     #[cfg(not(tarpaulin))]
     fn test_get_author_info() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
-        get_author_info(&gh_token)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        get_author_info(&http, &gh_token)?;
         Ok(())
     }
 
     #[test]
     fn test_get_readme_url() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
-        let readme_url = get_readme_url(&gh_token, "suecharo", "gh-trs")?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let readme_url = get_readme_url(&http, &gh_token, "suecharo", "gh-trs")?;
         assert_eq!(
             readme_url.to_string().as_str(),
             "https://github.com/suecharo/gh-trs/blob/main/README.md"
@@ -639,7 +1181,9 @@ mod tests {
     #[test]
     fn test_get_file_list_recursive() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
-        let file_list = get_file_list_recursive(&gh_token, "suecharo", "gh-trs", ".", "main")?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let file_list =
+            get_file_list_recursive(&http, &gh_token, "suecharo", "gh-trs", ".", "main", None)?;
         assert!(file_list.contains(&PathBuf::from("README.md")));
         assert!(file_list.contains(&PathBuf::from("LICENSE")));
         assert!(file_list.contains(&PathBuf::from("src/main.rs")));
@@ -649,8 +1193,49 @@ mod tests {
     #[test]
     fn test_get_file_list_recursive_with_dir() -> Result<()> {
         let gh_token = env::github_token(&None::<String>)?;
-        let file_list = get_file_list_recursive(&gh_token, "suecharo", "gh-trs", "src", "main")?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let file_list =
+            get_file_list_recursive(&http, &gh_token, "suecharo", "gh-trs", "src", "main", None)?;
         assert!(file_list.contains(&PathBuf::from("src/main.rs")));
         Ok(())
     }
+
+    #[test]
+    fn test_get_file_list_recursive_with_max_depth() -> Result<()> {
+        let gh_token = env::github_token(&None::<String>)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let file_list =
+            get_file_list_recursive(&http, &gh_token, "suecharo", "gh-trs", ".", "main", Some(0))?;
+        assert!(file_list.contains(&PathBuf::from("README.md")));
+        assert!(!file_list.contains(&PathBuf::from("src/main.rs")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_initial_branch_contents() {
+        let contents = initial_branch_contents(None::<String>);
+        assert!(contents[&PathBuf::from("README.md")].contains("gh-trs"));
+        assert!(contents[&PathBuf::from("index.html")].contains("./service-info"));
+        let contents = initial_branch_contents(Some("# My TRS"));
+        assert_eq!(contents[&PathBuf::from("README.md")], "# My TRS");
+    }
+
+    #[test]
+    fn test_is_commit_sha() {
+        assert!(is_commit_sha("458d0524e667f2442a5effb730b523c1f15748d4"));
+        assert!(!is_commit_sha("main"));
+        assert!(!is_commit_sha("458d0524"));
+    }
+
+    #[test]
+    fn test_resolve_base_sha() -> Result<()> {
+        let gh_token = env::github_token(&None::<String>)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let sha = resolve_base_sha(&http, &gh_token, "suecharo", "gh-trs", "main")?;
+        assert!(is_commit_sha(&sha));
+        assert!(
+            resolve_base_sha(&http, &gh_token, "suecharo", "gh-trs", "invalid_branch").is_err()
+        );
+        Ok(())
+    }
 }
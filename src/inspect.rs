@@ -1,32 +1,66 @@
 use crate::config;
 use crate::remote;
-use anyhow::{anyhow, Result};
-use colored::Colorize;
-use log::warn;
+use crate::warning::Warning;
+use anyhow::{anyhow, Context, Result};
 use regex::Regex;
 use serde_yaml;
 use std::collections::BTreeMap;
 use url::Url;
 
-pub fn inspect_wf_type_version(wf_loc: &Url) -> Result<config::types::Language> {
-    let wf_content = remote::fetch_raw_content(wf_loc)?;
-    let wf_type = inspect_wf_type(&wf_content);
-    let wf_version = inspect_wf_version(&wf_content, &wf_type);
-    Ok(config::types::Language {
-        r#type: wf_type,
-        version: wf_version,
-    })
+/// `wf_type` overrides the detected language type (`make-template --language-type`).
+/// The soft issues (e.g., a version that can not be parsed and is assumed) are returned as warnings.
+pub fn inspect_wf_type_version(
+    http: &remote::Http,
+    wf_loc: &Url,
+    wf_type: &Option<config::types::LanguageType>,
+) -> Result<(config::types::Language, Vec<Warning>)> {
+    let wf_content = remote::fetch_raw_content(http, wf_loc)?;
+    inspect_language(&wf_content, wf_type)
+        .with_context(|| format!("Failed to inspect the workflow language of {}", wf_loc))
+}
+
+/// It is an error if the language type is neither given nor detected,
+/// rather than leaving `workflow.language.type` empty to fail later at validate.
+pub fn inspect_language(
+    wf_content: impl AsRef<str>,
+    wf_type: &Option<config::types::LanguageType>,
+) -> Result<(config::types::Language, Vec<Warning>)> {
+    let mut warnings = Vec::new();
+    let wf_type = match wf_type {
+        Some(wf_type) => wf_type.clone(),
+        None => {
+            let (wf_type, type_warnings) = inspect_wf_type(&wf_content);
+            warnings.extend(type_warnings);
+            wf_type.ok_or_else(|| {
+                anyhow!("Could not detect the language type of the workflow. Specify it with --language-type <CWL|WDL|NFL|SMK>.")
+            })?
+        }
+    };
+    let (wf_version, version_warnings) = inspect_wf_version(&wf_content, &Some(wf_type.clone()));
+    warnings.extend(version_warnings);
+    Ok((
+        config::types::Language {
+            r#type: Some(wf_type),
+            version: wf_version,
+        },
+        warnings,
+    ))
 }
 
-pub fn inspect_wf_type(wf_content: impl AsRef<str>) -> Option<config::types::LanguageType> {
+pub fn inspect_wf_type(
+    wf_content: impl AsRef<str>,
+) -> (Option<config::types::LanguageType>, Vec<Warning>) {
     match check_by_shebang(&wf_content) {
-        Some(lang) => Some(lang),
+        Some(lang) => (Some(lang), vec![]),
         None => match check_by_regexp(&wf_content) {
-            Ok(lang) => lang,
-            Err(e) => {
-                warn!("{}: {}", "Warning".yellow(), e);
-                None
-            }
+            Ok(lang) => (lang, vec![]),
+            Err(e) => (
+                None,
+                vec![Warning::emit(
+                    "workflow.language.type",
+                    format!("Failed to inspect the language type: {}", e),
+                )],
+            ),
         },
     }
 }
@@ -65,40 +99,30 @@ pub fn check_by_regexp(wf_content: impl AsRef<str>) -> Result<Option<config::typ
     Ok(None)
 }
 
+/// If the version can not be inspected, the default version of the language is assumed with a warning.
 pub fn inspect_wf_version(
     wf_content: impl AsRef<str>,
     wf_type: &Option<config::types::LanguageType>,
-) -> Option<String> {
-    match wf_type {
-        Some(config::types::LanguageType::Cwl) => match inspect_cwl_version(wf_content) {
-            Ok(version) => Some(version),
-            Err(e) => {
-                warn!("{}: {}", "Warning".yellow(), e);
-                Some("v1.0".to_string())
-            }
-        },
-        Some(config::types::LanguageType::Wdl) => match inspect_wdl_version(wf_content) {
-            Ok(version) => Some(version),
-            Err(e) => {
-                warn!("{}: {}", "Warning".yellow(), e);
-                Some("1.0".to_string())
-            }
-        },
-        Some(config::types::LanguageType::Nfl) => match inspect_nfl_version(wf_content) {
-            Ok(version) => Some(version),
-            Err(e) => {
-                warn!("{}: {}", "Warning".yellow(), e);
-                Some("1.0".to_string())
-            }
-        },
-        Some(config::types::LanguageType::Smk) => match inspect_smk_version(wf_content) {
-            Ok(version) => Some(version),
-            Err(e) => {
-                warn!("{}: {}", "Warning".yellow(), e);
-                Some("1.0".to_string())
-            }
-        },
-        None => None,
+) -> (Option<String>, Vec<Warning>) {
+    let (inspected, default) = match wf_type {
+        Some(config::types::LanguageType::Cwl) => (inspect_cwl_version(wf_content), "v1.0"),
+        Some(config::types::LanguageType::Wdl) => (inspect_wdl_version(wf_content), "1.0"),
+        Some(config::types::LanguageType::Nfl) => (inspect_nfl_version(wf_content), "1.0"),
+        Some(config::types::LanguageType::Smk) => (inspect_smk_version(wf_content), "1.0"),
+        None => return (None, vec![]),
+    };
+    match inspected {
+        Ok(version) => (Some(version), vec![]),
+        Err(e) => (
+            Some(default.to_string()),
+            vec![Warning::emit(
+                "workflow.language.version",
+                format!(
+                    "Failed to inspect the language version, so {} is assumed: {}",
+                    default, e
+                ),
+            )],
+        ),
     }
 }
 
@@ -128,6 +152,17 @@ pub fn inspect_wdl_version(wf_content: impl AsRef<str>) -> Result<String> {
     Ok("1.0".to_string())
 }
 
+/// Extract the URIs of the `import` statements in a WDL workflow (relative paths or URLs).
+pub fn wdl_imports(wf_content: impl AsRef<str>) -> Result<Vec<String>> {
+    let pattern_import = Regex::new(r#"^\s*import\s+["']([^"']+)["']"#)?;
+    Ok(wf_content
+        .as_ref()
+        .lines()
+        .filter_map(|line| pattern_import.captures(line))
+        .map(|caps| caps[1].to_string())
+        .collect())
+}
+
 pub fn inspect_nfl_version(wf_content: impl AsRef<str>) -> Result<String> {
     for line in wf_content.as_ref().lines() {
         if line == "nextflow.enable.dsl=2" {
@@ -146,10 +181,55 @@ pub fn inspect_smk_version(_wf_content: impl AsRef<str>) -> Result<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_wdl_imports() -> Result<()> {
+        let wf_content = r#"version 1.0
+
+import "tasks/qc.wdl" as qc
+  import 'https://raw.githubusercontent.com/owner/repo/main/tasks/align.wdl' as align
+# import "commented.wdl"
+
+workflow main {}
+"#;
+        assert_eq!(
+            wdl_imports(wf_content)?,
+            vec![
+                "tasks/qc.wdl".to_string(),
+                "https://raw.githubusercontent.com/owner/repo/main/tasks/align.wdl".to_string()
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_language() -> Result<()> {
+        let (language, warnings) = inspect_language("cwlVersion: v1.2\nclass: Workflow\n", &None)?;
+        assert_eq!(language.r#type, Some(config::types::LanguageType::Cwl));
+        assert_eq!(language.version, Some("v1.2".to_string()));
+        assert!(warnings.is_empty());
+
+        let unknown = "echo hello\n";
+        let err = inspect_language(unknown, &None).unwrap_err();
+        assert!(err.to_string().contains("--language-type"));
+        let (language, _) = inspect_language(unknown, &Some(config::types::LanguageType::Smk))?;
+        assert_eq!(language.r#type, Some(config::types::LanguageType::Smk));
+
+        // A version that can not be parsed is assumed with a warning.
+        let (language, warnings) = inspect_language(
+            "cwlVersion: [v1.2\n",
+            &Some(config::types::LanguageType::Cwl),
+        )?;
+        assert_eq!(language.version, Some("v1.0".to_string()));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "workflow.language.version");
+        Ok(())
+    }
+
     #[test]
     fn test_inspect_wf_type_version_cwl() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let url = Url::parse("https://raw.githubusercontent.com/suecharo/gh-trs/main/tests/CWL/wf/trimming_and_qc.cwl")?;
-        let wf_type_version = inspect_wf_type_version(&url)?;
+        let (wf_type_version, _) = inspect_wf_type_version(&http, &url, &None)?;
         assert_eq!(
             wf_type_version.r#type,
             Some(config::types::LanguageType::Cwl)
@@ -160,8 +240,9 @@ mod tests {
 
     #[test]
     fn test_inspect_wf_type_version_wdl() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let url = Url::parse("https://raw.githubusercontent.com/suecharo/gh-trs/main/tests/WDL/wf/dockstore-tool-bamstats.wdl")?;
-        let wf_type_version = inspect_wf_type_version(&url)?;
+        let (wf_type_version, _) = inspect_wf_type_version(&http, &url, &None)?;
         assert_eq!(
             wf_type_version.r#type,
             Some(config::types::LanguageType::Wdl)
@@ -172,10 +253,11 @@ mod tests {
 
     #[test]
     fn test_inspect_wf_type_version_nfl() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let url = Url::parse(
             "https://raw.githubusercontent.com/suecharo/gh-trs/main/tests/NFL/wf/file_input.nf",
         )?;
-        let wf_type_version = inspect_wf_type_version(&url)?;
+        let (wf_type_version, _) = inspect_wf_type_version(&http, &url, &None)?;
         assert_eq!(
             wf_type_version.r#type,
             Some(config::types::LanguageType::Nfl)
@@ -186,10 +268,11 @@ mod tests {
 
     #[test]
     fn test_inspect_wf_type_version_smk() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let url = Url::parse(
             "https://raw.githubusercontent.com/suecharo/gh-trs/main/tests/SMK/wf/Snakefile",
         )?;
-        let wf_type_version = inspect_wf_type_version(&url)?;
+        let (wf_type_version, _) = inspect_wf_type_version(&http, &url, &None)?;
         assert_eq!(
             wf_type_version.r#type,
             Some(config::types::LanguageType::Smk)
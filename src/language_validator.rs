@@ -0,0 +1,201 @@
+use crate::config;
+use crate::remote;
+use crate::warning::Warning;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use log::{debug, info};
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+pub fn default_validator(wf_type: &config::types::LanguageType) -> &'static str {
+    match wf_type {
+        config::types::LanguageType::Cwl => "cwltool --validate",
+        config::types::LanguageType::Wdl => "womtool validate",
+        config::types::LanguageType::Nfl => "nextflow lint",
+        config::types::LanguageType::Smk => "snakemake --lint --snakefile",
+    }
+}
+
+/// The last entry for the language wins, falling back to the default validator.
+pub fn validator_command(
+    validator_map: &[(config::types::LanguageType, String)],
+    wf_type: &config::types::LanguageType,
+) -> String {
+    validator_map
+        .iter()
+        .rev()
+        .find(|(language, _)| language == wf_type)
+        .map_or_else(
+            || default_validator(wf_type).to_string(),
+            |(_, command)| command.clone(),
+        )
+}
+
+/// Check if the program is an executable file, looked up in `PATH` unless it contains `/`.
+pub fn is_on_path(program: impl AsRef<str>) -> bool {
+    let program = program.as_ref();
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    match env::var_os("PATH") {
+        Some(paths) => env::split_paths(&paths).any(|dir| dir.join(program).is_file()),
+        None => false,
+    }
+}
+
+/// Validate the workflow with the validator of its language (`validate --deep`),
+/// with the commands of `validator_map` used instead of the default ones.
+/// The workflow files are fetched into a temporary directory laid out by their `target`s,
+/// so that the relative imports of the primary workflow are resolved.
+/// If the validator is not installed, it is skipped with a warning.
+#[cfg(not(tarpaulin_include))]
+pub fn deep_validate(
+    http: &remote::Http,
+    config: &config::types::Config,
+    validator_map: &[(config::types::LanguageType, String)],
+) -> Result<Vec<Warning>> {
+    let wf_type = config
+        .workflow
+        .language
+        .r#type
+        .clone()
+        .ok_or_else(|| anyhow!("Workflow language type must be set"))?;
+    let command = validator_command(validator_map, &wf_type);
+    let program = command.split_whitespace().next().unwrap_or_default();
+    if !is_on_path(program) {
+        return Ok(vec![Warning::emit(
+            "workflow.language",
+            format!(
+                "The {} validator `{}` is not installed, so the deep validation is skipped",
+                wf_type, program
+            ),
+        )]);
+    }
+
+    let dir = env::temp_dir().join(format!(
+        "gh-trs-deep-validate-{}-{}",
+        std::process::id(),
+        uuid::Uuid::new_v4()
+    ));
+    let result = fetch_wf_files(http, config, &dir).and_then(|primary_wf_path| {
+        run_validator(&command, &dir, &primary_wf_path)
+            .with_context(|| format!("The {} validator `{}` failed", wf_type, command))
+    });
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        debug!("Failed to remove {}: {}", dir.display(), e);
+    }
+    result?;
+    Ok(vec![])
+}
+
+/// Fetch the workflow files into `dir` and return the path of the primary workflow relative to `dir`.
+#[cfg(not(tarpaulin_include))]
+fn fetch_wf_files(
+    http: &remote::Http,
+    config: &config::types::Config,
+    dir: &Path,
+) -> Result<PathBuf> {
+    let mut primary_wf_path = None;
+    for file in &config.workflow.files {
+        let target = file
+            .target
+            .clone()
+            .ok_or_else(|| anyhow!("File target must be set: {}", file.url))?;
+        ensure!(
+            target
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir)),
+            "The target {} of {} must be a relative path without `..`",
+            target.display(),
+            file.url
+        );
+        let path = dir.join(&target);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, remote::fetch_raw_bytes(http, &file.url)?)?;
+        if file.is_primary() {
+            primary_wf_path = Some(target);
+        }
+    }
+    primary_wf_path.ok_or_else(|| anyhow!("No primary workflow file"))
+}
+
+/// Run the validator command with `sh -c` in `dir`, with the primary workflow path as the last argument.
+/// A non-zero exit status is an error with the output of the validator.
+pub fn run_validator(
+    command: impl AsRef<str>,
+    dir: impl AsRef<Path>,
+    primary_wf_path: impl AsRef<Path>,
+) -> Result<()> {
+    info!(
+        "Validating {} with `{}`",
+        primary_wf_path.as_ref().display(),
+        command.as_ref()
+    );
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", command.as_ref()))
+        .arg("gh-trs-deep-validate")
+        .arg(primary_wf_path.as_ref())
+        .current_dir(dir.as_ref())
+        .output()
+        .context("Failed to run the validator")?;
+    let log = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    debug!("validator output: {}", log);
+    ensure!(
+        output.status.success(),
+        "{} with output:\n{}",
+        output.status,
+        log.trim_end()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validator_command() {
+        let validator_map = vec![(
+            config::types::LanguageType::Wdl,
+            "java -jar womtool.jar validate".to_string(),
+        )];
+        assert_eq!(
+            validator_command(&validator_map, &config::types::LanguageType::Wdl),
+            "java -jar womtool.jar validate"
+        );
+        assert_eq!(
+            validator_command(&validator_map, &config::types::LanguageType::Cwl),
+            "cwltool --validate"
+        );
+    }
+
+    #[test]
+    fn test_is_on_path() {
+        assert!(is_on_path("sh"));
+        assert!(!is_on_path("gh-trs-no-such-validator"));
+        assert!(!is_on_path("./no/such/validator"));
+    }
+
+    #[test]
+    fn test_run_validator() -> Result<()> {
+        run_validator("test -f", "./tests", "test_config_CWL.yml")?;
+        let err = run_validator(
+            "echo invalid workflow >&2; false",
+            "./tests",
+            "test_config_CWL.yml",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid workflow"));
+        Ok(())
+    }
+}
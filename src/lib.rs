@@ -4,8 +4,11 @@ pub mod config;
 pub mod env;
 pub mod github_api;
 pub mod inspect;
+pub mod language_validator;
 pub mod logger;
 pub mod raw_url;
 pub mod remote;
+pub mod s3;
 pub mod trs;
+pub mod warning;
 pub mod wes;
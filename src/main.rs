@@ -1,17 +1,24 @@
 use anyhow::Result;
 use colored::Colorize;
 use log::{debug, error, info};
+use std::path::PathBuf;
 use std::process::exit;
 use structopt::StructOpt;
 
 #[cfg(not(tarpaulin_include))]
 fn main() -> Result<()> {
     let args = gh_trs::args::Args::from_args();
-    let verbose = match args {
-        gh_trs::args::Args::MakeTemplate { verbose, .. } => verbose,
-        gh_trs::args::Args::Validate { verbose, .. } => verbose,
-        gh_trs::args::Args::Test { verbose, .. } => verbose,
-        gh_trs::args::Args::Publish { verbose, .. } => verbose,
+    let (verbose, http_args) = match &args {
+        gh_trs::args::Args::MakeTemplate { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::Validate { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::Normalize { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::BumpVersion { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::Diff { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::ExportRoCrate { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::PublishWorkflowhub { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::Test { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::Publish { verbose, http, .. } => (*verbose, http.clone()),
+        gh_trs::args::Args::Verify { verbose, http, .. } => (*verbose, http.clone()),
     };
     gh_trs::logger::init_logger(verbose);
 
@@ -22,21 +29,123 @@ fn main() -> Result<()> {
         gh_trs::args::Args::MakeTemplate {
             workflow_location,
             github_token,
+            github_token_file,
             output,
+            format,
             use_commit_url,
+            json,
+            check,
+            max_depth,
+            include,
+            exclude,
+            split_graph,
+            force,
+            language_type,
             ..
         } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, false);
+            let filter = match gh_trs::command::make_template::FileFilter::new(&include, &exclude) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    error!("{} to parse file filter with error: {}", "Failed".red(), e);
+                    exit(1);
+                }
+            };
+            let url_type = match use_commit_url {
+                true => gh_trs::raw_url::UrlType::Commit,
+                false => gh_trs::raw_url::UrlType::Branch,
+            };
+            if let Some(existing_loc) = check {
+                info!("{} make-template --check", "Running".green());
+                match gh_trs::command::make_template::check_template(
+                    &http,
+                    &workflow_location,
+                    &github_token,
+                    url_type,
+                    &language_type,
+                    &existing_loc,
+                    max_depth,
+                    &filter,
+                ) {
+                    Ok((diff, warnings)) if diff.is_empty() => {
+                        report_warnings(&warnings);
+                        info!("{} make-template --check", "Success".green());
+                    }
+                    Ok((diff, warnings)) => {
+                        report_warnings(&warnings);
+                        println!("{}", diff.join("\n"));
+                        error!(
+                            "{} to make-template --check: {} differs from the regenerated template",
+                            "Failed".red(),
+                            existing_loc
+                        );
+                        exit(1);
+                    }
+                    Err(e) => {
+                        error!(
+                            "{} to make-template --check with error: {}",
+                            "Failed".red(),
+                            e
+                        );
+                        exit(1);
+                    }
+                }
+                return Ok(());
+            }
             info!("{} make-template", "Running".green());
-            match gh_trs::command::make_template::make_template(
-                &workflow_location,
-                &github_token,
-                &output,
-                match use_commit_url {
-                    true => gh_trs::raw_url::UrlType::Commit,
-                    false => gh_trs::raw_url::UrlType::Branch,
-                },
-            ) {
-                Ok(()) => info!("{} make-template", "Success".green()),
+            let result = match split_graph {
+                true => gh_trs::command::make_template::make_template_graph(
+                    &http,
+                    &workflow_location,
+                    &github_token,
+                    &output,
+                    url_type,
+                    &language_type,
+                    &format,
+                    max_depth,
+                    &filter,
+                    force,
+                )
+                .and_then(|summaries| {
+                    // The templates split from one document share the warnings.
+                    let warnings = summaries
+                        .first()
+                        .map(|s| s.warnings.clone())
+                        .unwrap_or_default();
+                    Ok((serde_json::to_value(summaries)?, warnings))
+                }),
+                false => gh_trs::command::make_template::make_template(
+                    &http,
+                    &workflow_location,
+                    &github_token,
+                    &output,
+                    url_type,
+                    &language_type,
+                    &format,
+                    max_depth,
+                    &filter,
+                    force,
+                )
+                .and_then(|summary| {
+                    let warnings = summary.warnings.clone();
+                    Ok((serde_json::to_value(summary)?, warnings))
+                }),
+            };
+            match result {
+                Ok((summary, warnings)) => {
+                    report_warnings(&warnings);
+                    info!("{} make-template", "Success".green());
+                    if json {
+                        match serde_json::to_string(&summary) {
+                            Ok(summary) => println!("{}", summary),
+                            Err(e) => {
+                                error!("{} to serialize summary with error: {}", "Failed".red(), e);
+                                exit(1);
+                            }
+                        }
+                    }
+                }
                 Err(e) => {
                     error!("{} to make-template with error: {}", "Failed".red(), e);
                     exit(1);
@@ -46,44 +155,351 @@ fn main() -> Result<()> {
         gh_trs::args::Args::Validate {
             config_locations,
             github_token,
+            github_token_file,
+            strict,
+            validate_params,
+            workers,
+            show_url_changes,
+            offline,
+            cache,
+            json,
+            deep,
+            validator_map,
             ..
         } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, offline);
+            let deep = match deep {
+                true => Some(validator_map),
+                false => None,
+            };
             info!("{} validate", "Running".green());
-            match gh_trs::command::validate::validate(config_locations, &github_token) {
-                Ok(_) => info!("{} validate", "Success".green()),
+            if json {
+                let report = gh_trs::command::validate::validate_report(
+                    &http,
+                    config_locations,
+                    &github_token,
+                    strict,
+                    validate_params,
+                    workers,
+                    show_url_changes,
+                    offline,
+                    cache,
+                    &deep,
+                );
+                match serde_json::to_string(&report) {
+                    Ok(report) => println!("{}", report),
+                    Err(e) => {
+                        error!("{} to serialize report with error: {}", "Failed".red(), e);
+                        exit(1);
+                    }
+                }
+                report_warnings(
+                    &report
+                        .configs
+                        .iter()
+                        .flat_map(|c| c.warnings.clone())
+                        .chain(report.warnings.clone())
+                        .collect::<Vec<_>>(),
+                );
+                if !report.valid {
+                    error!("{} to validate", "Failed".red());
+                    exit(1);
+                }
+                info!("{} validate", "Success".green());
+                return Ok(());
+            }
+            match gh_trs::command::validate::validate(
+                &http,
+                config_locations,
+                &github_token,
+                strict,
+                validate_params,
+                workers,
+                show_url_changes,
+                offline,
+                cache,
+                &deep,
+            ) {
+                Ok((_, warnings)) => {
+                    info!("{} validate", "Success".green());
+                    if offline {
+                        info!("Validated offline, so the URL pinning and the reachability of the files were not checked");
+                    }
+                    report_warnings(&warnings);
+                }
                 Err(e) => {
+                    report_error(&e);
                     error!("{} to validate with error: {}", "Failed".red(), e);
                     exit(1);
                 }
             };
         }
+        gh_trs::args::Args::Normalize {
+            config_location,
+            github_token,
+            github_token_file,
+            output,
+            format,
+            pin_urls,
+            keep_comments,
+            ..
+        } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, false);
+            info!("{} normalize", "Running".green());
+            match gh_trs::command::normalize::normalize(
+                &http,
+                &config_location,
+                &github_token,
+                &output,
+                &format,
+                pin_urls,
+                keep_comments,
+            ) {
+                Ok(warnings) => {
+                    info!("{} normalize", "Success".green());
+                    report_warnings(&warnings);
+                }
+                Err(e) => {
+                    error!("{} to normalize with error: {}", "Failed".red(), e);
+                    exit(1);
+                }
+            }
+        }
+        gh_trs::args::Args::BumpVersion {
+            config_location,
+            part,
+            repo,
+            base_url,
+            cname,
+            output,
+            ..
+        } => {
+            let http = new_http(&http_args, &None, false);
+            info!("{} bump-version", "Running".green());
+            match gh_trs::command::bump_version::bump_version(
+                &http,
+                &config_location,
+                part,
+                &repo,
+                &base_url,
+                &cname,
+                &output,
+            ) {
+                Ok(version) => {
+                    info!("{} bump-version", "Success".green());
+                    println!("{}", version);
+                }
+                Err(e) => {
+                    error!("{} to bump the version with error: {}", "Failed".red(), e);
+                    exit(1);
+                }
+            }
+        }
+        gh_trs::args::Args::Diff {
+            source_trs,
+            target_trs,
+            ..
+        } => {
+            let http = new_http(&http_args, &None, false);
+            info!("{} diff", "Running".green());
+            match gh_trs::command::diff::diff(&http, &source_trs, &target_trs) {
+                Ok(entries) if entries.is_empty() => {
+                    info!(
+                        "{} diff: {} and {} serve the same tools and versions",
+                        "Success".green(),
+                        source_trs,
+                        target_trs
+                    );
+                }
+                Ok(entries) => {
+                    for entry in &entries {
+                        println!("{}", entry);
+                    }
+                    error!(
+                        "{} diff: {} difference(s) between {} and {}",
+                        "Failed".red(),
+                        entries.len(),
+                        source_trs,
+                        target_trs
+                    );
+                    exit(1);
+                }
+                Err(e) => {
+                    error!("{} to diff with error: {}", "Failed".red(), e);
+                    exit(1);
+                }
+            }
+        }
+        gh_trs::args::Args::ExportRoCrate {
+            config_location,
+            github_token,
+            github_token_file,
+            output_dir,
+            force,
+            ..
+        } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, false);
+            info!("{} export-ro-crate", "Running".green());
+            match gh_trs::command::export_ro_crate::export_ro_crate(
+                &http,
+                &config_location,
+                &output_dir,
+                force,
+            ) {
+                Ok(metadata_path) => {
+                    info!(
+                        "{} export-ro-crate: {}",
+                        "Success".green(),
+                        metadata_path.display()
+                    );
+                }
+                Err(e) => {
+                    error!(
+                        "{} to export the RO-Crate with error: {}",
+                        "Failed".red(),
+                        e
+                    );
+                    exit(1);
+                }
+            }
+        }
+        gh_trs::args::Args::PublishWorkflowhub {
+            config_location,
+            team,
+            api_url,
+            workflowhub_token,
+            github_token,
+            github_token_file,
+            json,
+            ..
+        } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, false);
+            info!("{} publish-workflowhub", "Running".green());
+            match gh_trs::command::publish_workflowhub::publish_workflowhub(
+                &http,
+                &config_location,
+                &api_url,
+                &workflowhub_token,
+                team,
+            ) {
+                Ok(workflow) => {
+                    info!(
+                        "{} publish-workflowhub: workflow {} is registered at {}",
+                        "Success".green(),
+                        workflow.id,
+                        workflow.url
+                    );
+                    if json {
+                        match serde_json::to_string(&workflow) {
+                            Ok(workflow) => println!("{}", workflow),
+                            Err(e) => {
+                                error!(
+                                    "{} to serialize workflow with error: {}",
+                                    "Failed".red(),
+                                    e
+                                );
+                                exit(1);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "{} to publish to WorkflowHub with error: {}",
+                        "Failed".red(),
+                        e
+                    );
+                    exit(1);
+                }
+            }
+        }
         gh_trs::args::Args::Test {
             config_locations,
             github_token,
+            github_token_file,
             wes_location,
             docker_host,
+            docker_run_timeout,
+            docker_timeout,
+            run_dir,
+            clean_run_dir,
+            clean_run_dir_min_age,
+            wes_port,
+            binds,
+            wes_tags,
+            engine_map,
+            profile,
+            no_color,
+            fail_fast,
+            test_log,
+            strict,
+            validate_params,
+            workers,
             ..
         } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, false);
+            gh_trs::wes::sapporo::set_docker_timeouts(docker_run_timeout, docker_timeout);
+            if clean_run_dir {
+                gh_trs::wes::sapporo::set_clean_run_dir(Some(std::time::Duration::from_secs(
+                    clean_run_dir_min_age.unwrap_or(0),
+                )));
+            }
+            gh_trs::wes::client::set_engine_map(engine_map);
+            if no_color {
+                colored::control::set_override(false);
+            }
             info!("{} validate", "Running".green());
-            let configs = match gh_trs::command::validate::validate(config_locations, &github_token)
-            {
-                Ok(configs) => {
+            let configs = match gh_trs::command::validate::validate(
+                &http,
+                config_locations,
+                &github_token,
+                strict,
+                validate_params,
+                workers,
+                false,
+                false,
+                false,
+                &None,
+            ) {
+                Ok((configs, warnings)) => {
                     info!("{} validate", "Success".green());
+                    report_warnings(&warnings);
                     configs
                 }
                 Err(e) => {
+                    report_error(&e);
                     error!("{} to validate with error: {}", "Failed".red(), e);
                     exit(1);
                 }
             };
 
             info!("{} test", "Running".green());
-            match gh_trs::command::test::test(&configs, &wes_location, &docker_host, false) {
+            match gh_trs::command::test::test(
+                &http,
+                &configs,
+                &wes_location,
+                &docker_host,
+                wes_port,
+                &binds,
+                &run_dir,
+                false,
+                &wes_tags,
+                &profile,
+                fail_fast,
+                test_log,
+            ) {
                 Ok(()) => info!("{} test", "Success".green()),
                 Err(e) => {
-                    match gh_trs::wes::stop_wes(&docker_host) {
-                        Ok(_) => {}
-                        Err(e) => error!("{} to stop WES with error: {}", "Failed".red(), e),
+                    if wes_location.is_none() {
+                        match gh_trs::wes::sapporo::stop_wes(&docker_host, &run_dir) {
+                            Ok(_) => {}
+                            Err(e) => error!("{} to stop WES with error: {}", "Failed".red(), e),
+                        }
                     }
                     error!("{} to test with error: {}", "Failed".red(), e);
                     exit(1);
@@ -93,19 +509,82 @@ fn main() -> Result<()> {
         gh_trs::args::Args::Publish {
             config_locations,
             github_token,
+            github_token_file,
             repo,
             branch,
             with_test,
             wes_location,
             docker_host,
+            docker_run_timeout,
+            docker_timeout,
+            run_dir,
+            clean_run_dir,
+            clean_run_dir_min_age,
+            wes_port,
+            binds,
+            wes_tags,
+            engine_map,
+            test_log,
             from_trs,
+            resume,
+            manifest,
+            allow_empty,
+            allow_cross_repo,
+            base_branch,
+            initial_readme,
+            base_url,
+            cname,
+            tag,
+            release,
+            require_increasing_version,
+            allow_id_reuse,
+            enable_pages,
+            print_curl,
+            json,
+            s3_bucket,
+            s3_prefix,
+            s3_endpoint,
+            pre_publish_hook,
+            with_html,
+            max_inline_size,
+            strict,
+            validate_params,
+            workers,
             ..
         } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, false);
+            gh_trs::wes::sapporo::set_docker_timeouts(docker_run_timeout, docker_timeout);
+            if clean_run_dir {
+                gh_trs::wes::sapporo::set_clean_run_dir(Some(std::time::Duration::from_secs(
+                    clean_run_dir_min_age.unwrap_or(0),
+                )));
+            }
+            gh_trs::wes::client::set_engine_map(engine_map);
+            // The S3 target and credentials are checked before the (possibly long) validation and test.
+            let s3_target = match &s3_bucket {
+                Some(s3_bucket) => match gh_trs::s3::Credentials::from_env()
+                    .and_then(|c| gh_trs::s3::S3Target::new(&s3_endpoint, s3_bucket, &s3_prefix, c))
+                {
+                    Ok(s3_target) => Some(s3_target),
+                    Err(e) => {
+                        error!("{} to set up S3 target with error: {}", "Failed".red(), e);
+                        exit(1);
+                    }
+                },
+                None => None,
+            };
             let config_locations = if from_trs {
                 info!("Run gh-trs in from_trs mode");
                 info!("TRS endpoint: {}", config_locations[0]);
-                match gh_trs::config::io::find_config_loc_recursively_from_trs(&config_locations[0])
-                {
+                if let Err(e) = gh_trs::config::io::check_trs_loc(&http, &config_locations[0]) {
+                    error!("{} to check TRS endpoint with error: {}", "Failed".red(), e);
+                    exit(1);
+                }
+                match gh_trs::config::io::find_config_loc_recursively_from_trs(
+                    &http,
+                    &config_locations[0],
+                ) {
                     Ok(config_locs) => config_locs,
                     Err(e) => {
                         error!("{} to find config locs with error: {}", "Failed".red(), e);
@@ -116,53 +595,225 @@ fn main() -> Result<()> {
                 config_locations
             };
 
-            info!("{} validate", "Running".green());
-            let configs = match gh_trs::command::validate::validate(config_locations, &github_token)
-            {
-                Ok(configs) => {
-                    info!("{} validate", "Success".green());
+            info!(
+                "{} validate{}",
+                "Running".green(),
+                if with_test { " and test" } else { "" }
+            );
+            let hook_config_locations = config_locations.clone();
+            let configs = match gh_trs::command::publish::prepare_configs(
+                &http,
+                config_locations,
+                &github_token,
+                strict,
+                validate_params,
+                workers,
+                with_test,
+                &wes_location,
+                &docker_host,
+                wes_port,
+                &binds,
+                &run_dir,
+                &wes_tags,
+                test_log,
+                resume,
+            ) {
+                Ok((configs, warnings)) => {
+                    info!(
+                        "{} validate{}",
+                        "Success".green(),
+                        if with_test { " and test" } else { "" }
+                    );
+                    report_warnings(&warnings);
                     configs
                 }
                 Err(e) => {
-                    error!("{} to validate with error: {}", "Failed".red(), e);
+                    if with_test && wes_location.is_none() {
+                        match gh_trs::wes::sapporo::stop_wes(&docker_host, &run_dir) {
+                            Ok(_) => {}
+                            Err(e) => error!("{} to stop WES with error: {}", "Failed".red(), e),
+                        }
+                    }
+                    report_error(&e);
+                    error!(
+                        "{} to validate{} with error: {}",
+                        "Failed".red(),
+                        if with_test { " and test" } else { "" },
+                        e
+                    );
+                    info!(
+                        "The progress is recorded in {}. Re-run with `--resume` to skip the processed configs.",
+                        gh_trs::command::publish::PUBLISH_STATE_FILE
+                    );
                     exit(1);
                 }
             };
+            let verified = with_test;
 
-            let verified = if with_test {
-                info!("{} test", "Running".green());
-                match gh_trs::command::test::test(&configs, &wes_location, &docker_host, true) {
-                    Ok(()) => info!("{} test", "Success".green()),
-                    Err(e) => {
-                        match gh_trs::wes::stop_wes(&docker_host) {
-                            Ok(_) => {}
-                            Err(e) => error!("{} to stop WES with error: {}", "Failed".red(), e),
-                        }
-                        error!("{} to test with error: {}", "Failed".red(), e);
+            if let Some(hook) = &pre_publish_hook {
+                for config_location in &hook_config_locations {
+                    if let Err(e) =
+                        gh_trs::command::publish::run_pre_publish_hook(hook, config_location)
+                    {
+                        error!(
+                            "{} to run pre-publish hook with error: {}",
+                            "Failed".red(),
+                            e
+                        );
                         exit(1);
                     }
                 }
-                true
-            } else {
-                false
-            };
+            }
 
             info!("{} publish", "Running".green());
             match gh_trs::command::publish::publish(
+                &http,
                 &configs,
                 &github_token,
                 &repo,
                 &branch,
                 verified,
+                manifest,
+                allow_empty,
+                &base_branch,
+                &initial_readme,
+                with_html,
+                max_inline_size,
+                strict,
+                allow_cross_repo,
+                &base_url,
+                &cname,
+                &tag,
+                release,
+                require_increasing_version,
+                allow_id_reuse,
+                enable_pages,
+                print_curl,
+                &s3_target,
             ) {
-                Ok(()) => info!("{} publish", "Success".green()),
+                Ok(metrics) => {
+                    info!("{} publish", "Success".green());
+                    info!("{}", metrics.summary_line());
+                    if json {
+                        match serde_json::to_string(&metrics) {
+                            Ok(metrics) => println!("{}", metrics),
+                            Err(e) => {
+                                error!("{} to serialize summary with error: {}", "Failed".red(), e);
+                                exit(1);
+                            }
+                        }
+                    }
+                    if let Err(e) = gh_trs::command::publish::PublishState::remove(
+                        gh_trs::command::publish::PUBLISH_STATE_FILE,
+                    ) {
+                        error!(
+                            "{} to remove publish state with error: {}",
+                            "Failed".red(),
+                            e
+                        );
+                    }
+                }
                 Err(e) => {
                     error!("{} to publish with error: {}", "Failed".red(), e);
                     exit(1);
                 }
             }
         }
+        gh_trs::args::Args::Verify {
+            config_locations,
+            github_token,
+            github_token_file,
+            repo,
+            branch,
+            base_url,
+            cname,
+            timeout,
+            no_wait,
+            ..
+        } => {
+            let github_token = resolve_github_token(github_token, github_token_file);
+            let http = new_http(&http_args, &github_token, false);
+            info!("{} verify", "Running".green());
+            match gh_trs::command::verify::verify(
+                &http,
+                &config_locations,
+                &github_token,
+                &repo,
+                &branch,
+                &base_url,
+                &cname,
+                !no_wait,
+                std::time::Duration::from_secs(timeout),
+            ) {
+                Ok(verified) => {
+                    for tool_version in &verified {
+                        println!("{}", tool_version);
+                    }
+                    info!("{} verify", "Success".green());
+                }
+                Err(e) => {
+                    error!("{} to verify with error: {}", "Failed".red(), e);
+                    exit(1);
+                }
+            }
+        }
     }
 
     Ok(())
 }
+
+/// In GitHub Actions, print the warnings as workflow commands so that they are shown as annotations.
+#[cfg(not(tarpaulin_include))]
+fn report_warnings(warnings: &[gh_trs::warning::Warning]) {
+    if gh_trs::env::in_github_actions() {
+        for warning in warnings {
+            eprintln!("{}", warning.to_gh_annotation());
+        }
+    }
+}
+
+/// In GitHub Actions, print an error found in a config as a workflow command so that it is shown inline.
+/// Other errors are only logged as usual.
+#[cfg(not(tarpaulin_include))]
+fn report_error(e: &anyhow::Error) {
+    if gh_trs::env::in_github_actions() {
+        if let Some(config_error) = e.downcast_ref::<gh_trs::warning::ConfigError>() {
+            eprintln!("{}", config_error.to_gh_annotation());
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn resolve_github_token(
+    github_token: Option<String>,
+    github_token_file: Option<PathBuf>,
+) -> Option<String> {
+    match gh_trs::env::arg_github_token(github_token, github_token_file) {
+        Ok(github_token) => github_token,
+        Err(e) => {
+            error!("{} to read GitHub token with error: {}", "Failed".red(), e);
+            exit(1);
+        }
+    }
+}
+
+/// The HTTP client with the settings of `HttpArgs`.
+/// The GitHub token (if any) is also used to fetch the raw content of private repos.
+#[cfg(not(tarpaulin_include))]
+fn new_http(
+    http_args: &gh_trs::args::HttpArgs,
+    github_token: &Option<String>,
+    offline: bool,
+) -> gh_trs::remote::Http {
+    match gh_trs::remote::Http::new(
+        http_args,
+        gh_trs::env::github_token(github_token).ok(),
+        offline,
+    ) {
+        Ok(http) => http,
+        Err(e) => {
+            error!("{} to set up HTTP client with error: {}", "Failed".red(), e);
+            exit(1);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::github_api;
+use crate::remote;
 use anyhow::{anyhow, ensure, Result};
 use regex::Regex;
 use std::collections::HashMap;
@@ -32,31 +33,20 @@ impl RawUrl {
     /// - https://raw.githubusercontent.com/<owner>/<name>/<branch>/<path_to_file>
     /// - https://raw.githubusercontent.com/<owner>/<name>/<commit_hash>/<path_to_file>
     pub fn new(
+        http: &remote::Http,
         gh_token: impl AsRef<str>,
         url: &Url,
         branch_memo: Option<&mut HashMap<String, String>>,
         commit_memo: Option<&mut HashMap<String, String>>,
     ) -> Result<Self> {
+        let (owner, name) = Self::owner_name(url)?;
         let host = url
             .host_str()
             .ok_or_else(|| anyhow!("No host found in URL: {}", url))?;
-        ensure!(
-            host == "github.com" || host == "raw.githubusercontent.com",
-            "Only GitHub URLs are supported, your input URL: {}",
-            url
-        );
         let path_segments = url
             .path_segments()
             .ok_or_else(|| anyhow!("Failed to parse URL path: {}", url))?
             .collect::<Vec<_>>();
-        let owner = path_segments
-            .get(0)
-            .ok_or_else(|| anyhow!("No repo owner found in URL: {}", url))?
-            .to_string();
-        let name = path_segments
-            .get(1)
-            .ok_or_else(|| anyhow!("No repo name found in URL: {}", url))?
-            .to_string();
         let branch_or_commit = match host {
             "github.com" => path_segments
                 .get(3)
@@ -66,15 +56,17 @@ impl RawUrl {
                 .ok_or_else(|| anyhow!("No branch or commit found in URL: {}", url))?,
             _ => unreachable!(),
         };
-        let (branch, commit) = match is_commit_hash(&branch_or_commit) {
+        let (branch, commit) = match is_commit_hash(branch_or_commit) {
             Ok(_) => {
                 let commit = branch_or_commit.to_string();
-                let branch = github_api::get_default_branch(gh_token, &owner, &name, branch_memo)?;
+                let branch =
+                    github_api::get_default_branch(http, gh_token, &owner, &name, branch_memo)?;
                 (branch, commit)
             }
             Err(_) => {
                 let branch = branch_or_commit.to_string();
                 let commit = github_api::get_latest_commit_sha(
+                    http,
                     gh_token,
                     &owner,
                     &name,
@@ -98,6 +90,31 @@ impl RawUrl {
         })
     }
 
+    /// Extract the repo owner and name from the workflow location (same formats as `new`) without accessing the GitHub API.
+    pub fn owner_name(url: &Url) -> Result<(String, String)> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("No host found in URL: {}", url))?;
+        ensure!(
+            host == "github.com" || host == "raw.githubusercontent.com",
+            "Only GitHub URLs are supported, your input URL: {}",
+            url
+        );
+        let path_segments = url
+            .path_segments()
+            .ok_or_else(|| anyhow!("Failed to parse URL path: {}", url))?
+            .collect::<Vec<_>>();
+        let owner = path_segments
+            .first()
+            .ok_or_else(|| anyhow!("No repo owner found in URL: {}", url))?
+            .to_string();
+        let name = path_segments
+            .get(1)
+            .ok_or_else(|| anyhow!("No repo name found in URL: {}", url))?
+            .to_string();
+        Ok((owner, name))
+    }
+
     pub fn file_stem(&self) -> Result<String> {
         Ok(self
             .file_path
@@ -190,6 +207,7 @@ mod tests {
 
     #[test]
     fn test_raw_url() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let owner = "suecharo".to_string();
         let name = "gh-trs".to_string();
@@ -226,10 +244,10 @@ mod tests {
             &file_path.to_string_lossy()
         ))?;
 
-        let raw_url_1 = RawUrl::new(&gh_token, &url_1, None, None)?;
-        let raw_url_2 = RawUrl::new(&gh_token, &url_2, None, None)?;
-        let raw_url_3 = RawUrl::new(&gh_token, &url_3, None, None)?;
-        let raw_url_4 = RawUrl::new(&gh_token, &url_4, None, None)?;
+        let raw_url_1 = RawUrl::new(&http, &gh_token, &url_1, None, None)?;
+        let raw_url_2 = RawUrl::new(&http, &gh_token, &url_2, None, None)?;
+        let raw_url_3 = RawUrl::new(&http, &gh_token, &url_3, None, None)?;
+        let raw_url_4 = RawUrl::new(&http, &gh_token, &url_4, None, None)?;
 
         let expect = RawUrl {
             owner,
@@ -258,9 +276,10 @@ mod tests {
 
     #[test]
     fn test_raw_url_invalid_url() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let url = Url::parse("https://example.com/path/to/file")?;
-        let err = RawUrl::new(&gh_token, &url, None, None).unwrap_err();
+        let err = RawUrl::new(&http, &gh_token, &url, None, None).unwrap_err();
         assert_eq!(
             err.to_string(),
             "Only GitHub URLs are supported, your input URL: https://example.com/path/to/file"
@@ -270,9 +289,10 @@ mod tests {
 
     #[test]
     fn test_raw_url_invalid_host() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let url = Url::parse("https://example.com/path/to/file")?;
-        let err = RawUrl::new(&gh_token, &url, None, None).unwrap_err();
+        let err = RawUrl::new(&http, &gh_token, &url, None, None).unwrap_err();
         assert_eq!(
             err.to_string(),
             "Only GitHub URLs are supported, your input URL: https://example.com/path/to/file"
@@ -282,10 +302,11 @@ mod tests {
 
     #[test]
     fn test_raw_url_invalid_path() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let url =
             Url::parse("https://github.com/suecharo/gh-trs/blob/invalid_branch/path/to/workflow")?;
-        assert!(RawUrl::new(&gh_token, &url, None, None).is_err());
+        assert!(RawUrl::new(&http, &gh_token, &url, None, None).is_err());
         Ok(())
     }
 
@@ -298,6 +319,7 @@ mod tests {
 
     #[test]
     fn test_base_dir() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let owner = "suecharo".to_string();
         let name = "gh-trs".to_string();
@@ -310,7 +332,7 @@ mod tests {
             &commit,
             &file_path.to_string_lossy()
         ))?;
-        let raw_url = RawUrl::new(&gh_token, &url, None, None)?;
+        let raw_url = RawUrl::new(&http, &gh_token, &url, None, None)?;
         let base_dir = raw_url.base_dir()?;
         assert_eq!(base_dir, PathBuf::from("path/to"));
         Ok(())
@@ -318,6 +340,7 @@ mod tests {
 
     #[test]
     fn test_to_url() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let owner = "suecharo".to_string();
         let name = "gh-trs".to_string();
@@ -330,7 +353,7 @@ mod tests {
             &commit,
             &file_path.to_string_lossy()
         ))?;
-        let raw_url = RawUrl::new(&gh_token, &url, None, None)?;
+        let raw_url = RawUrl::new(&http, &gh_token, &url, None, None)?;
         let to_url = raw_url.to_url(&UrlType::Commit)?;
         assert_eq!(
             to_url,
@@ -347,6 +370,7 @@ mod tests {
 
     #[test]
     fn test_to_base_url() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let gh_token = env::github_token(&None::<String>)?;
         let owner = "suecharo".to_string();
         let name = "gh-trs".to_string();
@@ -359,7 +383,7 @@ mod tests {
             &commit,
             &file_path.to_string_lossy()
         ))?;
-        let raw_url = RawUrl::new(&gh_token, &url, None, None)?;
+        let raw_url = RawUrl::new(&http, &gh_token, &url, None, None)?;
         let to_url = raw_url.to_base_url(&UrlType::Commit)?;
         assert_eq!(
             to_url,
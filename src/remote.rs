@@ -1,13 +1,457 @@
-use anyhow::{ensure, Result};
+use crate::args::HttpArgs;
+use crate::github_api;
+
+use anyhow::{ensure, Context, Result};
+use colored::Colorize;
+use log::{debug, warn};
 use reqwest;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time;
 use url::Url;
 
-pub fn fetch_raw_content(remote_loc: &Url) -> Result<String> {
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(remote_loc.as_str())
-        .header(reqwest::header::ACCEPT, "plain/text")
-        .send()?;
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// Default maximum size in bytes of a raw content (a workflow file) to fetch.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+/// Number of retries for transient failures (connection errors, timeouts, 429 and 5xx).
+pub const MAX_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY_MS: u64 = 1000;
+
+/// The HTTP settings of a command (`HttpArgs`, the GitHub token and `validate --offline`),
+/// passed to every function that sends a request to GitHub, TRS, WES or a raw content host.
+pub struct Http {
+    /// Shared by all threads (a clone shares the connection pool). None in offline mode.
+    client: Option<reqwest::blocking::Client>,
+    proxy: Option<Url>,
+    ca_cert: Option<reqwest::Certificate>,
+    insecure_skip_tls_verify: bool,
+    user_agent: String,
+    concurrency: usize,
+    in_flight: Arc<(Mutex<usize>, Condvar)>,
+    max_file_size: u64,
+    /// Used to fetch the raw content of private repos.
+    gh_token: Option<String>,
+    config_auth_header: Option<(String, String)>,
+    request_count: AtomicUsize,
+    private_repos: Mutex<BTreeMap<String, bool>>,
+}
+
+impl Http {
+    /// In offline mode (`validate --offline`), all HTTP requests are disallowed, so that no HTTP client is even built.
+    pub fn new(args: &HttpArgs, gh_token: Option<String>, offline: bool) -> Result<Self> {
+        let ca_cert = match &args.ca_cert {
+            Some(path) => Some(read_ca_cert(path)?),
+            None => None,
+        };
+        if args.insecure_skip_tls_verify {
+            warn!(
+                "{}: TLS certificate verification is disabled by `--insecure-skip-tls-verify`. Connections are NOT secure.",
+                "Warning".red().bold()
+            );
+        }
+        let mut http = Self {
+            client: None,
+            proxy: args.proxy.clone(),
+            ca_cert,
+            insecure_skip_tls_verify: args.insecure_skip_tls_verify,
+            user_agent: args.user_agent.clone().unwrap_or_else(default_user_agent),
+            concurrency: args.concurrency.max(1),
+            in_flight: Arc::new((Mutex::new(0), Condvar::new())),
+            max_file_size: args.max_file_size,
+            gh_token,
+            config_auth_header: args.config_auth_header.clone(),
+            request_count: AtomicUsize::new(0),
+            private_repos: Mutex::new(BTreeMap::new()),
+        };
+        if !offline {
+            http.client = Some(http.client_builder().build()?);
+        }
+        Ok(http)
+    }
+
+    /// The maximum number of simultaneous HTTP requests (`--concurrency`).
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    /// Number of HTTP requests sent so far with these settings, including the retries.
+    pub fn request_count(&self) -> usize {
+        self.request_count.load(Ordering::SeqCst)
+    }
+
+    /// Block until the number of in-flight requests is below the concurrency limit.
+    pub fn request_permit(&self) -> RequestPermit {
+        let (lock, cvar) = &*self.in_flight;
+        let mut in_flight = lock.lock().unwrap_or_else(|e| e.into_inner());
+        while *in_flight >= self.concurrency {
+            in_flight = cvar.wait(in_flight).unwrap_or_else(|e| e.into_inner());
+        }
+        *in_flight += 1;
+        RequestPermit {
+            in_flight: Arc::clone(&self.in_flight),
+        }
+    }
+
+    /// The base builder of all HTTP clients, so that the common settings apply everywhere.
+    /// Responses compressed with gzip, deflate or brotli are decompressed automatically.
+    pub fn client_builder(&self) -> reqwest::blocking::ClientBuilder {
+        let mut builder = reqwest::blocking::Client::builder()
+            .user_agent(self.user_agent.as_str())
+            .gzip(true)
+            .deflate(true)
+            .brotli(true);
+        if let Some(cert) = self.ca_cert.clone() {
+            builder = builder.add_root_certificate(cert);
+        }
+        if self.insecure_skip_tls_verify {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        // If not set, reqwest uses the proxy env vars (respecting `NO_PROXY`).
+        if let Some(proxy) = self.proxy.clone() {
+            let no_proxy = env::var("NO_PROXY")
+                .or_else(|_| env::var("no_proxy"))
+                .unwrap_or_default();
+            builder = builder.proxy(reqwest::Proxy::custom(move |url| match url.host_str() {
+                Some(host) if is_no_proxy(&no_proxy, host) => None,
+                _ => Some(proxy.clone()),
+            }));
+        }
+        builder
+    }
+
+    /// The HTTP client shared by all threads (a clone shares the connection pool).
+    pub fn client(&self) -> Result<reqwest::blocking::Client> {
+        self.client
+            .clone()
+            .context("Network access is disabled in offline mode")
+    }
+
+    /// Send the request, counted in `request_count`. All the requests are sent through this (or `send_with_retry`).
+    pub fn send(
+        &self,
+        request: reqwest::blocking::RequestBuilder,
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        self.request_count.fetch_add(1, Ordering::SeqCst);
+        request.send()
+    }
+
+    /// Send the request built by `request`, retrying transient failures up to `MAX_RETRIES` times with exponential backoff.
+    pub fn send_with_retry(
+        &self,
+        request: impl Fn() -> reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::Response> {
+        let mut attempt = 0;
+        loop {
+            let transient = match self.send(request()) {
+                Ok(response) if is_transient_status(response.status()) => {
+                    format!("status code {}", response.status())
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if e.is_timeout() || e.is_connect() => e.to_string(),
+                Err(e) => return Err(e.into()),
+            };
+            ensure!(
+                attempt < MAX_RETRIES,
+                "Request failed after {} retries: {}",
+                MAX_RETRIES,
+                transient
+            );
+            let delay = RETRY_BASE_DELAY_MS * 2u64.pow(attempt);
+            warn!(
+                "{}: Request failed with {}, retrying in {} ms",
+                "Warning".yellow(),
+                transient,
+                delay
+            );
+            thread::sleep(time::Duration::from_millis(delay));
+            attempt += 1;
+        }
+    }
+
+    /// `config_auth_header` with the GitHub token and the `--config-auth-header` of these settings.
+    pub fn auth_header(&self, remote_loc: &Url) -> Option<(String, String)> {
+        config_auth_header(remote_loc, &self.gh_token, &self.config_auth_header)
+    }
+}
+
+/// Held while an HTTP request is in flight. Released on drop.
+pub struct RequestPermit {
+    in_flight: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.in_flight;
+        let mut in_flight = lock.lock().unwrap_or_else(|e| e.into_inner());
+        *in_flight -= 1;
+        cvar.notify_one();
+    }
+}
+
+/// A response returned with the permit of its request, which is released when the body has been read (on drop),
+/// so that `--concurrency` also bounds the downloads streamed by the caller, not only the round trips of the headers.
+pub struct PermittedResponse {
+    response: reqwest::blocking::Response,
+    _permit: RequestPermit,
+}
+
+impl PermittedResponse {
+    pub fn new(response: reqwest::blocking::Response, permit: RequestPermit) -> Self {
+        Self {
+            response,
+            _permit: permit,
+        }
+    }
+
+    pub fn text(self) -> Result<String> {
+        Ok(self.response.text()?)
+    }
+}
+
+impl std::ops::Deref for PermittedResponse {
+    type Target = reqwest::blocking::Response;
+
+    fn deref(&self) -> &Self::Target {
+        &self.response
+    }
+}
+
+impl Read for PermittedResponse {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.response.read(buf)
+    }
+}
+
+/// `gh-trs/<version>`
+pub fn default_user_agent() -> String {
+    format!("gh-trs/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Read a CA certificate (PEM or DER) to add to the root store of the HTTP clients (`--ca-cert`), e.g., for an internal CA.
+pub fn read_ca_cert(path: impl AsRef<Path>) -> Result<reqwest::Certificate> {
+    let buf = fs::read(path.as_ref())
+        .with_context(|| format!("Failed to read CA certificate: {}", path.as_ref().display()))?;
+    reqwest::Certificate::from_pem(&buf)
+        .or_else(|_| reqwest::Certificate::from_der(&buf))
+        .with_context(|| {
+            format!(
+                "Failed to parse CA certificate as PEM or DER: {}",
+                path.as_ref().display()
+            )
+        })
+}
+
+/// Check if the host matches the `NO_PROXY` list (comma-separated, `*` matches all hosts).
+/// A domain entry matches the domain itself and its subdomains.
+pub fn is_no_proxy(no_proxy: impl AsRef<str>, host: impl AsRef<str>) -> bool {
+    let host = host.as_ref().trim_start_matches('[').trim_end_matches(']');
+    no_proxy
+        .as_ref()
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches('.'))
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| entry == "*" || host == entry || host.ends_with(&format!(".{}", entry)))
+}
+
+pub fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The text is decoded in the charset of the response (UTF-8 if not given), as `reqwest::blocking::Response::text` does.
+pub fn fetch_raw_content(http: &Http, remote_loc: &Url) -> Result<String> {
+    let (response, max_file_size) = fetch_raw_checked(http, remote_loc)?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let bytes = read_limited(remote_loc, response, max_file_size)?;
+    Ok(decode_body(&bytes, content_type.as_deref()))
+}
+
+/// Fetch the content as is, without decoding it as text (e.g., for binary files and checksums).
+/// The body is read up to `--max-file-size`, so a huge file (e.g., a BAM) is not loaded into memory.
+pub fn fetch_raw_bytes(http: &Http, remote_loc: &Url) -> Result<Vec<u8>> {
+    let (response, max_file_size) = fetch_raw_checked(http, remote_loc)?;
+    read_limited(remote_loc, response, max_file_size)
+}
+
+/// The response of the raw content (through the contents API for a private repo) and `--max-file-size`,
+/// checked against the Content-Length if any before the body is read.
+fn fetch_raw_checked(http: &Http, remote_loc: &Url) -> Result<(PermittedResponse, u64)> {
+    let max_file_size = http.max_file_size;
+    let response = match fetch_private_github_content(http, remote_loc)? {
+        Some(response) => response,
+        None => fetch_raw_response(http, remote_loc)?,
+    };
+    if let Some(content_length) = response.content_length() {
+        check_file_size(remote_loc, content_length, max_file_size)?;
+    }
+    Ok((response, max_file_size))
+}
+
+/// Decode the body in the charset of the `Content-Type` (UTF-8 if not given or unknown), replacing the malformed sequences.
+pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(|content_type| {
+            content_type.split(';').skip(1).find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                match key.trim().eq_ignore_ascii_case("charset") {
+                    true => Some(value.trim().trim_matches('"').to_string()),
+                    false => None,
+                }
+            })
+        })
+        .and_then(|charset| encoding_rs::Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+fn check_file_size(remote_loc: &Url, size: u64, max_file_size: u64) -> Result<()> {
+    ensure!(
+        max_file_size == 0 || size <= max_file_size,
+        "The content of {} is larger than the max file size {} bytes. Use --max-file-size to raise the limit.",
+        remote_loc,
+        max_file_size
+    );
+    Ok(())
+}
+
+/// Read the body, aborting as soon as it exceeds `max_file_size` bytes (0 means no limit).
+pub fn read_limited(remote_loc: &Url, body: impl Read, max_file_size: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    copy_limited(remote_loc, body, &mut bytes, max_file_size)?;
+    Ok(bytes)
+}
+
+/// Copy the body to the sink in chunks, aborting as soon as it exceeds `max_file_size` bytes (0 means no limit).
+/// Returns the number of bytes copied.
+pub fn copy_limited(
+    remote_loc: &Url,
+    body: impl Read,
+    sink: &mut impl Write,
+    max_file_size: u64,
+) -> Result<u64> {
+    let copied = match max_file_size {
+        0 => {
+            let mut body = body;
+            io::copy(&mut body, sink)?
+        }
+        _ => io::copy(&mut body.take(max_file_size + 1), sink)?,
+    };
+    check_file_size(remote_loc, copied, max_file_size)?;
+    Ok(copied)
+}
+
+/// Stream the content to the sink (e.g., a hasher) in chunks, so that the memory use is bounded whatever the size.
+/// It is also limited by `--max-file-size`, so that a huge file (e.g., a BAM) is not downloaded in full just for its checksum.
+pub fn fetch_raw_stream(http: &Http, remote_loc: &Url, sink: &mut impl Write) -> Result<u64> {
+    let (response, max_file_size) = fetch_raw_checked(http, remote_loc)?;
+    copy_limited(remote_loc, response, sink, max_file_size)
+}
+
+/// `raw.githubusercontent.com` does not accept the token, so the content of a private repo
+/// is fetched through the authenticated contents API instead, as a response to be streamed like the public ones.
+/// `None` if it is not a raw URL of a private repo (or no token is set), to be fetched as usual.
+fn fetch_private_github_content(
+    http: &Http,
+    remote_loc: &Url,
+) -> Result<Option<PermittedResponse>> {
+    let gh_token = match &http.gh_token {
+        Some(gh_token) => gh_token,
+        None => return Ok(None),
+    };
+    let (owner, name, git_ref, path) = match parse_raw_github_url(remote_loc) {
+        Some(location) => location,
+        None => return Ok(None),
+    };
+    if !is_private_repo(http, gh_token, &owner, &name) {
+        return Ok(None);
+    }
+    debug!(
+        "Fetch {} through the contents API as {}/{} is private",
+        remote_loc, owner, name
+    );
+    Ok(Some(github_api::get_file_raw(
+        http, gh_token, &owner, &name, &path, &git_ref,
+    )?))
+}
+
+/// Split `https://raw.githubusercontent.com/<owner>/<name>/<ref>/<path>` into (owner, name, ref, path).
+pub fn parse_raw_github_url(url: &Url) -> Option<(String, String, String, String)> {
+    if url.host_str() != Some("raw.githubusercontent.com") {
+        return None;
+    }
+    let segments = url.path_segments()?.collect::<Vec<_>>();
+    if segments.len() < 4 || segments[3..].iter().all(|s| s.is_empty()) {
+        return None;
+    }
+    Some((
+        segments[0].to_string(),
+        segments[1].to_string(),
+        segments[2].to_string(),
+        segments[3..].join("/"),
+    ))
+}
+
+/// Looked up once per repo with `github_api::get_repos`. A repo that can not be looked up is treated as public.
+fn is_private_repo(http: &Http, gh_token: &str, owner: &str, name: &str) -> bool {
+    let key = format!("{}/{}", owner, name);
+    if let Some(private) = http
+        .private_repos
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key)
+    {
+        return *private;
+    }
+    let private = match github_api::get_repos(http, gh_token, owner, name) {
+        Ok(res) => res
+            .get("private")
+            .and_then(|p| p.as_bool())
+            .unwrap_or(false),
+        Err(e) => {
+            debug!("Failed to get the visibility of {}: {}", key, e);
+            false
+        }
+    };
+    http.private_repos
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key, private);
+    private
+}
+
+/// Prefer text, but accept any media type, as the raw content is the same whatever the server negotiates.
+pub const RAW_CONTENT_ACCEPT: &str = "text/plain, */*;q=0.8";
+
+/// A server that still refuses the `Accept` header (406 Not Acceptable) is asked again without it.
+fn fetch_raw_response(http: &Http, remote_loc: &Url) -> Result<PermittedResponse> {
+    let permit = http.request_permit();
+    let client = http.client()?;
+    let mut response = http.send_with_retry(|| {
+        client
+            .get(remote_loc.as_str())
+            .header(reqwest::header::ACCEPT, RAW_CONTENT_ACCEPT)
+    })?;
+    if response.status() == reqwest::StatusCode::NOT_ACCEPTABLE {
+        debug!(
+            "{} refused Accept: {}, so fetch it again without the header",
+            remote_loc, RAW_CONTENT_ACCEPT
+        );
+        response = http.send_with_retry(|| client.get(remote_loc.as_str()))?;
+    }
     ensure!(
         response.status().is_success(),
         "Failed to fetch raw content from {} with status code {}",
@@ -15,15 +459,64 @@ pub fn fetch_raw_content(remote_loc: &Url) -> Result<String> {
         response.status()
     );
 
-    Ok(response.text()?)
+    Ok(PermittedResponse::new(response, permit))
 }
 
-pub fn fetch_json_content(remote_loc: &Url) -> Result<String> {
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(remote_loc.as_str())
-        .header(reqwest::header::ACCEPT, "application/json")
-        .send()?;
+/// Decode the content as UTF-8 text. `None` if it is binary (contains a NUL byte or is not valid UTF-8).
+pub fn decode_text(bytes: Vec<u8>) -> Option<String> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The header to authenticate the request for a remote config or a TRS endpoint:
+/// the GitHub token for `api.github.com`, and the `--config-auth-header` for the hosts outside GitHub.
+/// The raw URLs of private repos are fetched through the contents API instead (`fetch_private_github_content`),
+/// so no header is attached for GitHub hosts other than the API (including GitHub Pages, `*.github.io`).
+pub fn config_auth_header(
+    remote_loc: &Url,
+    gh_token: &Option<String>,
+    config_auth_header: &Option<(String, String)>,
+) -> Option<(String, String)> {
+    match remote_loc.host_str() {
+        Some("api.github.com") => gh_token
+            .as_ref()
+            .map(|gh_token| ("Authorization".to_string(), format!("token {}", gh_token))),
+        Some(host) if is_github_host(host) => None,
+        _ => config_auth_header.clone(),
+    }
+}
+
+fn is_github_host(host: &str) -> bool {
+    let host = host.to_lowercase();
+    ["github.com", "githubusercontent.com", "github.io"]
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+}
+
+/// Fetch a remote config. A config in a private repo or on an authenticated host is fetched with the credentials
+/// (see `config_auth_header`). A contents API URL of `api.github.com` is fetched as the raw content.
+pub fn fetch_json_content(http: &Http, remote_loc: &Url) -> Result<String> {
+    if let Some(response) = fetch_private_github_content(http, remote_loc)? {
+        return response.text();
+    }
+    let auth_header = http.auth_header(remote_loc);
+    let accept = match remote_loc.host_str() {
+        Some("api.github.com") => "application/vnd.github.v3.raw",
+        _ => "application/json",
+    };
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let response = http.send_with_retry(|| {
+        let request = client
+            .get(remote_loc.as_str())
+            .header(reqwest::header::ACCEPT, accept);
+        match &auth_header {
+            Some((name, value)) => request.header(name.as_str(), value.as_str()),
+            None => request,
+        }
+    })?;
     ensure!(
         response.status().is_success(),
         "Failed to fetch json content from {} with status code {}",
@@ -38,13 +531,282 @@ pub fn fetch_json_content(remote_loc: &Url) -> Result<String> {
 #[cfg(not(tarpaulin_include))]
 mod tests {
     use super::*;
+    use std::thread;
+    use std::time;
     use url::Url;
 
+    #[test]
+    fn test_decode_body() {
+        assert_eq!(
+            decode_body("cwlVersion: v1.0\n".as_bytes(), None),
+            "cwlVersion: v1.0\n"
+        );
+        // `é` in ISO-8859-1 is decoded by the charset, not as (invalid) UTF-8.
+        assert_eq!(
+            decode_body(b"label: caf\xe9\n", Some("text/plain; charset=ISO-8859-1")),
+            "label: café\n"
+        );
+        assert_eq!(
+            decode_body(
+                "label: café\n".as_bytes(),
+                Some("text/plain; Charset=\"utf-8\"")
+            ),
+            "label: café\n"
+        );
+        assert_eq!(
+            decode_body(b"caf\xe9", Some("text/plain; charset=unknown")),
+            "caf\u{fffd}"
+        );
+    }
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(
+            decode_text("cwlVersion: v1.0\n".as_bytes().to_vec()),
+            Some("cwlVersion: v1.0\n".to_string())
+        );
+        assert_eq!(
+            decode_text(vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00]),
+            None
+        );
+        assert_eq!(decode_text(vec![b'a', 0x00, b'b']), None);
+        assert_eq!(decode_text(vec![0xff, 0xfe]), None);
+    }
+
+    #[test]
+    fn test_is_no_proxy() -> Result<()> {
+        let no_proxy = "localhost, .example.com,192.168.1.1";
+        assert!(is_no_proxy(no_proxy, "localhost"));
+        assert!(is_no_proxy(no_proxy, "example.com"));
+        assert!(is_no_proxy(no_proxy, "api.example.com"));
+        assert!(is_no_proxy(no_proxy, "192.168.1.1"));
+        assert!(!is_no_proxy(no_proxy, "notexample.com"));
+        assert!(!is_no_proxy(no_proxy, "github.com"));
+        assert!(is_no_proxy("*", "github.com"));
+        assert!(!is_no_proxy("", "github.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_ca_cert_invalid() -> Result<()> {
+        let path = env::temp_dir().join("gh-trs-test-invalid-ca-cert.pem");
+        fs::write(&path, "not a certificate")?;
+        assert!(read_ca_cert(&path).is_err());
+        let args = HttpArgs {
+            ca_cert: Some(path.clone()),
+            ..Default::default()
+        };
+        assert!(Http::new(&args, None, false).is_err());
+        fs::remove_file(&path)?;
+        assert!(read_ca_cert("/path/to/not_found.pem").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_offline() -> Result<()> {
+        let http = Http::new(&HttpArgs::default(), None, true)?;
+        let url = Url::parse("https://raw.githubusercontent.com/suecharo/gh-trs/main/README.md")?;
+        let err = fetch_raw_content(&http, &url).unwrap_err();
+        assert!(err.to_string().contains("offline mode"));
+        assert_eq!(http.request_count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_transient_status() {
+        assert!(is_transient_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_transient_status(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(!is_transient_status(reqwest::StatusCode::NOT_FOUND));
+        assert!(!is_transient_status(reqwest::StatusCode::OK));
+    }
+
+    #[test]
+    fn test_request_permit() -> Result<()> {
+        let args = HttpArgs {
+            concurrency: 2,
+            ..Default::default()
+        };
+        let http = Http::new(&args, None, false)?;
+        let max_observed = AtomicUsize::new(0);
+        let running = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..6 {
+                scope.spawn(|| {
+                    let _permit = http.request_permit();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_observed.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(time::Duration::from_millis(50));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert!(max_observed.load(Ordering::SeqCst) <= 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_limited() -> Result<()> {
+        let url = Url::parse("https://example.com/large.bam")?;
+        let body = vec![b'a'; 10];
+        assert_eq!(read_limited(&url, body.as_slice(), 10)?.len(), 10);
+        assert_eq!(read_limited(&url, body.as_slice(), 0)?.len(), 10);
+        let mut sink = Vec::new();
+        assert_eq!(copy_limited(&url, body.as_slice(), &mut sink, 0)?, 10);
+        assert_eq!(sink, body);
+        let err = read_limited(&url, body.as_slice(), 9).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("larger than the max file size 9 bytes"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_auth_header() -> Result<()> {
+        let gh_token = Some("ghp_xxx".to_string());
+        let header = Some(("X-Api-Key".to_string(), "secret".to_string()));
+        assert_eq!(
+            config_auth_header(
+                &Url::parse("https://api.github.com/repos/o/n/contents/gh-trs-config.yml")?,
+                &gh_token,
+                &header
+            ),
+            Some(("Authorization".to_string(), "token ghp_xxx".to_string()))
+        );
+        assert_eq!(
+            config_auth_header(
+                &Url::parse("https://raw.githubusercontent.com/o/n/main/gh-trs-config.yml")?,
+                &gh_token,
+                &header
+            ),
+            None
+        );
+        assert_eq!(
+            config_auth_header(
+                &Url::parse("https://configs.example.com/gh-trs-config.yml")?,
+                &gh_token,
+                &header
+            ),
+            header
+        );
+        for url in [
+            "https://suecharo.github.io/gh-trs/tools",
+            "https://Suecharo.GitHub.io/service-info",
+            "https://github.com/o/n/blob/main/gh-trs-config.yml",
+            "https://objects.githubusercontent.com/o/n",
+        ] {
+            assert_eq!(
+                config_auth_header(&Url::parse(url)?, &gh_token, &header),
+                None
+            );
+        }
+        // Not a GitHub host, even though it contains `github.io`.
+        assert_eq!(
+            config_auth_header(
+                &Url::parse("https://github.io.example.com/trs/tools")?,
+                &gh_token,
+                &header
+            ),
+            header
+        );
+        assert_eq!(
+            config_auth_header(
+                &Url::parse("https://configs.example.com/gh-trs-config.yml")?,
+                &gh_token,
+                &None
+            ),
+            None
+        );
+        Ok(())
+    }
+
+    /// Serve `count` requests on a local port, responding with `respond(accept header)` (status and body).
+    fn serve_strict(count: usize, respond: fn(Option<&str>) -> (u16, &'static str)) -> Result<Url> {
+        use std::io::{BufRead, BufReader, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = Url::parse(&format!("http://{}/wf.cwl", listener.local_addr()?))?;
+        thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut accept = None;
+                for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("accept") {
+                            accept = Some(value.trim().to_string());
+                        }
+                    }
+                }
+                let (status, body) = respond(accept.as_deref());
+                write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Type: application/x-yaml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        Ok(url)
+    }
+
+    #[test]
+    fn test_fetch_raw_content_strict_server() -> Result<()> {
+        // Serves only `application/x-yaml`, so a non-standard or text-only Accept is refused.
+        let url = serve_strict(1, |accept| match accept {
+            Some(accept) if accept.contains("*/*") || accept.contains("application/x-yaml") => {
+                (200, "cwlVersion: v1.0\n")
+            }
+            _ => (406, ""),
+        })?;
+        let http = Http::new(&HttpArgs::default(), None, false)?;
+        assert_eq!(fetch_raw_content(&http, &url)?, "cwlVersion: v1.0\n");
+
+        // Refuses any explicit Accept other than the default of the client.
+        let url = serve_strict(2, |accept| match accept {
+            Some("*/*") => (200, "cwlVersion: v1.0\n"),
+            _ => (406, ""),
+        })?;
+        let start_request_count = http.request_count();
+        assert_eq!(fetch_raw_content(&http, &url)?, "cwlVersion: v1.0\n");
+        // The re-fetch after 406 is counted as another request.
+        assert_eq!(http.request_count() - start_request_count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_raw_github_url() -> Result<()> {
+        let url = Url::parse(
+            "https://raw.githubusercontent.com/suecharo/gh-trs/458d0524e667f2442a5effb730b523c1f15748d4/tests/CWL/wf/trimming_and_qc.cwl",
+        )?;
+        assert_eq!(
+            parse_raw_github_url(&url),
+            Some((
+                "suecharo".to_string(),
+                "gh-trs".to_string(),
+                "458d0524e667f2442a5effb730b523c1f15748d4".to_string(),
+                "tests/CWL/wf/trimming_and_qc.cwl".to_string()
+            ))
+        );
+        assert!(parse_raw_github_url(&Url::parse(
+            "https://raw.githubusercontent.com/suecharo/gh-trs/main/"
+        )?)
+        .is_none());
+        assert!(
+            parse_raw_github_url(&Url::parse("https://example.com/a/b/main/wf.cwl")?).is_none()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_fetch_raw_content() -> Result<()> {
         let remote_loc =
             Url::parse("https://raw.githubusercontent.com/suecharo/gh-trs/main/README.md")?;
-        let content = fetch_raw_content(&remote_loc)?;
+        let http = Http::new(&HttpArgs::default(), None, false)?;
+        let content = fetch_raw_content(&http, &remote_loc)?;
         assert!(content.contains("gh-trs"));
         Ok(())
     }
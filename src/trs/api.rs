@@ -1,16 +1,38 @@
+use crate::remote;
 use crate::trs;
 
 use anyhow::{anyhow, ensure, Result};
 use reqwest;
+use std::path::{Component, Path};
 use url::Url;
 
-pub fn get_request(url: &Url) -> Result<String> {
-    let client = reqwest::blocking::Client::new();
-    let response = client
+/// A TRS endpoint hosted outside GitHub is requested with `--config-auth-header`, e.g., a private TRS of `--from-trs`.
+pub fn get_request(http: &remote::Http, url: &Url) -> Result<String> {
+    get_request_if_found(http, url)?.ok_or_else(|| {
+        anyhow!(
+            "Failed to get request to {} with status: {}",
+            url,
+            reqwest::StatusCode::NOT_FOUND
+        )
+    })
+}
+
+/// Same as `get_request`, but None if the URL is not found (404), e.g., a tool not published yet.
+/// The other failures (not reachable, 5xx, etc.) are errors.
+pub fn get_request_if_found(http: &remote::Http, url: &Url) -> Result<Option<String>> {
+    let _permit = http.request_permit();
+    let client = http.client()?;
+    let request = client
         .get(url.as_str())
-        .header(reqwest::header::ACCEPT, "application/json")
-        .send()?;
+        .header(reqwest::header::ACCEPT, "application/json");
+    let response = http.send(match http.auth_header(url) {
+        Some((name, value)) => request.header(name.as_str(), value.as_str()),
+        None => request,
+    })?;
     let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
     ensure!(
         status.is_success(),
         "Failed to get request to {} with status: {}",
@@ -18,7 +40,42 @@ pub fn get_request(url: &Url) -> Result<String> {
         status
     );
     let body = response.text()?;
-    Ok(body)
+    Ok(Some(body))
+}
+
+/// The TRS root URL (with a trailing slash) served by GitHub Pages of the repo.
+///
+/// - Project pages: `https://<owner>.github.io/<name>/`
+/// - User or organization pages (the repo named `<owner>.github.io`): `https://<owner>.github.io/`
+///
+/// `dest` is the subdirectory of the branch where the TRS response is published, if any.
+pub fn gh_pages_trs_url(
+    owner: impl AsRef<str>,
+    name: impl AsRef<str>,
+    dest: Option<&Path>,
+) -> Result<Url> {
+    let owner = owner.as_ref();
+    let name = name.as_ref();
+    let mut url = if name.eq_ignore_ascii_case(&format!("{}.github.io", owner)) {
+        Url::parse(&format!("https://{}.github.io/", owner))?
+    } else {
+        Url::parse(&format!("https://{}.github.io/{}/", owner, name))?
+    };
+    if let Some(dest) = dest {
+        for component in dest.components() {
+            match component {
+                Component::Normal(segment) => {
+                    let segment = segment
+                        .to_str()
+                        .ok_or_else(|| anyhow!("Invalid dest: {}", dest.display()))?;
+                    url = url.join(&format!("{}/", segment))?;
+                }
+                Component::CurDir | Component::RootDir => {}
+                _ => return Err(anyhow!("Invalid dest: {}", dest.display())),
+            }
+        }
+    }
+    Ok(url)
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -33,11 +90,7 @@ impl TrsEndpoint {
     }
 
     pub fn new_gh_pages(owner: impl AsRef<str>, name: impl AsRef<str>) -> Result<Self> {
-        let url = Url::parse(&format!(
-            "https://{}.github.io/{}/",
-            owner.as_ref(),
-            name.as_ref()
-        ))?;
+        let url = gh_pages_trs_url(owner, name, None)?;
         Ok(TrsEndpoint { url })
     }
 
@@ -57,22 +110,54 @@ impl TrsEndpoint {
         path_segments.pop();
         path_segments.pop();
         path_segments.pop();
+        // The TRS root of user or organization pages (`<owner>.github.io` repo) has no path.
         let trs_url = Url::parse(&format!(
-            "{}://{}/{}/",
+            "{}://{}/{}",
             scheme,
             host,
-            path_segments.join("/")
+            path_segments
+                .iter()
+                .map(|s| format!("{}/", s))
+                .collect::<String>()
         ))?;
         Ok(TrsEndpoint { url: trs_url })
     }
 
-    pub fn is_valid(&self) -> Result<()> {
-        let service_info = get_service_info(self)?;
-        ensure!(
-            service_info.r#type.artifact == "gh-trs" && service_info.r#type.version == "2.0.1",
-            "gh-trs only supports gh-trs 2.0.1 as a TRS endpoint"
-        );
-        Ok(())
+    /// Check that the endpoint serves a gh-trs 2.0.1 service-info.
+    /// The error tells whether it is not reachable, not JSON, not a TRS service-info, or the wrong artifact/version.
+    pub fn is_valid(&self, http: &remote::Http) -> Result<()> {
+        let url = Url::parse(&format!(
+            "{}/service-info",
+            self.url.as_str().trim().trim_matches('/')
+        ))?;
+        let body = get_request(http, &url).map_err(|e| {
+            anyhow!(
+                "TRS endpoint {} is not reachable. Please check the URL: {}",
+                self.url,
+                e
+            )
+        })?;
+        check_service_info(&url, &body)
+    }
+
+    /// False if the endpoint has no service-info (404), i.e., nothing is published to it yet.
+    /// Unlike `is_valid`, an endpoint that is not reachable is an error, not "not published".
+    pub fn is_published(&self, http: &remote::Http) -> Result<bool> {
+        let url = Url::parse(&format!(
+            "{}/service-info",
+            self.url.as_str().trim().trim_matches('/')
+        ))?;
+        let body = get_request_if_found(http, &url).map_err(|e| {
+            anyhow!(
+                "TRS endpoint {} is not reachable. Please check the URL: {}",
+                self.url,
+                e
+            )
+        })?;
+        match body {
+            Some(body) => check_service_info(&url, &body).map(|_| true),
+            None => Ok(false),
+        }
     }
 
     pub fn to_config_url(
@@ -89,72 +174,157 @@ impl TrsEndpoint {
         Ok(url)
     }
 
-    pub fn all_versions(&self, wf_id: impl AsRef<str>) -> Result<Vec<String>> {
-        let tool = get_tool(self, wf_id.as_ref())?;
-        let versions: Vec<String> = tool
-            .versions
-            .into_iter()
-            .map(|v| {
-                v.url
-                    .path_segments()
-                    .ok_or_else(|| anyhow!("Invalid url: {}", v.url))
-                    .and_then(|segments| {
-                        segments
-                            .last()
-                            .ok_or_else(|| anyhow!("Invalid url: {}", v.url))
-                    })
-                    .map(|s| s.to_string())
-            })
-            .collect::<Result<Vec<_>>>()?;
-        Ok(versions)
+    pub fn all_versions(&self, http: &remote::Http, wf_id: impl AsRef<str>) -> Result<Vec<String>> {
+        tool_versions(&get_tool(http, self, wf_id.as_ref())?)
+    }
+
+    /// None if the tool is not published in the TRS (404).
+    pub fn find_versions(
+        &self,
+        http: &remote::Http,
+        wf_id: impl AsRef<str>,
+    ) -> Result<Option<Vec<String>>> {
+        find_tool(http, self, wf_id.as_ref())?
+            .map(|tool| tool_versions(&tool))
+            .transpose()
     }
 }
 
+/// The versions (the last path segment of the version URLs) of the tool.
+fn tool_versions(tool: &trs::types::Tool) -> Result<Vec<String>> {
+    let versions: Vec<String> = tool
+        .versions
+        .iter()
+        .map(|v| {
+            v.url
+                .path_segments()
+                .ok_or_else(|| anyhow!("Invalid url: {}", v.url))
+                .and_then(|mut segments| {
+                    segments
+                        .next_back()
+                        .ok_or_else(|| anyhow!("Invalid url: {}", v.url))
+                })
+                .map(|s| s.to_string())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(versions)
+}
+
+fn check_service_info(url: &Url, body: impl AsRef<str>) -> Result<()> {
+    let value: serde_json::Value = serde_json::from_str(body.as_ref()).map_err(|e| {
+        anyhow!(
+            "{} is not JSON. Please point at the root of a gh-trs TRS endpoint: {}",
+            url,
+            e
+        )
+    })?;
+    let service_info: trs::types::ServiceInfo = serde_json::from_value(value)
+        .map_err(|e| anyhow!("{} is not a TRS service-info: {}", url, e))?;
+    ensure!(
+        service_info.r#type.artifact == trs::types::TRS_ARTIFACT
+            && service_info.r#type.version == trs::types::TRS_ARTIFACT_VERSION,
+        "gh-trs only supports {} {} as a TRS endpoint, but {} serves artifact: {}, version: {}",
+        trs::types::TRS_ARTIFACT,
+        trs::types::TRS_ARTIFACT_VERSION,
+        url,
+        service_info.r#type.artifact,
+        service_info.r#type.version
+    );
+    Ok(())
+}
+
 /// /service-info -> trs::types::ServiceInfo
-pub fn get_service_info(trs_endpoint: &TrsEndpoint) -> Result<trs::types::ServiceInfo> {
+pub fn get_service_info(
+    http: &remote::Http,
+    trs_endpoint: &TrsEndpoint,
+) -> Result<trs::types::ServiceInfo> {
     let url = Url::parse(&format!(
         "{}/service-info",
         trs_endpoint.url.as_str().trim().trim_matches('/')
     ))?;
-    let body = get_request(&url)?;
+    let body = get_request(http, &url)?;
     let service_info: trs::types::ServiceInfo = serde_json::from_str(&body)?;
     Ok(service_info)
 }
 
 /// /toolClasses -> trs::types::ToolClass[]
-pub fn get_tool_classes(trs_endpoint: &TrsEndpoint) -> Result<Vec<trs::types::ToolClass>> {
+pub fn get_tool_classes(
+    http: &remote::Http,
+    trs_endpoint: &TrsEndpoint,
+) -> Result<Vec<trs::types::ToolClass>> {
     let url = Url::parse(&format!(
         "{}/toolClasses",
         trs_endpoint.url.as_str().trim().trim_matches('/')
     ))?;
-    let body = get_request(&url)?;
+    let body = get_request(http, &url)?;
     let tool_classes: Vec<trs::types::ToolClass> = serde_json::from_str(&body)?;
     Ok(tool_classes)
 }
 
 /// /tools -> trs::types::Tool[]
-pub fn get_tools(trs_endpoint: &TrsEndpoint) -> Result<Vec<trs::types::Tool>> {
+pub fn get_tools(http: &remote::Http, trs_endpoint: &TrsEndpoint) -> Result<Vec<trs::types::Tool>> {
     let url = Url::parse(&format!(
         "{}/tools",
         trs_endpoint.url.as_str().trim().trim_matches('/')
     ))?;
-    let body = get_request(&url)?;
+    let body = get_request(http, &url)?;
     let tools: Vec<trs::types::Tool> = serde_json::from_str(&body)?;
     Ok(tools)
 }
 
 /// /tools/<wf_id> -> trs::types::Tool
-pub fn get_tool(trs_endpoint: &TrsEndpoint, wf_id: impl AsRef<str>) -> Result<trs::types::Tool> {
+pub fn get_tool(
+    http: &remote::Http,
+    trs_endpoint: &TrsEndpoint,
+    wf_id: impl AsRef<str>,
+) -> Result<trs::types::Tool> {
     let url = Url::parse(&format!(
         "{}/tools/{}",
         trs_endpoint.url.as_str().trim().trim_matches('/'),
         wf_id.as_ref()
     ))?;
-    let body = get_request(&url)?;
+    let body = get_request(http, &url)?;
     let tool: trs::types::Tool = serde_json::from_str(&body)?;
     Ok(tool)
 }
 
+/// /tools/<wf_id> -> trs::types::Tool, or None if the tool is not found (404)
+pub fn find_tool(
+    http: &remote::Http,
+    trs_endpoint: &TrsEndpoint,
+    wf_id: impl AsRef<str>,
+) -> Result<Option<trs::types::Tool>> {
+    let url = Url::parse(&format!(
+        "{}/tools/{}",
+        trs_endpoint.url.as_str().trim().trim_matches('/'),
+        wf_id.as_ref()
+    ))?;
+    match get_request_if_found(http, &url)? {
+        Some(body) => Ok(Some(serde_json::from_str(&body)?)),
+        None => Ok(None),
+    }
+}
+
+/// /tools/<wf_id>/versions/<wf_version>/<descriptor_type>/files -> trs::types::ToolFile[]
+pub fn get_tool_files(
+    http: &remote::Http,
+    trs_endpoint: &TrsEndpoint,
+    wf_id: impl AsRef<str>,
+    wf_version: impl AsRef<str>,
+    descriptor_type: impl AsRef<str>,
+) -> Result<Vec<trs::types::ToolFile>> {
+    let url = Url::parse(&format!(
+        "{}/tools/{}/versions/{}/{}/files",
+        trs_endpoint.url.as_str().trim().trim_matches('/'),
+        wf_id.as_ref(),
+        wf_version.as_ref(),
+        descriptor_type.as_ref()
+    ))?;
+    let body = get_request(http, &url)?;
+    let files: Vec<trs::types::ToolFile> = serde_json::from_str(&body)?;
+    Ok(files)
+}
+
 #[cfg(test)]
 #[cfg(not(tarpaulin_include))]
 mod tests {
@@ -163,17 +333,66 @@ mod tests {
     #[test]
     #[ignore]
     fn test_get_request() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let url = Url::parse("https://suecharo.github.io/gh-pages-rest-api-hosting/foo")?;
-        get_request(&url)?;
+        get_request(&http, &url)?;
         Ok(())
     }
 
     #[test]
     fn test_get_request_not_found() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let url = Url::parse("https://suecharo.github.io/gh-trs/invalid_path")?;
-        let res = get_request(&url);
+        let res = get_request(&http, &url);
         assert!(res.is_err());
         assert!(res.unwrap_err().to_string().contains("404"));
+        assert_eq!(get_request_if_found(&http, &url)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gh_pages_trs_url() -> Result<()> {
+        assert_eq!(
+            gh_pages_trs_url("suecharo", "gh-trs", None)?.as_str(),
+            "https://suecharo.github.io/gh-trs/"
+        );
+        assert_eq!(
+            gh_pages_trs_url("suecharo", "suecharo.github.io", None)?.as_str(),
+            "https://suecharo.github.io/"
+        );
+        assert_eq!(
+            gh_pages_trs_url("Suecharo", "suecharo.github.io", None)?.as_str(),
+            "https://suecharo.github.io/"
+        );
+        assert_eq!(
+            gh_pages_trs_url("suecharo", "gh-trs", Some(Path::new("trs/v2")))?.as_str(),
+            "https://suecharo.github.io/gh-trs/trs/v2/"
+        );
+        assert_eq!(
+            gh_pages_trs_url("suecharo", "suecharo.github.io", Some(Path::new("./trs/")))?.as_str(),
+            "https://suecharo.github.io/trs/"
+        );
+        assert!(gh_pages_trs_url("suecharo", "gh-trs", Some(Path::new("../trs"))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_service_info() -> Result<()> {
+        let url = Url::parse("https://example.com/service-info")?;
+        let service_info = trs::types::ServiceInfo::new("test_owner", "test_name")?;
+        let body = serde_json::to_string(&service_info)?;
+        assert!(check_service_info(&url, &body).is_ok());
+
+        let err = check_service_info(&url, "<html></html>").unwrap_err();
+        assert!(err.to_string().contains("is not JSON"));
+
+        let err = check_service_info(&url, r#"{"foo": "bar"}"#).unwrap_err();
+        assert!(err.to_string().contains("is not a TRS service-info"));
+
+        let mut other = service_info;
+        other.r#type.artifact = "other-trs".to_string();
+        let err = check_service_info(&url, serde_json::to_string(&other)?).unwrap_err();
+        assert!(err.to_string().contains("artifact: other-trs"));
         Ok(())
     }
 
@@ -185,6 +404,17 @@ mod tests {
             trs_endpoint.url,
             Url::parse("https://suecharo.github.io/gh-trs/")?
         );
+
+        let url = Url::parse("https://suecharo.github.io/tools/foo/versions/bar")?;
+        let trs_endpoint = TrsEndpoint::new_from_tool_version_url(&url)?;
+        assert_eq!(trs_endpoint.url, Url::parse("https://suecharo.github.io/")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_gh_pages_user_pages() -> Result<()> {
+        let trs_endpoint = TrsEndpoint::new_gh_pages("suecharo", "suecharo.github.io")?;
+        assert_eq!(trs_endpoint.url, Url::parse("https://suecharo.github.io/")?);
         Ok(())
     }
 }
@@ -3,36 +3,63 @@ use crate::remote;
 use crate::trs;
 
 use anyhow::Result;
+use colored::Colorize;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use uuid::Uuid;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use url::Url;
+
+/// Manifest of the generated paths, emitted only with `publish --manifest`.
+pub const MANIFEST_FILE: &str = "gh-trs-manifest.json";
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub size: usize,
+    pub checksum: trs::types::Checksum,
+}
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TrsResponse {
-    pub gh_trs_config: HashMap<(Uuid, String), config::types::Config>,
+    pub gh_trs_config: HashMap<(config::types::WorkflowId, String), config::types::Config>,
     pub service_info: trs::types::ServiceInfo,
     pub tool_classes: Vec<trs::types::ToolClass>,
     pub tools: Vec<trs::types::Tool>,
-    pub tools_descriptor: HashMap<(Uuid, String), trs::types::FileWrapper>,
-    pub tools_files: HashMap<(Uuid, String), Vec<trs::types::ToolFile>>,
-    pub tools_tests: HashMap<(Uuid, String), Vec<trs::types::FileWrapper>>,
+    pub tools_descriptor: HashMap<(config::types::WorkflowId, String), trs::types::FileWrapper>,
+    pub tools_files: HashMap<(config::types::WorkflowId, String), Vec<trs::types::ToolFile>>,
+    pub tools_tests: HashMap<(config::types::WorkflowId, String), Vec<trs::types::FileWrapper>>,
 }
 
 impl TrsResponse {
-    pub fn new(owner: impl AsRef<str>, name: impl AsRef<str>) -> Result<Self> {
-        let trs_endpoint = trs::api::TrsEndpoint::new_gh_pages(&owner, &name)?;
-        let service_info = trs::types::ServiceInfo::new_or_update(
-            trs::api::get_service_info(&trs_endpoint).ok(),
-            &owner,
-            &name,
-        )?;
-        let tool_classes = generate_tool_classes(&trs_endpoint)?;
-        let tools = match trs::api::get_tools(&trs_endpoint) {
-            Ok(tools) => tools,
-            Err(_) => vec![],
-        };
+    /// `trs_url` is the TRS root URL where the response is published,
+    /// usually `trs::api::gh_pages_trs_url`, or a custom domain.
+    pub fn new(
+        http: &remote::Http,
+        owner: impl AsRef<str>,
+        name: impl AsRef<str>,
+        trs_url: &Url,
+    ) -> Result<Self> {
+        let trs_endpoint = trs::api::TrsEndpoint::new_from_url(trs_url)?;
+        let prev_service_info = trs::api::get_service_info(http, &trs_endpoint).ok();
+        if let Some(prev) = &prev_service_info {
+            if prev.gh_trs_version.as_deref() != Some(trs::types::GH_TRS_VERSION) {
+                info!(
+                    "The TRS response at {} was generated by gh-trs {}, and is updated by gh-trs {}",
+                    trs_url,
+                    prev.gh_trs_version.as_deref().unwrap_or("(unknown)"),
+                    trs::types::GH_TRS_VERSION
+                );
+            }
+        }
+        let service_info =
+            trs::types::ServiceInfo::new_or_update(prev_service_info, &owner, &name)?;
+        let tool_classes = generate_tool_classes(http, &trs_endpoint)?;
+        let tools = trs::api::get_tools(http, &trs_endpoint).unwrap_or_default();
 
         Ok(Self {
             gh_trs_config: HashMap::new(),
@@ -47,41 +74,52 @@ impl TrsResponse {
 
     pub fn add(
         &mut self,
-        owner: impl AsRef<str>,
-        name: impl AsRef<str>,
+        http: &remote::Http,
+        trs_url: &Url,
         config: &config::types::Config,
         verified: bool,
+        max_inline_size: Option<usize>,
     ) -> Result<()> {
         match self.tools.iter_mut().find(|t| t.id == config.id) {
             Some(tool) => {
                 // update tool
-                tool.add_new_tool_version(config, &owner, &name, verified)?;
+                tool.add_new_tool_version(config, trs_url, verified)?;
             }
             None => {
                 // create tool and add
-                let mut tool = trs::types::Tool::new(config, &owner, &name)?;
-                tool.add_new_tool_version(config, &owner, &name, verified)?;
+                let mut tool = trs::types::Tool::new(config, trs_url)?;
+                tool.add_new_tool_version(config, trs_url, verified)?;
                 self.tools.push(tool);
             }
         };
 
         self.tools_descriptor.insert(
-            (config.id, config.version.clone()),
-            generate_descriptor(config)?,
+            (config.id.clone(), config.version.clone()),
+            generate_descriptor(http, config, max_inline_size)?,
+        );
+        self.tools_files.insert(
+            (config.id.clone(), config.version.clone()),
+            generate_files(http, config)?,
+        );
+        self.tools_tests.insert(
+            (config.id.clone(), config.version.clone()),
+            generate_tests(config)?,
         );
-        self.tools_files
-            .insert((config.id, config.version.clone()), generate_files(config)?);
-        self.tools_tests
-            .insert((config.id, config.version.clone()), generate_tests(config)?);
 
         self.gh_trs_config
-            .insert((config.id, config.version.clone()), config.clone());
+            .insert((config.id.clone(), config.version.clone()), config.clone());
 
         Ok(())
     }
 
-    pub fn generate_contents(&self) -> Result<HashMap<PathBuf, String>> {
+    /// If `with_html` is true, `index.html` listing the tools for humans is also generated.
+    pub fn generate_contents(&self, with_html: bool) -> Result<HashMap<PathBuf, String>> {
         let mut map: HashMap<PathBuf, String> = HashMap::new();
+        // Disable Jekyll on GitHub Pages so that all files are served verbatim.
+        map.insert(PathBuf::from(".nojekyll"), "".to_string());
+        if with_html {
+            map.insert(PathBuf::from("index.html"), self.generate_html());
+        }
         map.insert(
             PathBuf::from("service-info/index.json"),
             serde_json::to_string(&self.service_info)?,
@@ -101,9 +139,18 @@ impl TrsResponse {
                 .iter()
                 .find(|v| &v.version() == version)
                 .unwrap();
-            let tools_descriptor = self.tools_descriptor.get(&(*id, version.clone())).unwrap();
-            let tools_files = self.tools_files.get(&(*id, version.clone())).unwrap();
-            let tools_tests = self.tools_tests.get(&(*id, version.clone())).unwrap();
+            let tools_descriptor = self
+                .tools_descriptor
+                .get(&(id.clone(), version.clone()))
+                .unwrap();
+            let tools_files = self
+                .tools_files
+                .get(&(id.clone(), version.clone()))
+                .unwrap();
+            let tools_tests = self
+                .tools_tests
+                .get(&(id.clone(), version.clone()))
+                .unwrap();
 
             let desc_type = config.workflow.language.r#type.clone().unwrap().to_string();
 
@@ -157,12 +204,88 @@ impl TrsResponse {
         }
         Ok(map)
     }
+
+    /// Human-friendly `index.html` listing the tools and their versions with links to the JSON endpoints.
+    pub fn generate_html(&self) -> String {
+        let mut tools = self.tools.iter().collect::<Vec<_>>();
+        tools.sort_by_key(|t| t.name.clone().unwrap_or_default());
+        let tool_items = tools
+            .iter()
+            .map(|tool| {
+                let version_items = tool
+                    .versions
+                    .iter()
+                    .map(|v| {
+                        format!(
+                            "          <li><a href=\"tools/{}/versions/{}/index.json\">{}</a>{}</li>\n",
+                            tool.id,
+                            escape_html(v.version()),
+                            escape_html(v.version()),
+                            if v.verified == Some(true) {
+                                " (verified)"
+                            } else {
+                                ""
+                            }
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    "      <li>\n        <a href=\"tools/{}/index.json\">{}</a> ({})\n        <ul>\n{}        </ul>\n      </li>\n",
+                    tool.id,
+                    escape_html(tool.name.clone().unwrap_or_default()),
+                    tool.id,
+                    version_items
+                )
+            })
+            .collect::<String>();
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>{name}</title>
+  </head>
+  <body>
+    <h1>{name}</h1>
+    <p><a href="service-info/index.json">service-info</a> | <a href="toolClasses/index.json">toolClasses</a> | <a href="tools/index.json">tools</a></p>
+    <ul>
+{tool_items}    </ul>
+  </body>
+</html>
+"#,
+            name = escape_html(&self.service_info.name),
+            tool_items = tool_items
+        )
+    }
+}
+
+fn escape_html(s: impl AsRef<str>) -> String {
+    s.as_ref()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// List every generated path with its byte size and checksum, sorted by path.
+pub fn generate_manifest(contents: &HashMap<PathBuf, String>) -> Result<String> {
+    let mut entries = contents
+        .iter()
+        .map(|(path, content)| ManifestEntry {
+            path: path.clone(),
+            size: content.len(),
+            checksum: trs::types::Checksum::new_from_string(content),
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(serde_json::to_string(&entries)?)
 }
 
 pub fn generate_tool_classes(
+    http: &remote::Http,
     trs_endpoint: &trs::api::TrsEndpoint,
 ) -> Result<Vec<trs::types::ToolClass>> {
-    match trs::api::get_tool_classes(trs_endpoint) {
+    match trs::api::get_tool_classes(http, trs_endpoint) {
         Ok(mut tool_classes) => {
             let has_workflow = tool_classes
                 .iter()
@@ -176,12 +299,30 @@ pub fn generate_tool_classes(
     }
 }
 
-pub fn generate_descriptor(config: &config::types::Config) -> Result<trs::types::FileWrapper> {
+/// If `max_inline_size` is set and the primary workflow is larger than it (in bytes),
+/// the content is omitted and only the url and checksum are emitted.
+/// The same applies to a binary primary workflow; the checksum is always computed over the raw bytes.
+pub fn generate_descriptor(
+    http: &remote::Http,
+    config: &config::types::Config,
+    max_inline_size: Option<usize>,
+) -> Result<trs::types::FileWrapper> {
     let primary_wf = config.workflow.primary_wf()?;
-    let (content, checksum) = match remote::fetch_raw_content(&primary_wf.url) {
-        Ok(content) => {
-            let checksum = trs::types::Checksum::new_from_string(content.clone());
-            (Some(content), Some(vec![checksum]))
+    let (content, checksum) = match remote::fetch_raw_bytes(http, &primary_wf.url) {
+        Ok(bytes) => {
+            let checksum = trs::types::Checksum::new_from_bytes(&bytes);
+            let content = match remote::decode_text(bytes) {
+                Some(content) => inline_content(content, max_inline_size),
+                None => {
+                    warn!(
+                        "{}: The primary workflow {} is a binary file, so its content is not inlined",
+                        "Warning".yellow(),
+                        primary_wf.url
+                    );
+                    None
+                }
+            };
+            (content, Some(vec![checksum]))
         }
         Err(_) => (None, None),
     };
@@ -192,25 +333,76 @@ pub fn generate_descriptor(config: &config::types::Config) -> Result<trs::types:
     })
 }
 
-pub fn generate_files(config: &config::types::Config) -> Result<Vec<trs::types::ToolFile>> {
-    Ok(config
-        .workflow
-        .files
-        .iter()
-        .map(|f| {
-            let checksum = match trs::types::Checksum::new_from_url(&f.url) {
+fn inline_content(content: String, max_inline_size: Option<usize>) -> Option<String> {
+    match max_inline_size {
+        Some(max_inline_size) if content.len() > max_inline_size => None,
+        _ => Some(content),
+    }
+}
+
+/// The checksums of the files are computed concurrently by up to `--concurrency` threads,
+/// keeping the order of `workflow.files`.
+pub fn generate_files(
+    http: &remote::Http,
+    config: &config::types::Config,
+) -> Result<Vec<trs::types::ToolFile>> {
+    let files = &config.workflow.files;
+    let checksums =
+        map_concurrently(
+            files,
+            http.concurrency(),
+            |f| match trs::types::Checksum::new_from_url(http, &f.url) {
                 Ok(checksum) => Some(checksum),
-                Err(_) => None,
-            };
-            trs::types::ToolFile {
-                path: Some(f.url.clone()),
-                file_type: Some(trs::types::FileType::new_from_file_type(&f.r#type)),
-                checksum,
-            }
+                Err(e) => {
+                    warn!(
+                        "{}: The checksum of {} is omitted: {}",
+                        "Warning".yellow(),
+                        f.url,
+                        e
+                    );
+                    None
+                }
+            },
+        );
+    Ok(files
+        .iter()
+        .zip(checksums)
+        .map(|(f, checksum)| trs::types::ToolFile {
+            path: Some(f.url.clone()),
+            file_type: Some(trs::types::FileType::new_from_file_type(&f.r#type)),
+            checksum,
         })
         .collect())
 }
 
+/// Apply `f` to the items by up to `workers` threads, returning the results in the order of the items.
+pub fn map_concurrently<T: Sync, R: Send>(
+    items: &[T],
+    workers: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= items.len() {
+                    break;
+                }
+                let result = f(&items[i]);
+                results.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
 pub fn generate_tests(config: &config::types::Config) -> Result<Vec<trs::types::FileWrapper>> {
     config
         .workflow
@@ -235,14 +427,117 @@ mod tests {
 
     #[test]
     fn test_trs_response_new() -> Result<()> {
-        TrsResponse::new("test_owner", "test_name")?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let trs_url = trs::api::gh_pages_trs_url("test_owner", "test_name", None)?;
+        TrsResponse::new(&http, "test_owner", "test_name", &trs_url)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_concurrently() {
+        let items = (0..20).collect::<Vec<u64>>();
+        let results = map_concurrently(&items, 4, |i| {
+            // Finish in the reverse order of the start.
+            std::thread::sleep(std::time::Duration::from_millis(20 - i));
+            i * 2
+        });
+        assert_eq!(results, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+        assert!(map_concurrently(&Vec::<u64>::new(), 4, |i| *i).is_empty());
+    }
+
+    #[test]
+    fn test_generate_manifest() -> Result<()> {
+        let mut contents = HashMap::new();
+        contents.insert(PathBuf::from("tools/index.json"), "[]".to_string());
+        contents.insert(PathBuf::from("service-info/index.json"), "{}".to_string());
+        let manifest = serde_json::from_str::<Vec<ManifestEntry>>(&generate_manifest(&contents)?)?;
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(manifest[0].path, PathBuf::from("service-info/index.json"));
+        assert_eq!(manifest[0].size, 2);
+        assert_eq!(
+            manifest[0].checksum,
+            trs::types::Checksum::new_from_string("{}")
+        );
+        assert_eq!(manifest[1].path, PathBuf::from("tools/index.json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_html() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = trs::api::gh_pages_trs_url("test_owner", "test_name", None)?;
+        let mut tool = trs::types::Tool::new(&config, &trs_url)?;
+        tool.add_new_tool_version(&config, &trs_url, true)?;
+        let trs_response = TrsResponse {
+            gh_trs_config: HashMap::new(),
+            service_info: trs::types::ServiceInfo::new("test_owner", "test_name")?,
+            tool_classes: vec![],
+            tools: vec![tool],
+            tools_descriptor: HashMap::new(),
+            tools_files: HashMap::new(),
+            tools_tests: HashMap::new(),
+        };
+        let html = trs_response.generate_html();
+        assert!(html.contains(&format!("tools/{}/index.json", config.id)));
+        assert!(html.contains(&format!(
+            "tools/{}/versions/{}/index.json",
+            config.id, config.version
+        )));
+        assert!(html.contains("(verified)"));
         Ok(())
     }
 
+    #[test]
+    fn test_generate_contents() -> Result<()> {
+        let trs_response = TrsResponse {
+            gh_trs_config: HashMap::new(),
+            service_info: trs::types::ServiceInfo::new("test_owner", "test_name")?,
+            tool_classes: vec![],
+            tools: vec![],
+            tools_descriptor: HashMap::new(),
+            tools_files: HashMap::new(),
+            tools_tests: HashMap::new(),
+        };
+        let contents = trs_response.generate_contents(false)?;
+        assert_eq!(
+            contents.get(&PathBuf::from(".nojekyll")),
+            Some(&"".to_string())
+        );
+        assert!(contents.contains_key(&PathBuf::from("service-info/index.json")));
+        assert!(!contents.contains_key(&PathBuf::from("index.html")));
+        let contents = trs_response.generate_contents(true)?;
+        assert!(contents.contains_key(&PathBuf::from("index.html")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_content() {
+        assert_eq!(
+            inline_content("abc".to_string(), None),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            inline_content("abc".to_string(), Some(3)),
+            Some("abc".to_string())
+        );
+        assert_eq!(inline_content("abcd".to_string(), Some(3)), None);
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">&</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
     #[test]
     fn test_generate_tool_classes() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
         let trs_endpoint = trs::api::TrsEndpoint::new_gh_pages("test_owner", "test_name")?;
-        let tool_classes = generate_tool_classes(&trs_endpoint)?;
+        let tool_classes = generate_tool_classes(&http, &trs_endpoint)?;
         let expect = serde_json::from_str::<Vec<trs::types::ToolClass>>(
             r#"
 [
@@ -259,15 +554,19 @@ mod tests {
 
     #[test]
     fn test_generate_descriptor() -> Result<()> {
-        let config = config::io::read_config("./tests/test_config_CWL_validated.yml")?;
-        generate_descriptor(&config)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        generate_descriptor(&http, &config, None)?;
         Ok(())
     }
 
     #[test]
     fn test_generate_files() -> Result<()> {
-        let config = config::io::read_config("./tests/test_config_CWL_validated.yml")?;
-        let files = generate_files(&config)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let files = generate_files(&http, &config)?;
         let expect = serde_json::from_str::<Vec<trs::types::ToolFile>>(
             r#"
 [
@@ -303,7 +602,9 @@ mod tests {
 
     #[test]
     fn test_generate_tests() -> Result<()> {
-        let config = config::io::read_config("./tests/test_config_CWL_validated.yml")?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
         let tests = generate_tests(&config)?;
         let expect = serde_json::from_str::<Vec<trs::types::FileWrapper>>(
             r#"
@@ -1,13 +1,21 @@
 use crate::config;
 use crate::env;
+use crate::remote;
 
-use anyhow::{anyhow, ensure, Result};
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use sha2::{Digest, Sha256};
+use std::io::{self, Read};
 use url::Url;
-use uuid::Uuid;
+
+/// Artifact name and version of the service-info generated by gh-trs.
+/// `TrsEndpoint::is_valid` accepts only the endpoints matching these.
+pub const TRS_ARTIFACT: &str = "gh-trs";
+pub const TRS_ARTIFACT_VERSION: &str = "2.0.1";
+/// Version of gh-trs itself, recorded in the service-info as `ghTrsVersion`.
+pub const GH_TRS_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// https://raw.githubusercontent.com/ga4gh-discovery/ga4gh-service-info/v1.0.0/service-info.yaml#/paths/~1service-info
 #[skip_serializing_none]
@@ -27,6 +35,9 @@ pub struct ServiceInfo {
     pub updated_at: Option<DateTime<Utc>>,
     pub environment: Option<String>,
     pub version: String,
+    /// The gh-trs version that generated the TRS response (not a field of the spec).
+    /// It is None for the service-info generated by an older gh-trs.
+    pub gh_trs_version: Option<String>,
 }
 
 pub fn serialize_date_time<S>(dt: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
@@ -50,8 +61,8 @@ impl Default for ServiceType {
     fn default() -> Self {
         Self {
             group: "gh-trs".to_string(),
-            artifact: "gh-trs".to_string(),
-            version: "2.0.1".to_string(),
+            artifact: TRS_ARTIFACT.to_string(),
+            version: TRS_ARTIFACT_VERSION.to_string(),
         }
     }
 }
@@ -83,10 +94,11 @@ impl ServiceInfo {
             updated_at: Some(created_at),
             environment: None,
             version: created_at.format("%Y%m%d%H%M%S").to_string(),
+            gh_trs_version: Some(GH_TRS_VERSION.to_string()),
         })
     }
 
-    /// Basically, prev has priority in all fields.
+    /// Basically, prev has priority in all fields, except `updated_at`, `version` and `gh_trs_version`.
     /// This is only for service-info, because there may be cases where to modify service-info by hand.
     pub fn new_or_update(
         prev: Option<Self>,
@@ -120,30 +132,37 @@ pub struct Checksum {
 
 impl Checksum {
     pub fn new_from_string(s: impl AsRef<str>) -> Self {
+        Self::new_from_bytes(s.as_ref().as_bytes())
+    }
+
+    pub fn new_from_bytes(bytes: impl AsRef<[u8]>) -> Self {
         let mut hasher = Sha256::new();
-        hasher.update(s.as_ref().as_bytes());
-        let checksum = format!("{:x}", hasher.finalize());
-        Self {
-            checksum,
-            r#type: "sha256".to_string(),
-        }
+        hasher.update(bytes.as_ref());
+        Self::new_from_hasher(hasher)
     }
 
-    pub fn new_from_url(url: &Url) -> Result<Self> {
-        let res = reqwest::blocking::get(url.as_str())?;
-        ensure!(
-            res.status().is_success(),
-            "Failed to get {} with status {}",
-            url,
-            res.status()
-        );
+    /// The reader is hashed in chunks, without reading it all into memory.
+    pub fn new_from_reader(mut reader: impl Read) -> Result<Self> {
         let mut hasher = Sha256::new();
-        hasher.update(res.bytes()?);
-        let checksum = format!("{:x}", hasher.finalize());
-        Ok(Self {
-            checksum,
+        io::copy(&mut reader, &mut hasher)?;
+        Ok(Self::new_from_hasher(hasher))
+    }
+
+    /// The checksum is computed over the raw bytes of the response, not the decoded text,
+    /// so it matches what a consumer computes over the downloaded file (e.g., with CRLF line endings).
+    /// The response body is streamed through the hasher, so a large file does not spike the memory use.
+    /// A file larger than `--max-file-size` is an error.
+    pub fn new_from_url(http: &remote::Http, url: &Url) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        remote::fetch_raw_stream(http, url, &mut hasher)?;
+        Ok(Self::new_from_hasher(hasher))
+    }
+
+    fn new_from_hasher(hasher: Sha256) -> Self {
+        Self {
+            checksum: format!("{:x}", hasher.finalize()),
             r#type: "sha256".to_string(),
-        })
+        }
     }
 }
 
@@ -200,7 +219,7 @@ impl Default for ToolClass {
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Tool {
     pub url: Url,
-    pub id: Uuid,
+    pub id: config::types::WorkflowId,
     pub aliases: Option<Vec<String>>,
     pub organization: String,
     pub name: Option<String>,
@@ -213,11 +232,8 @@ pub struct Tool {
 }
 
 impl Tool {
-    pub fn new(
-        config: &config::types::Config,
-        owner: impl AsRef<str>,
-        name: impl AsRef<str>,
-    ) -> Result<Self> {
+    /// `trs_url` is the TRS root URL (with a trailing slash) where the tool is published.
+    pub fn new(config: &config::types::Config, trs_url: &Url) -> Result<Self> {
         let organization = config
             .authors
             .iter()
@@ -225,13 +241,8 @@ impl Tool {
             .collect::<Vec<_>>()
             .join(", ");
         Ok(Self {
-            url: Url::parse(&format!(
-                "https://{}.github.io/{}/tools/{}",
-                owner.as_ref(),
-                name.as_ref(),
-                config.id,
-            ))?,
-            id: config.id,
+            url: trs_url.join(&format!("tools/{}", config.id))?,
+            id: config.id.clone(),
             aliases: None,
             organization,
             name: Some(config.workflow.name.clone()),
@@ -249,8 +260,7 @@ impl Tool {
     pub fn add_new_tool_version(
         &mut self,
         config: &config::types::Config,
-        owner: impl AsRef<str>,
-        name: impl AsRef<str>,
+        trs_url: &Url,
         verified: bool,
     ) -> Result<()> {
         let mut versions = self
@@ -268,11 +278,11 @@ impl Tool {
                 .find(|v| v.version() == config.version)
                 .unwrap()
                 .clone();
-            same_version.update(config, &owner, &name, verified)?;
+            same_version.update(config, trs_url, verified)?;
             versions.push(same_version);
         } else {
             // new
-            versions.push(ToolVersion::new(config, &owner, &name, verified)?);
+            versions.push(ToolVersion::new(config, trs_url, verified)?);
         }
         self.versions = versions;
         Ok(())
@@ -285,7 +295,7 @@ pub struct ToolVersion {
     pub author: Option<Vec<String>>,
     pub name: Option<String>,
     pub url: Url,
-    pub id: Uuid,
+    pub id: config::types::WorkflowId,
     pub is_production: Option<bool>,
     pub images: Option<Vec<ImageData>>,
     pub descriptor_type: Option<Vec<DescriptorType>>,
@@ -298,12 +308,7 @@ pub struct ToolVersion {
 }
 
 impl ToolVersion {
-    pub fn new(
-        config: &config::types::Config,
-        owner: impl AsRef<str>,
-        name: impl AsRef<str>,
-        verified: bool,
-    ) -> Result<Self> {
+    pub fn new(config: &config::types::Config, trs_url: &Url, verified: bool) -> Result<Self> {
         let verified_source = if verified {
             if env::in_ci() {
                 match env::gh_actions_url() {
@@ -326,14 +331,8 @@ impl ToolVersion {
                     .collect::<Vec<String>>(),
             ),
             name: Some(config.workflow.name.clone()),
-            url: Url::parse(&format!(
-                "https://{}.github.io/{}/tools/{}/versions/{}",
-                owner.as_ref(),
-                name.as_ref(),
-                config.id,
-                &config.version
-            ))?,
-            id: config.id,
+            url: trs_url.join(&format!("tools/{}/versions/{}", config.id, &config.version))?,
+            id: config.id.clone(),
             is_production: None,
             images: None,
             descriptor_type: Some(vec![DescriptorType::new(
@@ -356,8 +355,7 @@ impl ToolVersion {
     pub fn update(
         &mut self,
         config: &config::types::Config,
-        owner: impl AsRef<str>,
-        name: impl AsRef<str>,
+        trs_url: &Url,
         verified: bool,
     ) -> Result<()> {
         let new_verified_source = if verified {
@@ -387,14 +385,8 @@ impl ToolVersion {
                 .collect::<Vec<String>>(),
         );
         self.name = Some(config.workflow.name.clone());
-        self.url = Url::parse(&format!(
-            "https://{}.github.io/{}/tools/{}/versions/{}",
-            owner.as_ref(),
-            name.as_ref(),
-            config.id,
-            &config.version
-        ))?;
-        self.id = config.id;
+        self.url = trs_url.join(&format!("tools/{}/versions/{}", config.id, &config.version))?;
+        self.id = config.id.clone();
         self.descriptor_type = Some(vec![DescriptorType::new(
             &config
                 .workflow
@@ -412,8 +404,8 @@ impl ToolVersion {
     }
 
     pub fn version(&self) -> String {
-        let path_segments = self.url.path_segments().unwrap();
-        path_segments.last().unwrap().to_string()
+        let mut path_segments = self.url.path_segments().unwrap();
+        path_segments.next_back().unwrap().to_string()
     }
 }
 
@@ -485,6 +477,26 @@ pub struct FileWrapper {
 mod tests {
     use super::*;
     use crate::config;
+    use crate::trs;
+
+    #[test]
+    fn test_checksum_new_from_bytes() -> Result<()> {
+        let bytes = std::fs::read("./tests/test_crlf.txt")?;
+        assert!(bytes.ends_with(b"\r\n"));
+        let checksum = Checksum::new_from_bytes(&bytes);
+        assert_eq!(
+            checksum.checksum,
+            "4ad3ef64dfb83f7a8f789bce6f30cc1f8d18491b14db4c875309b150d2a7d213"
+        );
+        assert_eq!(checksum.r#type, "sha256");
+        assert_ne!(checksum, Checksum::new_from_string("line1\nline2\n"));
+        assert_eq!(checksum, Checksum::new_from_string("line1\r\nline2\r\n"));
+        assert_eq!(
+            checksum,
+            Checksum::new_from_reader(std::fs::File::open("./tests/test_crlf.txt")?)?
+        );
+        Ok(())
+    }
 
     #[test]
     fn test_new_or_update_service_info() -> Result<()> {
@@ -518,6 +530,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_service_info_matches_trs_artifact_version() -> Result<()> {
+        let service_info = ServiceInfo::new_or_update(None, "test_owner", "test_name")?;
+        assert_eq!(service_info.r#type.artifact, TRS_ARTIFACT);
+        assert_eq!(service_info.r#type.version, TRS_ARTIFACT_VERSION);
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_info_gh_trs_version() -> Result<()> {
+        let mut prev = ServiceInfo::new("test_owner", "test_name")?;
+        prev.gh_trs_version = Some("1.0.0".to_string());
+        let service_info = ServiceInfo::new_or_update(Some(prev), "test_owner", "test_name")?;
+        assert_eq!(
+            service_info.gh_trs_version,
+            Some(GH_TRS_VERSION.to_string())
+        );
+        assert_ne!(
+            service_info.gh_trs_version,
+            Some(service_info.version.clone())
+        );
+
+        let value = serde_json::to_value(&service_info)?;
+        assert_eq!(value["ghTrsVersion"], GH_TRS_VERSION);
+        assert_eq!(value["type"]["version"], TRS_ARTIFACT_VERSION);
+
+        // The service-info generated by an older gh-trs has no `ghTrsVersion`.
+        let mut value = value;
+        value.as_object_mut().unwrap().remove("ghTrsVersion");
+        let old: ServiceInfo = serde_json::from_value(value)?;
+        assert_eq!(old.gh_trs_version, None);
+        Ok(())
+    }
+
     #[test]
     fn test_file_type_new_from_file_type() -> Result<()> {
         let file_type = FileType::new_from_file_type(&config::types::FileType::Primary);
@@ -541,8 +587,11 @@ mod tests {
 
     #[test]
     fn test_tool_new() -> Result<()> {
-        let config = config::io::read_config("./tests/test_config_CWL_validated.yml")?;
-        let tool = Tool::new(&config, "test_owner", "test_name")?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = trs::api::gh_pages_trs_url("test_owner", "test_name", None)?;
+        let tool = Tool::new(&config, &trs_url)?;
 
         let expect = serde_json::from_str::<Tool>(
             r#"
@@ -569,11 +618,14 @@ mod tests {
 
     #[test]
     fn test_tool_add_new_tool_version() -> Result<()> {
-        let config = config::io::read_config("./tests/test_config_CWL_validated.yml")?;
-        let mut tool = Tool::new(&config, "test_owner", "test_name")?;
-        tool.add_new_tool_version(&config, "test_owner", "test_name", true)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = trs::api::gh_pages_trs_url("test_owner", "test_name", None)?;
+        let mut tool = Tool::new(&config, &trs_url)?;
+        tool.add_new_tool_version(&config, &trs_url, true)?;
         assert_eq!(tool.versions.len(), 1);
-        tool.add_new_tool_version(&config, "test_owner", "test_name", true)?;
+        tool.add_new_tool_version(&config, &trs_url, true)?;
         assert_eq!(tool.versions.len(), 1);
 
         //         let expect = serde_json::from_str::<Tool>(
@@ -613,8 +665,11 @@ mod tests {
 
     #[test]
     fn test_tool_version_new() -> Result<()> {
-        let config = config::io::read_config("./tests/test_config_CWL_validated.yml")?;
-        ToolVersion::new(&config, "test_owner", "test_name", true)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = trs::api::gh_pages_trs_url("test_owner", "test_name", None)?;
+        ToolVersion::new(&config, &trs_url, true)?;
         //         let expect = serde_json::from_str::<ToolVersion>(
         //             r#"
         // {
@@ -636,10 +691,27 @@ mod tests {
 
     #[test]
     fn test_tool_version_version() -> Result<()> {
-        let config = config::io::read_config("./tests/test_config_CWL_validated.yml")?;
-        let tool_version = ToolVersion::new(&config, "test_owner", "test_name", true)?;
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = trs::api::gh_pages_trs_url("test_owner", "test_name", None)?;
+        let tool_version = ToolVersion::new(&config, &trs_url, true)?;
         let version = tool_version.version();
         assert_eq!(version, "1.0.0");
         Ok(())
     }
+
+    #[test]
+    fn test_tool_version_url_user_pages() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let trs_url = trs::api::gh_pages_trs_url("suecharo", "suecharo.github.io", None)?;
+        let tool_version = ToolVersion::new(&config, &trs_url, true)?;
+        assert_eq!(
+            tool_version.url.as_str(),
+            "https://suecharo.github.io/tools/493c4948-987a-482b-993c-733bdb3d1d6c/versions/1.0.0"
+        );
+        Ok(())
+    }
 }
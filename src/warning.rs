@@ -0,0 +1,183 @@
+use colored::Colorize;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use url::Url;
+
+/// A soft issue found in a config.
+/// It is logged when emitted and collected to be reported as a GitHub Actions annotation in CI.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Warning {
+    /// Location of the config (local file path or remote URL), if known.
+    pub location: Option<String>,
+    /// Path of the field in the config, e.g., `workflow.name`.
+    pub path: String,
+    pub message: String,
+}
+
+impl Warning {
+    /// Log the warning and return it.
+    pub fn emit(path: impl AsRef<str>, message: impl AsRef<str>) -> Self {
+        warn!(
+            "{}: {} ({})",
+            "Warning".yellow(),
+            message.as_ref(),
+            path.as_ref()
+        );
+        Self {
+            location: None,
+            path: path.as_ref().to_string(),
+            message: message.as_ref().to_string(),
+        }
+    }
+
+    pub fn with_location(mut self, location: impl AsRef<str>) -> Self {
+        self.location = Some(location.as_ref().to_string());
+        self
+    }
+
+    /// https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions#setting-a-warning-message
+    /// Only a local file path is given as `file`, a remote URL is prepended to the message.
+    pub fn to_gh_annotation(&self) -> String {
+        match &self.location {
+            Some(location) if Url::parse(location).is_err() => format!(
+                "::warning file={},title={}::{}",
+                escape_property(location),
+                escape_property(&self.path),
+                escape_data(&self.message)
+            ),
+            Some(location) => format!(
+                "::warning title={}::{}",
+                escape_property(&self.path),
+                escape_data(format!("{}: {}", location, self.message))
+            ),
+            None => format!(
+                "::warning title={}::{}",
+                escape_property(&self.path),
+                escape_data(&self.message)
+            ),
+        }
+    }
+}
+
+/// An error found in a config, carrying the location of the config (and the line if known).
+/// The message is the same as the wrapped error, so the normal error output is unchanged.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub location: String,
+    pub line: Option<usize>,
+    pub source: anyhow::Error,
+}
+
+impl ConfigError {
+    /// The line is taken from the YAML parse error in the chain of `source`, if any.
+    pub fn new(location: impl AsRef<str>, source: anyhow::Error) -> Self {
+        let line = source
+            .chain()
+            .find_map(|e| e.downcast_ref::<serde_yaml::Error>())
+            .and_then(|e| e.location())
+            .map(|l| l.line());
+        Self {
+            location: location.as_ref().to_string(),
+            line,
+            source,
+        }
+    }
+
+    /// https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions#setting-an-error-message
+    /// As with `Warning`, a remote URL is prepended to the message instead of being given as `file`.
+    pub fn to_gh_annotation(&self) -> String {
+        if Url::parse(&self.location).is_ok() {
+            return format!(
+                "::error::{}",
+                escape_data(format!("{}: {}", self.location, self.source))
+            );
+        }
+        match self.line {
+            Some(line) => format!(
+                "::error file={},line={}::{}",
+                escape_property(&self.location),
+                line,
+                escape_data(self.source.to_string())
+            ),
+            None => format!(
+                "::error file={}::{}",
+                escape_property(&self.location),
+                escape_data(self.source.to_string())
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.source()
+    }
+}
+
+fn escape_data(s: impl AsRef<str>) -> String {
+    s.as_ref()
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: impl AsRef<str>) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_gh_annotation() {
+        let warning = Warning::emit("workflow.name", "Workflow name is normalized");
+        assert_eq!(
+            warning.to_gh_annotation(),
+            "::warning title=workflow.name::Workflow name is normalized"
+        );
+
+        let warning = warning.with_location("configs/a.yml");
+        assert_eq!(
+            warning.to_gh_annotation(),
+            "::warning file=configs/a.yml,title=workflow.name::Workflow name is normalized"
+        );
+
+        let warning = Warning::emit("lisence", "Unknown field is ignored\n100%")
+            .with_location("https://example.com/a.yml");
+        assert_eq!(
+            warning.to_gh_annotation(),
+            "::warning title=lisence::https://example.com/a.yml: Unknown field is ignored%0A100%25"
+        );
+    }
+
+    #[test]
+    fn test_config_error_to_gh_annotation() {
+        let source = serde_yaml::from_str::<serde_yaml::Value>("id: a\nversion: [1.0.0\n")
+            .unwrap_err()
+            .into();
+        let err = ConfigError::new("configs/a.yml", source);
+        assert_eq!(err.line, Some(3));
+        assert!(err
+            .to_gh_annotation()
+            .starts_with("::error file=configs/a.yml,line=3::"));
+
+        let err = ConfigError::new(
+            "https://example.com/a.yml",
+            anyhow::anyhow!("No authors found in config file"),
+        );
+        assert_eq!(err.line, None);
+        assert_eq!(
+            err.to_gh_annotation(),
+            "::error::https://example.com/a.yml: No authors found in config file"
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use crate::config;
+use crate::remote;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use reqwest;
+use reqwest::blocking::multipart;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time;
+use url::Url;
+
+const MAX_ERROR_BODY_LEN: usize = 1000;
+
+static ENGINE_MAP: Mutex<Vec<(config::types::LanguageType, String)>> = Mutex::new(Vec::new());
+
+/// Set the workflow engine names used instead of the default ones for all test cases.
+pub fn set_engine_map(engine_map: Vec<(config::types::LanguageType, String)>) {
+    *ENGINE_MAP.lock().unwrap_or_else(|e| e.into_inner()) = engine_map;
+}
+
+pub fn default_engine_name(wf_type: &config::types::LanguageType) -> &'static str {
+    match wf_type {
+        config::types::LanguageType::Cwl => "cwltool",
+        config::types::LanguageType::Wdl => "cromwell",
+        config::types::LanguageType::Nfl => "nextflow",
+        config::types::LanguageType::Smk => "snakemake",
+    }
+}
+
+/// The last matching entry of `engine_map` wins, otherwise the default engine of the language is used.
+pub fn engine_name(
+    engine_map: &[(config::types::LanguageType, String)],
+    wf_type: &config::types::LanguageType,
+) -> String {
+    engine_map
+        .iter()
+        .rev()
+        .find(|(language, _)| language == wf_type)
+        .map_or_else(
+            || default_engine_name(wf_type).to_string(),
+            |(_, engine)| engine.clone(),
+        )
+}
+
+/// Read the response body for an error message, truncated to `MAX_ERROR_BODY_LEN` characters.
+pub fn error_body(response: reqwest::blocking::Response) -> String {
+    truncate_body(response.text().unwrap_or_default())
+}
+
+pub fn truncate_body(body: impl AsRef<str>) -> String {
+    let body = body.as_ref().trim();
+    match body.char_indices().nth(MAX_ERROR_BODY_LEN) {
+        Some((pos, _)) => format!("{}... (truncated)", &body[..pos]),
+        None => body.to_string(),
+    }
+}
+
+pub fn get_supported_wes_versions(http: &remote::Http, wes_loc: &Url) -> Result<Vec<String>> {
+    let url = Url::parse(&format!(
+        "{}/service-info",
+        wes_loc.as_str().trim().trim_end_matches('/')
+    ))?;
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .get(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/json"),
+    )?;
+    ensure!(
+        response.status().is_success(),
+        "Failed to get service-info with status: {} from {}",
+        response.status(),
+        url.as_str()
+    );
+    let res_body = response.json::<Value>()?;
+    let err_msg = "Failed to parse the response to get service-info";
+    let supported_wes_versions = res_body
+        .get("supported_wes_versions")
+        .ok_or_else(|| anyhow!("{}", err_msg))?
+        .as_array()
+        .ok_or_else(|| anyhow!("{}", err_msg))?
+        .iter()
+        .map(|v| v.as_str().ok_or_else(|| anyhow!("{}", err_msg)))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect();
+    Ok(supported_wes_versions)
+}
+
+pub fn test_case_to_form(
+    http: &remote::Http,
+    config: &config::types::Config,
+    test_case: &config::types::Testing,
+    extra_tags: &[(String, String)],
+) -> Result<multipart::Form> {
+    let wf = &config.workflow;
+    let wf_type = wf_type(wf)?;
+    let form = multipart::Form::new()
+        .text("workflow_type", wf_type.to_string())
+        .text("workflow_type_version", wf_type_version(wf)?)
+        .text("workflow_url", wf_url(wf)?)
+        .text(
+            "workflow_engine_name",
+            engine_name(
+                &ENGINE_MAP.lock().unwrap_or_else(|e| e.into_inner()),
+                &wf_type,
+            ),
+        )
+        .text("workflow_params", test_case.wf_params(http)?)
+        .text(
+            "workflow_engine_parameters",
+            test_case.wf_engine_params(http)?,
+        )
+        .text("workflow_attachment", wf_attachment(wf, test_case)?)
+        .text("tags", run_tags(config, test_case, extra_tags)?);
+    Ok(form)
+}
+
+/// Tags to identify the run in the WES run list.
+/// The workflow id, version, and test id take precedence over the extra tags.
+pub fn run_tags(
+    config: &config::types::Config,
+    test_case: &config::types::Testing,
+    extra_tags: &[(String, String)],
+) -> Result<String> {
+    let mut tags = Map::new();
+    for (key, value) in extra_tags {
+        tags.insert(key.clone(), Value::String(value.clone()));
+    }
+    tags.insert(
+        "workflow_id".to_string(),
+        Value::String(config.id.to_string()),
+    );
+    tags.insert(
+        "workflow_version".to_string(),
+        Value::String(config.version.clone()),
+    );
+    tags.insert("test_id".to_string(), Value::String(test_case.id.clone()));
+    Ok(serde_json::to_string(&tags)?)
+}
+
+pub fn wf_type(wf: &config::types::Workflow) -> Result<config::types::LanguageType> {
+    wf.language
+        .r#type
+        .clone()
+        .ok_or_else(|| anyhow!("Workflow language type must be set"))
+}
+
+pub fn wf_type_version(wf: &config::types::Workflow) -> Result<String> {
+    wf.language
+        .version
+        .clone()
+        .ok_or_else(|| anyhow!("Workflow language version must be set"))
+}
+
+pub fn wf_url(wf: &config::types::Workflow) -> Result<String> {
+    let primary_wf = wf.primary_wf()?;
+    match wf_type(wf)? {
+        config::types::LanguageType::Nfl => {
+            let file_name = match primary_wf.target.as_ref().and_then(|t| t.to_str()) {
+                Some(file_name) => file_name.to_string(),
+                None => primary_wf.url.path().to_string(),
+            };
+            Ok(file_name)
+        }
+        _ => Ok(primary_wf.url.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachedFile {
+    file_name: PathBuf,
+    file_url: Url,
+}
+
+impl AttachedFile {
+    pub fn new_from_file(file: &config::types::File) -> Result<Self> {
+        Ok(Self {
+            file_name: file
+                .target
+                .clone()
+                .ok_or_else(|| anyhow!("File target must be set: {}", file.url))?,
+            file_url: file.url.clone(),
+        })
+    }
+
+    pub fn new_from_test_file(test_file: &config::types::TestFile) -> Result<Self> {
+        Ok(Self {
+            file_name: test_file
+                .target
+                .clone()
+                .ok_or_else(|| anyhow!("Test file target must be set: {}", test_file.url))?,
+            file_url: test_file.url.clone(),
+        })
+    }
+}
+
+pub fn wf_attachment(
+    wf: &config::types::Workflow,
+    test_case: &config::types::Testing,
+) -> Result<String> {
+    let wf_type = wf_type(wf)?;
+    let mut attachments: Vec<AttachedFile> = vec![];
+    for f in &wf.files {
+        match &f.r#type {
+            config::types::FileType::Primary => {
+                if wf_type == config::types::LanguageType::Nfl {
+                    attachments.push(AttachedFile::new_from_file(f)?);
+                }
+            }
+            config::types::FileType::Secondary => {
+                attachments.push(AttachedFile::new_from_file(f)?);
+            }
+        }
+    }
+    for f in &test_case.files {
+        if f.r#type == config::types::TestFileType::Other {
+            attachments.push(AttachedFile::new_from_test_file(f)?);
+        }
+    }
+    let attachments_json = serde_json::to_string(&attachments)?;
+    Ok(attachments_json)
+}
+
+pub fn post_run(http: &remote::Http, wes_loc: &Url, form: multipart::Form) -> Result<String> {
+    let url = Url::parse(&format!(
+        "{}/runs",
+        wes_loc.as_str().trim().trim_end_matches('/')
+    ))?;
+    let client = http
+        .client_builder()
+        .timeout(time::Duration::from_secs(300))
+        .build()?;
+    let response = http.send(
+        client
+            .post(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(reqwest::header::CONTENT_TYPE, "multipart/form-data")
+            .multipart(form),
+    )?;
+    let status = response.status();
+    ensure!(
+        status.is_success(),
+        "Failed to post run with status: {} from {}. Response: {}",
+        status,
+        url.as_str(),
+        error_body(response)
+    );
+    let res_body = response.json::<Value>()?;
+    let err_msg = "Failed to parse the response to post a run";
+    let run_id = res_body
+        .get("run_id")
+        .ok_or_else(|| anyhow!(err_msg))?
+        .as_str()
+        .ok_or_else(|| anyhow!(err_msg))?
+        .to_string();
+    Ok(run_id)
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum RunStatus {
+    Running,
+    Complete,
+    Failed,
+}
+
+impl FromStr for RunStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "QUEUED" => Ok(RunStatus::Running),
+            "INITIALIZING" => Ok(RunStatus::Running),
+            "RUNNING" => Ok(RunStatus::Running),
+            "PAUSED" => Ok(RunStatus::Running),
+            "COMPLETE" => Ok(RunStatus::Complete),
+            "EXECUTOR_ERROR" => Ok(RunStatus::Failed),
+            "SYSTEM_ERROR" => Ok(RunStatus::Failed),
+            "CANCELED" => Ok(RunStatus::Failed),
+            "CANCELING" => Ok(RunStatus::Failed),
+            "UNKNOWN" => bail!("Unknown run status: {}", s),
+            _ => Err(anyhow!("Failed to parse run status")),
+        }
+    }
+}
+
+pub fn get_run_status(
+    http: &remote::Http,
+    wes_loc: &Url,
+    run_id: impl AsRef<str>,
+) -> Result<RunStatus> {
+    let url = Url::parse(&format!(
+        "{}/runs/{}/status",
+        wes_loc.as_str().trim().trim_end_matches('/'),
+        run_id.as_ref()
+    ))?;
+    let client = http.client()?;
+    let mut retry_count = 0;
+    let response = loop {
+        match http.send(client.get(url.as_str())) {
+            Ok(response) => break response,
+            Err(e) => {
+                retry_count += 1;
+                if retry_count > 3 {
+                    bail!("Failed to get run status: {}", e);
+                }
+                thread::sleep(time::Duration::from_secs(5));
+            }
+        }
+    };
+    let status = response.status();
+    ensure!(
+        status.is_success(),
+        "Failed to get run status with status: {} from {}. Response: {}",
+        status,
+        url.as_str(),
+        error_body(response)
+    );
+    let err_msg = "Failed to parse the response to get run status";
+    let res_body = response.json::<Value>()?;
+    RunStatus::from_str(
+        res_body
+            .get("state")
+            .ok_or_else(|| anyhow!(err_msg))?
+            .as_str()
+            .ok_or_else(|| anyhow!(err_msg))?,
+    )
+}
+
+pub fn get_run_log(http: &remote::Http, wes_loc: &Url, run_id: impl AsRef<str>) -> Result<Value> {
+    let url = Url::parse(&format!(
+        "{}/runs/{}",
+        wes_loc.as_str().trim().trim_end_matches('/'),
+        run_id.as_ref()
+    ))?;
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .get(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/json"),
+    )?;
+    let status = response.status();
+    ensure!(
+        status.is_success(),
+        "Failed to get run log with status: {} from {}. Response: {}",
+        status,
+        url.as_str(),
+        error_body(response)
+    );
+    let res_body = response.json::<Value>()?;
+    Ok(res_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wes::sapporo;
+
+    #[test]
+    fn test_engine_name() {
+        let engine_map = vec![
+            (config::types::LanguageType::Cwl, "toil".to_string()),
+            (config::types::LanguageType::Cwl, "cwl-runner".to_string()),
+        ];
+        assert_eq!(
+            engine_name(&engine_map, &config::types::LanguageType::Cwl),
+            "cwl-runner"
+        );
+        assert_eq!(
+            engine_name(&engine_map, &config::types::LanguageType::Wdl),
+            "cromwell"
+        );
+        assert_eq!(
+            engine_name(&[], &config::types::LanguageType::Cwl),
+            "cwltool"
+        );
+    }
+
+    #[test]
+    fn test_run_tags() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let tags = run_tags(
+            &config,
+            &config.workflow.testing[0],
+            &[
+                ("ci".to_string(), "true".to_string()),
+                ("test_id".to_string(), "overridden".to_string()),
+            ],
+        )?;
+        let tags: Value = serde_json::from_str(&tags)?;
+        assert_eq!(tags["workflow_id"], "493c4948-987a-482b-993c-733bdb3d1d6c");
+        assert_eq!(tags["workflow_version"], "1.0.0");
+        assert_eq!(tags["test_id"], "test_1");
+        assert_eq!(tags["ci"], "true");
+        Ok(())
+    }
+
+    #[test]
+    fn test_test_case_to_form_without_language() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let mut config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        config.workflow.language.version = None;
+        let err = test_case_to_form(&http, &config, &config.workflow.testing[0], &[]).unwrap_err();
+        assert_eq!(err.to_string(), "Workflow language version must be set");
+        config.workflow.language.r#type = None;
+        let err = test_case_to_form(&http, &config, &config.workflow.testing[0], &[]).unwrap_err();
+        assert_eq!(err.to_string(), "Workflow language type must be set");
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_body() -> Result<()> {
+        assert_eq!(truncate_body(" short body \n"), "short body");
+        let long_body = "あ".repeat(MAX_ERROR_BODY_LEN + 1);
+        assert_eq!(
+            truncate_body(&long_body),
+            format!("{}... (truncated)", "あ".repeat(MAX_ERROR_BODY_LEN))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_supported_wes_versions() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let docker_host = Url::parse("unix:///var/run/docker.sock")?;
+        let wes_port = sapporo::free_port()?;
+        sapporo::start_wes(&http, &docker_host, wes_port, &[], &None)?;
+        let wes_loc = Url::parse(&sapporo::default_wes_location(wes_port))?;
+        let supported_wes_versions = get_supported_wes_versions(&http, &wes_loc)?;
+        assert!(!supported_wes_versions.is_empty());
+        sapporo::stop_wes(&docker_host, &None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_post_run() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let docker_host = Url::parse("unix:///var/run/docker.sock")?;
+        let wes_port = sapporo::free_port()?;
+        sapporo::start_wes(&http, &docker_host, wes_port, &[], &None)?;
+        let wes_loc = Url::parse(&sapporo::default_wes_location(wes_port))?;
+        let config =
+            config::io::read_config(&http, "./tests/test_config_CWL_validated.yml", false)?;
+        let form = test_case_to_form(&http, &config, &config.workflow.testing[0], &[])?;
+        let run_id = post_run(&http, &wes_loc, form)?;
+        assert!(!run_id.is_empty());
+        sapporo::stop_wes(&docker_host, &None)?;
+        Ok(())
+    }
+}
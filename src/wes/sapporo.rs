@@ -0,0 +1,599 @@
+use crate::env;
+use crate::remote;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use colored::Colorize;
+use log::{debug, info, warn};
+use reqwest;
+use std::env as std_env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time;
+use url::Url;
+
+const SAPPORO_SERVICE_IMAGE: &str = "ghcr.io/sapporo-wes/sapporo-service:1.2.4";
+/// Port sapporo-service listens on inside the container.
+const SAPPORO_SERVICE_PORT: u16 = 1122;
+
+pub const DEFAULT_DOCKER_RUN_TIMEOUT: u64 = 60;
+pub const DEFAULT_DOCKER_TIMEOUT: u64 = 30;
+
+static DOCKER_RUN_TIMEOUT: AtomicU64 = AtomicU64::new(DEFAULT_DOCKER_RUN_TIMEOUT);
+static DOCKER_TIMEOUT: AtomicU64 = AtomicU64::new(DEFAULT_DOCKER_TIMEOUT);
+
+/// Set the timeouts (in seconds) for `docker run` and the other docker commands (`ps`, `kill`).
+pub fn set_docker_timeouts(run_timeout: u64, timeout: u64) {
+    DOCKER_RUN_TIMEOUT.store(run_timeout, Ordering::SeqCst);
+    DOCKER_TIMEOUT.store(timeout, Ordering::SeqCst);
+}
+
+fn docker_run_timeout() -> time::Duration {
+    time::Duration::from_secs(DOCKER_RUN_TIMEOUT.load(Ordering::SeqCst))
+}
+
+fn docker_timeout() -> time::Duration {
+    time::Duration::from_secs(DOCKER_TIMEOUT.load(Ordering::SeqCst))
+}
+
+/// States of sapporo-service runs that are not finished yet, whose run directories must not be removed.
+const IN_PROGRESS_STATES: [&str; 5] = ["QUEUED", "INITIALIZING", "RUNNING", "PAUSED", "CANCELING"];
+
+static CLEAN_RUN_DIR: Mutex<Option<time::Duration>> = Mutex::new(None);
+
+/// Remove the finished runs older than `min_age` under the run dir when sapporo-service is started and stopped.
+/// `None` disables the cleanup.
+pub fn set_clean_run_dir(min_age: Option<time::Duration>) {
+    *CLEAN_RUN_DIR.lock().unwrap_or_else(|e| e.into_inner()) = min_age;
+}
+
+#[cfg(not(tarpaulin_include))]
+fn clean_run_dir_if_enabled(run_dir: &Option<PathBuf>) {
+    let min_age = match *CLEAN_RUN_DIR.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(min_age) => min_age,
+        None => return,
+    };
+    match env::sapporo_run_dir(run_dir).and_then(|run_dir| clean_run_dir(run_dir, min_age)) {
+        Ok(removed) => info!("Removed {} finished runs from the run dir", removed),
+        Err(e) => warn!("{}: Failed to clean the run dir: {}", "Warning".yellow(), e),
+    }
+}
+
+/// Remove the directories of the finished runs (`<run_dir>/<run_id[:2]>/<run_id>`) whose `state.txt` was last
+/// updated more than `min_age` ago, and return the number of the removed runs.
+/// Runs in progress and runs without `state.txt` (e.g., being initialized) are kept.
+pub fn clean_run_dir(run_dir: impl AsRef<Path>, min_age: time::Duration) -> Result<usize> {
+    if !run_dir.as_ref().is_dir() {
+        return Ok(0);
+    }
+    let now = time::SystemTime::now();
+    let mut removed = 0;
+    for prefix_dir in fs::read_dir(run_dir)? {
+        let prefix_dir = prefix_dir?.path();
+        if !prefix_dir.is_dir() {
+            continue;
+        }
+        for run in fs::read_dir(&prefix_dir)? {
+            let run = run?.path();
+            let state_file = run.join("state.txt");
+            let state = match fs::read_to_string(&state_file) {
+                Ok(state) => state.trim().to_string(),
+                Err(_) => continue,
+            };
+            if IN_PROGRESS_STATES.contains(&state.as_str()) {
+                debug!("Keep the run {} in state {}", run.display(), state);
+                continue;
+            }
+            let age = now
+                .duration_since(fs::metadata(&state_file)?.modified()?)
+                .unwrap_or_default();
+            if age < min_age {
+                continue;
+            }
+            match fs::remove_dir_all(&run) {
+                Ok(()) => {
+                    debug!("Removed the run {} in state {}", run.display(), state);
+                    removed += 1;
+                }
+                // e.g., files written by the containers as another user
+                Err(e) => warn!(
+                    "{}: Failed to remove the run {}: {}",
+                    "Warning".yellow(),
+                    run.display(),
+                    e
+                ),
+            }
+        }
+        // Only succeeds if no run is left.
+        fs::remove_dir(&prefix_dir).ok();
+    }
+    Ok(removed)
+}
+
+/// Run the command and wait for it to finish.
+/// If it does not finish within the timeout (e.g., a hung docker daemon), kill it and return an error.
+fn output_with_timeout(command: &mut Command, timeout: time::Duration) -> Result<Output> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Please make sure that the docker command is present in your PATH")?;
+    // Read the pipes in other threads so that the child does not block on a full pipe.
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture stdout"))?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture stderr"))?;
+    let stdout_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        stdout.read_to_end(&mut buf).map(|_| buf)
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        stderr.read_to_end(&mut buf).map(|_| buf)
+    });
+
+    let start = time::Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            child.kill().ok();
+            child.wait().ok();
+            bail!(
+                "The docker command did not finish within {} seconds and was killed. Please make sure that the docker daemon is responding",
+                timeout.as_secs()
+            );
+        }
+        thread::sleep(time::Duration::from_millis(100));
+    };
+    let stdout = stdout_reader
+        .join()
+        .map_err(|_| anyhow!("Failed to read stdout"))??;
+    let stderr = stderr_reader
+        .join()
+        .map_err(|_| anyhow!("Failed to read stderr"))??;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// The container name of sapporo-service, unique to this process like the default run dir,
+/// so that an invocation never reuses (or kills) the sapporo-service of a concurrent one.
+pub fn sapporo_service_name() -> String {
+    format!("gh-trs-sapporo-service-{}", std::process::id())
+}
+
+/// A free host port to publish sapporo-service on when `--wes-port` is not given.
+pub fn free_port() -> Result<u16> {
+    Ok(TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port())
+}
+
+pub fn inside_docker_container() -> bool {
+    Path::new("/.dockerenv").exists()
+}
+
+/// Location of the sapporo-service started by gh-trs.
+/// `wes_port` is the host port, which is not used inside a docker container (accessed through the docker network).
+pub fn default_wes_location(wes_port: u16) -> String {
+    if inside_docker_container() {
+        format!("http://{}:{}", sapporo_service_name(), SAPPORO_SERVICE_PORT)
+    } else {
+        format!("http://localhost:{}", wes_port)
+    }
+}
+
+/// `run_dir` is the `--run-dir` (see `env::sapporo_run_dir`).
+pub fn start_wes(
+    http: &remote::Http,
+    docker_host: &Url,
+    wes_port: u16,
+    binds: &[(PathBuf, PathBuf)],
+    run_dir: &Option<PathBuf>,
+) -> Result<()> {
+    clean_run_dir_if_enabled(run_dir);
+    let status = check_wes_running(docker_host)?;
+    if status {
+        info!("sapporo-service is already running. So skip starting it.");
+        return Ok(());
+    }
+
+    if !image_exists(docker_host)? {
+        pull_image(docker_host)?;
+    }
+
+    info!(
+        "Starting sapporo-service using docker_host: {}",
+        docker_host.as_str()
+    );
+    let sapporo_run_dir = &env::sapporo_run_dir(run_dir)?;
+    fs::create_dir_all(sapporo_run_dir)?;
+    info!("Use run dir: {}", sapporo_run_dir);
+    let arg_socket_val = &format!("{}:/var/run/docker.sock", docker_host.path());
+    let arg_tmp_val = &format!(
+        "{}:/tmp",
+        std_env::temp_dir()
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid path"))?
+    );
+    let arg_run_dir_val = &format!("{}:{}", sapporo_run_dir, sapporo_run_dir);
+    let arg_port_val = &format!("{}:{}", wes_port, SAPPORO_SERVICE_PORT);
+    let (arg_network, arg_network_val) = if inside_docker_container() {
+        ("--network", "gh-trs-network")
+    } else {
+        ("-p", arg_port_val.as_str())
+    };
+    let mut command = Command::new("docker");
+    command.args([
+        "-H",
+        docker_host.as_str(),
+        "run",
+        "-d",
+        "--rm",
+        "-v",
+        arg_socket_val,
+        "-v",
+        arg_tmp_val,
+        "-v",
+        arg_run_dir_val,
+    ]);
+    for (host, container) in binds {
+        command.arg("-v").arg(format!(
+            "{}:{}",
+            host.to_str().ok_or_else(|| anyhow!("Invalid path"))?,
+            container.to_str().ok_or_else(|| anyhow!("Invalid path"))?
+        ));
+    }
+    let name = sapporo_service_name();
+    command.args([
+        arg_network,
+        arg_network_val,
+        "--name",
+        &name,
+        SAPPORO_SERVICE_IMAGE,
+        "sapporo",
+        "--run-dir",
+        sapporo_run_dir,
+    ]);
+    let output = output_with_timeout(&mut command, docker_run_timeout())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        debug!("Stderr from docker:\n{}", stderr.trim());
+        match docker_error_hint(&stderr) {
+            Some(hint) => bail!("Failed to start sapporo-service: {}", hint),
+            None => bail!("Failed to start sapporo-service:\n{}", stderr),
+        }
+    }
+    info!(
+        "Stdout from docker:\n{}",
+        String::from_utf8_lossy(&output.stdout).trim()
+    );
+
+    // health check
+    let mut retry = 0;
+    while retry < 5 {
+        match sapporo_health_check(http, wes_port) {
+            Ok(_) => break,
+            Err(_) => thread::sleep(time::Duration::from_secs(2)),
+        }
+        retry += 1;
+    }
+    ensure!(
+        retry < 5,
+        "Failed to start sapporo-service:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(())
+}
+
+pub fn image_exists(docker_host: &Url) -> Result<bool> {
+    let output = output_with_timeout(
+        Command::new("docker").args([
+            "-H",
+            docker_host.as_str(),
+            "image",
+            "inspect",
+            SAPPORO_SERVICE_IMAGE,
+        ]),
+        docker_timeout(),
+    )?;
+    Ok(output.status.success())
+}
+
+/// Pull the sapporo-service image, streaming the progress of `docker pull` at info level.
+/// No timeout is applied because the duration depends on the network.
+pub fn pull_image(docker_host: &Url) -> Result<()> {
+    info!("Pulling {}", SAPPORO_SERVICE_IMAGE);
+    let mut child = Command::new("docker")
+        .args(["-H", docker_host.as_str(), "pull", SAPPORO_SERVICE_IMAGE])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Please make sure that the docker command is present in your PATH")?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture stderr"))?;
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        stderr.read_to_end(&mut buf).map(|_| buf)
+    });
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture stdout"))?;
+    for line in BufReader::new(stdout).lines() {
+        info!("{}", line?.trim());
+    }
+    let status = child.wait()?;
+    let stderr = stderr_reader
+        .join()
+        .map_err(|_| anyhow!("Failed to read stderr"))??;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        debug!("Stderr from docker:\n{}", stderr.trim());
+        match docker_error_hint(&stderr) {
+            Some(hint) => bail!("Failed to pull {}: {}", SAPPORO_SERVICE_IMAGE, hint),
+            None => bail!("Failed to pull {}:\n{}", SAPPORO_SERVICE_IMAGE, stderr),
+        }
+    }
+    Ok(())
+}
+
+/// Map the common failures of docker to concise and actionable messages.
+fn docker_error_hint(stderr: &str) -> Option<&'static str> {
+    if stderr.contains("port is already allocated") || stderr.contains("address already in use") {
+        Some("The port is already in use. Please specify another port with `--wes-port` or a running WES with `--wes-location`")
+    } else if stderr.contains("Cannot connect to the Docker daemon") {
+        Some("Cannot connect to the Docker daemon. Please make sure that docker is running and `--docker-host` is correct")
+    } else if stderr.contains("manifest unknown") || stderr.contains("pull access denied") {
+        Some("Failed to pull the sapporo-service image. Please check the network connection and the image name")
+    } else if stderr.contains("is already in use by container") {
+        Some("A container with the same name already exists. Please remove the stale `gh-trs-sapporo-service-<pid>` container with `docker rm -f`")
+    } else {
+        None
+    }
+}
+
+pub fn stop_wes(docker_host: &Url, run_dir: &Option<PathBuf>) -> Result<()> {
+    let status = check_wes_running(docker_host)?;
+    if !status {
+        info!("sapporo-service is not running. So skip stopping it.");
+        clean_run_dir_if_enabled(run_dir);
+        return Ok(());
+    }
+
+    info!("Stopping sapporo-service");
+    let output = output_with_timeout(
+        Command::new("docker").args(["-H", docker_host.as_str(), "kill", &sapporo_service_name()]),
+        docker_timeout(),
+    )?;
+    ensure!(
+        output.status.success(),
+        "Failed to stop the sapporo-service:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    info!(
+        "Stdout from docker:\n{}",
+        String::from_utf8_lossy(&output.stdout).trim()
+    );
+    thread::sleep(time::Duration::from_secs(3));
+    clean_run_dir_if_enabled(run_dir);
+    remove_default_run_dir(run_dir);
+    Ok(())
+}
+
+/// The default run dir is unique to this invocation, so remove it after use (best effort).
+/// The artifacts are kept only in a run dir given by `--run-dir` or `SAPPORO_RUN_DIR`.
+#[cfg(not(tarpaulin_include))]
+fn remove_default_run_dir(run_dir: &Option<PathBuf>) {
+    let default_run_dir = env::default_sapporo_run_dir();
+    if env::sapporo_run_dir(run_dir).ok().as_deref() != default_run_dir.to_str()
+        || !default_run_dir.exists()
+    {
+        return;
+    }
+    if let Err(e) = fs::remove_dir_all(&default_run_dir) {
+        debug!(
+            "Failed to remove the run dir {}: {}",
+            default_run_dir.display(),
+            e
+        );
+    }
+}
+
+/// Only the sapporo-service of this invocation (`sapporo_service_name`) is looked up.
+pub fn check_wes_running(docker_host: &Url) -> Result<bool> {
+    let name = sapporo_service_name();
+    let output = output_with_timeout(
+        Command::new("docker").args([
+            "-H",
+            docker_host.as_str(),
+            "ps",
+            "-f",
+            &format!("name=^{}$", name),
+            "--format",
+            "{{.Names}}",
+        ]),
+        docker_timeout(),
+    )?;
+    if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines().any(|line| line.trim() == name))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        debug!("Stderr from docker:\n{}", stderr.trim());
+        match docker_error_hint(&stderr) {
+            Some(hint) => bail!("Failed to check sapporo-service status: {}", hint),
+            None => bail!("Failed to check sapporo-service status:\n{}", stderr),
+        }
+    }
+}
+
+pub fn sapporo_health_check(http: &remote::Http, wes_port: u16) -> Result<()> {
+    let wes_loc = Url::parse(&default_wes_location(wes_port))?;
+    let url = Url::parse(&format!(
+        "{}/service-info",
+        wes_loc.as_str().trim().trim_end_matches('/')
+    ))?;
+    let client = http.client()?;
+    let response = http.send(
+        client
+            .get(url.as_str())
+            .header(reqwest::header::ACCEPT, "application/json"),
+    )?;
+    ensure!(
+        response.status().is_success(),
+        "Failed to get service-info with status: {} from {}",
+        response.status(),
+        url.as_str()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_run_dir() -> Result<()> {
+        let run_dir =
+            std_env::temp_dir().join(format!("gh-trs-clean-run-dir-{}", std::process::id()));
+        for (run_id, state) in [
+            ("aa0001", Some("COMPLETE")),
+            ("aa0002", Some("RUNNING")),
+            ("bb0001", Some("EXECUTOR_ERROR")),
+            ("bb0002", None),
+        ] {
+            let run = run_dir.join(&run_id[..2]).join(run_id);
+            fs::create_dir_all(&run)?;
+            if let Some(state) = state {
+                fs::write(run.join("state.txt"), format!("{}\n", state))?;
+            }
+        }
+
+        // Nothing is old enough.
+        assert_eq!(clean_run_dir(&run_dir, time::Duration::from_secs(3600))?, 0);
+        assert_eq!(clean_run_dir(&run_dir, time::Duration::from_secs(0))?, 2);
+        assert!(!run_dir.join("aa/aa0001").exists());
+        assert!(run_dir.join("aa/aa0002").exists());
+        assert!(!run_dir.join("bb/bb0001").exists());
+        assert!(run_dir.join("bb/bb0002").exists());
+
+        fs::remove_dir_all(&run_dir)?;
+        assert_eq!(clean_run_dir(&run_dir, time::Duration::from_secs(0))?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_wes() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let docker_host = Url::parse("unix:///var/run/docker.sock")?;
+        assert!(start_wes(&http, &docker_host, free_port()?, &[], &None).is_ok());
+        stop_wes(&docker_host, &None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_wes() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let docker_host = Url::parse("unix:///var/run/docker.sock")?;
+        start_wes(&http, &docker_host, free_port()?, &[], &None)?;
+        assert!(stop_wes(&docker_host, &None).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_wes_running() -> Result<()> {
+        let http = remote::Http::new(&Default::default(), None, false)?;
+        let docker_host = Url::parse("unix:///var/run/docker.sock")?;
+        start_wes(&http, &docker_host, free_port()?, &[], &None)?;
+        assert!(check_wes_running(&docker_host)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_with_timeout() -> Result<()> {
+        let output = output_with_timeout(
+            Command::new("echo").arg("hello"),
+            time::Duration::from_secs(5),
+        )?;
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+
+        let result = output_with_timeout(
+            Command::new("sleep").arg("10"),
+            time::Duration::from_secs(1),
+        );
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("did not finish within 1 seconds"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_wes_location() {
+        if inside_docker_container() {
+            assert_eq!(
+                default_wes_location(8080),
+                format!("http://gh-trs-sapporo-service-{}:1122", std::process::id())
+            );
+        } else {
+            assert_eq!(default_wes_location(8080), "http://localhost:8080");
+        }
+    }
+
+    #[test]
+    fn test_free_port() -> Result<()> {
+        let port = free_port()?;
+        assert_ne!(port, 0);
+        assert!(TcpListener::bind(("127.0.0.1", port)).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_docker_error_hint() {
+        assert!(docker_error_hint(
+            "docker: Error response from daemon: driver failed programming external connectivity on endpoint gh-trs-sapporo-service: Bind for 0.0.0.0:1122 failed: port is already allocated."
+        )
+        .unwrap()
+        .contains("The port is already in use"));
+        assert!(docker_error_hint(
+            "docker: Cannot connect to the Docker daemon at unix:///var/run/invalid. Is the docker daemon running?"
+        )
+        .unwrap()
+        .contains("Cannot connect to the Docker daemon"));
+        assert!(docker_error_hint(
+            "docker: Error response from daemon: manifest for ghcr.io/sapporo-wes/sapporo-service:x not found: manifest unknown"
+        )
+        .unwrap()
+        .contains("Failed to pull"));
+        assert!(docker_error_hint("unexpected error").is_none());
+    }
+
+    #[test]
+    fn test_check_wes_running_with_invalid_docker_host() -> Result<()> {
+        let docker_host = Url::parse("unix:///var/run/invalid")?;
+        let result = check_wes_running(&docker_host);
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Please make sure that docker is running"));
+        Ok(())
+    }
+}